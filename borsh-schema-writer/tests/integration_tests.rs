@@ -1,3 +1,5 @@
+#![allow(clippy::collapsible_match, clippy::assertions_on_constants)]
+
 use std::fs::File;
 use std::io::BufReader;
 use borsh::{BorshDeserialize, BorshSerialize, BorshSchema};
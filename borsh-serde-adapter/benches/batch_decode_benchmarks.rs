@@ -0,0 +1,61 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::batch_decode::{decode_batch, decode_framed_batch};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: [u8; 32],
+    lamports: u64,
+    label: String,
+    positions: Vec<Position>,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Position {
+    market: u16,
+    size: i64,
+    open: bool,
+}
+
+fn account(seed: u64) -> Account {
+    Account {
+        owner: [seed as u8; 32],
+        lamports: seed * 1_000,
+        label: format!("account {seed}"),
+        positions: (0..seed % 16)
+            .map(|market| Position {
+                market: market as u16,
+                size: seed as i64 - 100,
+                open: market % 2 == 0,
+            })
+            .collect(),
+    }
+}
+
+fn batch_benchmarks(c: &mut Criterion) {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let records: Vec<Vec<u8>> = (0..10_000).map(|seed| to_vec(&account(seed)).unwrap()).collect();
+    let framed: Vec<u8> = records
+        .iter()
+        .flat_map(|record| (record.len() as u32).to_le_bytes().into_iter().chain(record.iter().copied()))
+        .collect();
+
+    let mut group = c.benchmark_group("decode_10000_accounts");
+    group.sample_size(20);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            black_box(&records)
+                .iter()
+                .map(|record| deserialize_from_schema(&mut record.as_slice(), &container))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("parallel", |b| b.iter(|| decode_batch(black_box(&records), &container)));
+    group.bench_function("parallel_framed", |b| b.iter(|| decode_framed_batch(black_box(&framed), &container).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, batch_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,135 @@
+#![recursion_limit = "256"]
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_derive::Serialize;
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub struct Person {
+    first_name: String,
+    last_name: String,
+}
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub enum TestEnum {
+    One(u8),
+    Two(u8),
+    Three(u8),
+}
+
+/// The AllTypes fixture of the integration tests without u128 and i128, which the serializer only accepts as strings
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub struct AllTypes {
+    type_u8: u8,
+    type_u16: u16,
+    type_u32: u32,
+    type_u64: u64,
+    type_i8: i8,
+    type_i16: i16,
+    type_i32: i32,
+    type_i64: i64,
+    type_f32: f32,
+    type_f64: f64,
+    type_string: String,
+    type_bool: bool,
+    type_array: [u8; 3],
+    type_sequence: Vec<String>,
+    type_tuple: (u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, String, bool),
+    type_enum: TestEnum,
+    type_struct: Person,
+}
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub struct Balances {
+    owner: String,
+    amounts: Vec<u64>,
+}
+
+fn all_types() -> AllTypes {
+    AllTypes {
+        type_u8: u8::MAX,
+        type_u16: u16::MAX,
+        type_u32: u32::MAX,
+        type_u64: u64::MAX,
+        type_i8: i8::MIN,
+        type_i16: i16::MIN,
+        type_i32: i32::MIN,
+        type_i64: i64::MIN,
+        type_f32: f32::MIN,
+        type_f64: f64::MIN,
+        type_string: "abc".to_string(),
+        type_bool: false,
+        type_array: [b'a', b'b', b'c'],
+        type_sequence: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        type_tuple: (u8::MAX, u16::MAX, u32::MAX, u64::MAX, i8::MIN, i16::MIN, i32::MIN, i64::MIN, f32::MAX, f64::MAX, "xyz".to_string(), true),
+        type_enum: TestEnum::One(1),
+        type_struct: Person {
+            first_name: "John".to_string(),
+            last_name: "Doe".to_string(),
+        },
+    }
+}
+
+fn decode_benchmarks(c: &mut Criterion) {
+    let container: BorshSchemaContainer = schema_container_of::<AllTypes>();
+    let plan = SchemaPlan::compile(&container);
+    let all_types_ser = to_vec(&all_types()).expect("Error trying to serialize AllTypes");
+
+    let mut group = c.benchmark_group("decode_all_types");
+    group.bench_function("schema", |b| {
+        b.iter(|| deserialize_from_schema(&mut black_box(all_types_ser.as_slice()), &container).unwrap())
+    });
+    group.bench_function("plan", |b| b.iter(|| plan.deserialize(&mut black_box(all_types_ser.as_slice())).unwrap()));
+    group.finish();
+
+    // Sequences of fixed size primitives are where the precomputed sizes pay off
+    let container: BorshSchemaContainer = schema_container_of::<Balances>();
+    let plan = SchemaPlan::compile(&container);
+    let balances = Balances {
+        owner: "alice".to_string(),
+        amounts: (0..1024).collect(),
+    };
+    let balances_ser = to_vec(&balances).expect("Error trying to serialize Balances");
+
+    let mut group = c.benchmark_group("decode_balances");
+    group.bench_function("schema", |b| {
+        b.iter(|| deserialize_from_schema(&mut black_box(balances_ser.as_slice()), &container).unwrap())
+    });
+    group.bench_function("plan", |b| b.iter(|| plan.deserialize(&mut black_box(balances_ser.as_slice())).unwrap()));
+    group.finish();
+}
+
+fn encode_benchmarks(c: &mut Criterion) {
+    let container: BorshSchemaContainer = schema_container_of::<AllTypes>();
+    let plan = SchemaPlan::compile(&container);
+    let all_types_value = serde_json::to_value(all_types()).expect("Error serializing all_types");
+
+    let mut group = c.benchmark_group("encode_all_types");
+    group.bench_function("schema", |b| {
+        b.iter(|| {
+            let mut writer = Vec::new();
+            serialize_serde_json_to_borsh(&mut writer, black_box(&all_types_value), &container).unwrap();
+            writer
+        })
+    });
+    group.bench_function("plan", |b| {
+        b.iter(|| {
+            let mut writer = Vec::new();
+            plan.serialize(&mut writer, black_box(&all_types_value)).unwrap();
+            writer
+        })
+    });
+    group.finish();
+}
+
+fn compile_benchmarks(c: &mut Criterion) {
+    let container: BorshSchemaContainer = schema_container_of::<AllTypes>();
+    c.bench_function("compile_all_types", |b| b.iter(|| SchemaPlan::compile(black_box(&container))));
+}
+
+criterion_group!(benches, decode_benchmarks, encode_benchmarks, compile_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,89 @@
+#![no_main]
+
+use borsh::schema::BorshSchemaContainer;
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::hexdump::Hexdump;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use libfuzzer_sys::fuzz_target;
+
+/// Whether the bytes hold an infinite f32 or f64, which decodes to null as JSON has no number for it
+fn has_infinite_float(bytes: &[u8], container: &BorshSchemaContainer) -> bool {
+    Hexdump::annotate(bytes, container).spans.iter().any(|span| {
+        let float = &bytes[span.start..span.end];
+        match span.declaration.as_str() {
+            "f32" => f32::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            "f64" => f64::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            _ => false,
+        }
+    })
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Person {
+    first_name: String,
+    last_name: String,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum TestEnum {
+    One(u8),
+    Two(u8),
+    Three(u8),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct AllTypes {
+    type_u8: u8,
+    type_u16: u16,
+    type_u32: u32,
+    type_u64: u64,
+    type_u128: u128,
+    type_i8: i8,
+    type_i16: i16,
+    type_i32: i32,
+    type_i64: i64,
+    type_i128: i128,
+    type_f32: f32,
+    type_f64: f64,
+    type_string: String,
+    type_bool: bool,
+    type_array: [u8; 3],
+    type_sequence: Vec<String>,
+    type_tuple: (u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64, String, bool),
+    type_enum: TestEnum,
+    type_struct: Person,
+}
+
+// Decodes arbitrary bytes as AllTypes with the adapter and with the derived decoder. Both must accept or reject the
+// same inputs and read the same bytes, and whatever is accepted must serialize back to the bytes that were read. Values
+// with infinite floats must be refused instead, as the floats decode to null.
+fuzz_target!(|data: &[u8]| {
+    let container = schema_container_of::<AllTypes>();
+
+    let mut derived_buffer = data;
+    let derived = AllTypes::deserialize(&mut derived_buffer);
+    let mut buffer = data;
+    let result = deserialize_from_schema(&mut buffer, &container);
+    assert_eq!(result.is_ok(), derived.is_ok(), "adapter gave {result:?}");
+
+    let (Ok(derived), Ok(value)) = (derived, result) else {
+        return;
+    };
+    assert_eq!(buffer.len(), derived_buffer.len());
+    let consumed = &data[..data.len() - buffer.len()];
+    assert_eq!(to_vec(&derived).unwrap(), consumed);
+
+    let plan = SchemaPlan::compile(&container);
+    assert_eq!(plan.deserialize(&mut &consumed[..]).unwrap(), value);
+
+    let mut writer = Vec::new();
+    let serialized = serialize_serde_json_to_borsh(&mut writer, &value, &container);
+    if has_infinite_float(consumed, &container) {
+        assert!(serialized.is_err(), "{value} was serialized");
+        return;
+    }
+    serialized.unwrap();
+    assert_eq!(writer, consumed);
+});
@@ -0,0 +1,54 @@
+#![no_main]
+
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::hexdump::Hexdump;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use borsh_serde_adapter::value_generator::random_schema;
+use libfuzzer_sys::fuzz_target;
+
+/// Whether the bytes hold an infinite f32 or f64, which decodes to null as JSON has no number for it
+fn has_infinite_float(bytes: &[u8], container: &BorshSchemaContainer) -> bool {
+    Hexdump::annotate(bytes, container).spans.iter().any(|span| {
+        let float = &bytes[span.start..span.end];
+        match span.declaration.as_str() {
+            "f32" => f32::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            "f64" => f64::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            _ => false,
+        }
+    })
+}
+
+// The first 8 bytes pick a random schema and the rest are decoded with it. Decoding must never panic, the adapter and
+// the compiled plan must agree, and whatever is accepted must serialize back to the bytes that were read. Values with
+// infinite floats must be refused instead, as the floats decode to null.
+fuzz_target!(|data: &[u8]| {
+    let Some((seed, bytes)) = data.split_first_chunk::<8>() else {
+        return;
+    };
+    let container = random_schema(u64::from_le_bytes(*seed), 4);
+    let plan = SchemaPlan::compile(&container);
+
+    let mut buffer = bytes;
+    let result = deserialize_from_schema(&mut buffer, &container);
+    let mut plan_buffer = bytes;
+    let plan_result = plan.deserialize(&mut plan_buffer);
+    assert_eq!(result.is_ok(), plan_result.is_ok(), "adapter gave {result:?}, plan gave {plan_result:?}");
+
+    let Ok(value) = result else {
+        return;
+    };
+    assert_eq!(plan_result.unwrap(), value);
+    assert_eq!(buffer.len(), plan_buffer.len());
+    let consumed = &bytes[..bytes.len() - buffer.len()];
+
+    let mut writer = Vec::new();
+    let serialized = serialize_serde_json_to_borsh(&mut writer, &value, &container);
+    if has_infinite_float(consumed, &container) {
+        assert!(serialized.is_err(), "{value} was serialized");
+        return;
+    }
+    serialized.unwrap();
+    assert_eq!(writer, consumed);
+});
@@ -0,0 +1,510 @@
+use std::collections::{BTreeMap, HashMap};
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use heck::ToSnakeCase;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::borsh_schema_util::{definition_children, hex, is_supported_primitive};
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::errors::IdlError;
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// The length of the discriminator Anchor puts before account data and instruction arguments
+pub const DISCRIMINATOR_LENGTH: usize = 8;
+
+/// The declaration of public keys in schemas made from IDLs, defined as `[u8; 32]`
+pub const PUBKEY_DECLARATION: &str = "Pubkey";
+
+/// The accounts, types and instructions of an Anchor IDL. Both the current IDL format and the legacy one from before
+/// Anchor 0.30 are read. Types are converted to schemas as they are asked for, so a type that can't be decoded with borsh,
+/// such as a zero copy account, only fails when it is used.
+///
+/// ```ignore
+/// let idl = AnchorIdl::from_json(&serde_json::from_str(&idl_json)?)?;
+/// let (account, value) = idl.decode_account(&account_data)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnchorIdl {
+    types: HashMap<String, Value>,
+    accounts: Vec<(String, [u8; DISCRIMINATOR_LENGTH])>,
+    instructions: Vec<IdlInstruction>,
+}
+
+#[derive(Debug, Clone)]
+struct IdlInstruction {
+    name: String,
+    discriminator: [u8; DISCRIMINATOR_LENGTH],
+    args: Vec<(String, Value)>,
+}
+
+impl AnchorIdl {
+    pub fn from_json(idl: &Value) -> Result<AnchorIdl, IdlError> {
+        let mut types = HashMap::new();
+        // Legacy IDLs define account types with the accounts rather than with the other types
+        for type_definition in list(idl, "types")?.iter().chain(list(idl, "accounts")?.iter().filter(|account| account.get("type").is_some())) {
+            types.insert(name_of(type_definition)?.to_string(), type_definition.clone());
+        }
+
+        let accounts = list(idl, "accounts")?
+            .iter()
+            .map(|account| {
+                let name = name_of(account)?;
+                let discriminator = match account.get("discriminator") {
+                    Some(discriminator) => parse_discriminator(discriminator, name)?,
+                    None => account_discriminator(name),
+                };
+                Ok((name.to_string(), discriminator))
+            })
+            .collect::<Result<Vec<_>, IdlError>>()?;
+
+        let instructions = list(idl, "instructions")?
+            .iter()
+            .map(|instruction| {
+                let name = name_of(instruction)?;
+                let discriminator = match instruction.get("discriminator") {
+                    Some(discriminator) => parse_discriminator(discriminator, name)?,
+                    None => instruction_discriminator(name),
+                };
+                let args = list(instruction, "args")?
+                    .iter()
+                    .map(|arg| Ok((name_of(arg)?.to_string(), type_of(arg)?.clone())))
+                    .collect::<Result<Vec<_>, IdlError>>()?;
+                Ok(IdlInstruction {
+                    name: name.to_string(),
+                    discriminator,
+                    args,
+                })
+            })
+            .collect::<Result<Vec<_>, IdlError>>()?;
+
+        Ok(AnchorIdl { types, accounts, instructions })
+    }
+
+    pub fn account_names(&self) -> impl Iterator<Item = &str> {
+        self.accounts.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn instruction_names(&self) -> impl Iterator<Item = &str> {
+        self.instructions.iter().map(|instruction| instruction.name.as_str())
+    }
+
+    pub fn account_discriminator(&self, name: &str) -> Option<[u8; DISCRIMINATOR_LENGTH]> {
+        self.accounts.iter().find(|(account, _)| account == name).map(|(_, discriminator)| *discriminator)
+    }
+
+    pub fn instruction_discriminator(&self, name: &str) -> Option<[u8; DISCRIMINATOR_LENGTH]> {
+        self.instruction(name).ok().map(|instruction| instruction.discriminator)
+    }
+
+    /// Returns the schema of an account's data after its discriminator
+    pub fn account_schema(&self, name: &str) -> Result<BorshSchemaContainer, IdlError> {
+        if self.account_discriminator(name).is_none() {
+            return Err(IdlError::AccountNotFound(name.to_string()));
+        }
+        self.type_schema(name)
+    }
+
+    /// Returns the schema of a type defined in the IDL
+    pub fn type_schema(&self, name: &str) -> Result<BorshSchemaContainer, IdlError> {
+        let mut converter = TypeConverter::new(&self.types);
+        let declaration = converter.defined(name)?;
+        Ok(converter.container(declaration))
+    }
+
+    /// Returns the schema of an instruction's arguments after its discriminator, a struct with a field per argument
+    /// declared with the name of the instruction
+    pub fn instruction_schema(&self, name: &str) -> Result<BorshSchemaContainer, IdlError> {
+        let instruction = self.instruction(name)?;
+        let mut converter = TypeConverter::new(&self.types);
+        let fields = instruction
+            .args
+            .iter()
+            .map(|(arg, idl_type)| Ok((arg.clone(), converter.declaration(idl_type)?)))
+            .collect::<Result<Vec<_>, IdlError>>()?;
+        converter.definitions.insert(name.to_string(), Definition::Struct { fields: named_fields(fields) });
+        Ok(converter.container(name.to_string()))
+    }
+
+    /// Finds the account by the discriminator at the start of data and decodes the rest of data, which may be longer
+    /// than the account's value. Returns the name of the account and its value.
+    pub fn decode_account(&self, data: &[u8]) -> Result<(String, Value), IdlError> {
+        let (discriminator, mut rest) = split_discriminator(data)?;
+        let (name, _) = self
+            .accounts
+            .iter()
+            .find(|(_, account_discriminator)| *account_discriminator == discriminator)
+            .ok_or_else(|| IdlError::UnknownDiscriminator(hex(&discriminator)))?;
+        let value = deserialize_from_schema(&mut rest, &self.account_schema(name)?)?;
+        Ok((name.clone(), value))
+    }
+
+    /// Serializes an account's value, with its discriminator first
+    pub fn encode_account(&self, name: &str, value: &Value) -> Result<Vec<u8>, IdlError> {
+        let discriminator = self.account_discriminator(name).ok_or_else(|| IdlError::AccountNotFound(name.to_string()))?;
+        encode(discriminator, value, &self.account_schema(name)?)
+    }
+
+    /// Finds the instruction by the discriminator at the start of data and decodes its arguments. Returns the name of
+    /// the instruction and an object with its arguments.
+    pub fn decode_instruction(&self, data: &[u8]) -> Result<(String, Value), IdlError> {
+        let (discriminator, mut rest) = split_discriminator(data)?;
+        let instruction = self
+            .instructions
+            .iter()
+            .find(|instruction| instruction.discriminator == discriminator)
+            .ok_or_else(|| IdlError::UnknownDiscriminator(hex(&discriminator)))?;
+        let value = deserialize_from_schema(&mut rest, &self.instruction_schema(&instruction.name)?)?;
+        Ok((instruction.name.clone(), value))
+    }
+
+    /// Serializes an instruction's arguments, given as an object, with its discriminator first
+    pub fn encode_instruction(&self, name: &str, args: &Value) -> Result<Vec<u8>, IdlError> {
+        encode(self.instruction(name)?.discriminator, args, &self.instruction_schema(name)?)
+    }
+
+    fn instruction(&self, name: &str) -> Result<&IdlInstruction, IdlError> {
+        self.instructions
+            .iter()
+            .find(|instruction| instruction.name == name)
+            .ok_or_else(|| IdlError::InstructionNotFound(name.to_string()))
+    }
+}
+
+/// The discriminator Anchor gives an account that doesn't declare one: the start of the SHA-256 of `account:<name>`
+pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_LENGTH] {
+    discriminator(&format!("account:{name}"))
+}
+
+/// The discriminator Anchor gives an instruction that doesn't declare one: the start of the SHA-256 of
+/// `global:<name>`, with the name in snake case as legacy IDLs give it in camel case. The conversion is heck's, as
+/// in Anchor, so acronyms are one word: `mintNFT` is `mint_nft`
+pub fn instruction_discriminator(name: &str) -> [u8; DISCRIMINATOR_LENGTH] {
+    discriminator(&format!("global:{}", name.to_snake_case()))
+}
+
+/// Returns IDL type definitions, as in the `types` of an IDL, for the structs and enums of a schema. Vecs, arrays,
+/// Options and Pubkeys are written as the IDL's own types, and the structs of enum variants as the variants' fields.
+/// Tuples, enums with explicit discriminants and widths Anchor doesn't use have no IDL equivalent and are an error.
+pub fn schema_to_idl_types(schema: &BorshSchemaContainer) -> Result<Vec<Value>, IdlError> {
+    let mut variant_structs = Vec::new();
+    for (_, definition) in schema.definitions() {
+        if let Definition::Enum { variants, .. } = definition {
+            variant_structs.extend(variants.iter().map(|(_, _, variant)| variant));
+        }
+    }
+
+    let mut types = Vec::new();
+    for (declaration, definition) in schema.definitions() {
+        if variant_structs.contains(&declaration) || declaration == PUBKEY_DECLARATION || is_supported_primitive(declaration) {
+            continue;
+        }
+        let type_definition = match definition {
+            Definition::Struct { fields } => json!({"kind": "struct", "fields": idl_fields(schema, fields)?}),
+            Definition::Enum { variants, .. } if is_option(variants) => continue,
+            Definition::Enum { tag_width: 1, variants } => {
+                let variants = variants
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (discriminant, name, variant))| {
+                        if *discriminant != index as i64 {
+                            return Err(IdlError::Unsupported(declaration.clone(), "IDL enums have no explicit discriminants".to_string()));
+                        }
+                        let fields = match schema.get_definition(variant) {
+                            _ if variant == "()" => None,
+                            Some(Definition::Struct { fields }) if !is_supported_primitive(variant) => idl_fields(schema, fields)?,
+                            _ => Some(json!([idl_type(schema, variant)?])),
+                        };
+                        Ok(match fields {
+                            Some(fields) => json!({"name": name, "fields": fields}),
+                            None => json!({"name": name}),
+                        })
+                    })
+                    .collect::<Result<Vec<Value>, IdlError>>()?;
+                json!({"kind": "enum", "variants": variants})
+            }
+            Definition::Enum { .. } => return Err(IdlError::Unsupported(declaration.clone(), "IDL enums have a 1 byte tag".to_string())),
+            Definition::Tuple { .. } => return Err(IdlError::Unsupported(declaration.clone(), "IDLs have no tuple types".to_string())),
+            _ => continue,
+        };
+        let mut type_definition = json!({"name": declaration, "type": type_definition});
+        if type_definition["type"]["fields"].is_null() {
+            type_definition["type"].as_object_mut().expect("Built as an object").remove("fields");
+        }
+        types.push(type_definition);
+    }
+    Ok(types)
+}
+
+fn idl_fields(schema: &BorshSchemaContainer, fields: &Fields) -> Result<Option<Value>, IdlError> {
+    Ok(match fields {
+        Fields::NamedFields(fields) => Some(
+            fields
+                .iter()
+                .map(|(name, field)| Ok(json!({"name": name, "type": idl_type(schema, field)?})))
+                .collect::<Result<Value, IdlError>>()?,
+        ),
+        Fields::UnnamedFields(fields) => Some(fields.iter().map(|field| idl_type(schema, field)).collect::<Result<Value, IdlError>>()?),
+        Fields::Empty => None,
+    })
+}
+
+/// Returns the IDL type of a declaration of the schema
+fn idl_type(schema: &BorshSchemaContainer, declaration: &Declaration) -> Result<Value, IdlError> {
+    let unsupported = |reason: &str| Err(IdlError::Unsupported(declaration.clone(), reason.to_string()));
+    if declaration == PUBKEY_DECLARATION {
+        return Ok("pubkey".into());
+    }
+    if is_supported_primitive(declaration) {
+        return match declaration.as_str() {
+            "String" => Ok("string".into()),
+            "()" => unsupported("IDLs have no unit type outside of enum variants"),
+            primitive => Ok(primitive.into()),
+        };
+    }
+    match schema.get_definition(declaration) {
+        Some(Definition::Sequence { length_width: 0, length_range, elements }) => Ok(json!({"array": [idl_type(schema, elements)?, length_range.end()]})),
+        Some(Definition::Sequence { length_width: 4, elements, .. }) if elements == "u8" => Ok("bytes".into()),
+        Some(Definition::Sequence { length_width: 4, elements, .. }) => Ok(json!({"vec": idl_type(schema, elements)?})),
+        Some(Definition::Sequence { .. }) => unsupported("IDL vectors have a 4 byte length"),
+        Some(Definition::Enum { tag_width, variants }) if is_option(variants) => match tag_width {
+            1 => Ok(json!({"option": idl_type(schema, &variants[1].2)?})),
+            4 => Ok(json!({"coption": idl_type(schema, &variants[1].2)?})),
+            _ => unsupported("IDL options have a 1 or 4 byte tag"),
+        },
+        Some(Definition::Enum { tag_width: 1, .. } | Definition::Struct { .. }) => Ok(json!({"defined": {"name": declaration}})),
+        Some(Definition::Enum { .. }) => unsupported("IDL enums have a 1 byte tag"),
+        Some(Definition::Tuple { .. }) => unsupported("IDLs have no tuple types"),
+        Some(Definition::Primitive(_)) | None => unsupported("the type is not supported"),
+    }
+}
+
+fn is_option(variants: &[(i64, String, Declaration)]) -> bool {
+    matches!(variants, [(0, none, unit), (1, some, _)] if none == "None" && some == "Some" && unit == "()")
+}
+
+/// Converts IDL types to definitions, which are named as the BorshSchema derive would name them: the structs of enum
+/// variants are `<Enum>__<Variant>`
+struct TypeConverter<'a> {
+    types: &'a HashMap<String, Value>,
+    definitions: BTreeMap<Declaration, Definition>,
+    /// Aliases being resolved, to catch aliases of themselves
+    aliasing: Vec<String>,
+}
+
+impl<'a> TypeConverter<'a> {
+    fn new(types: &'a HashMap<String, Value>) -> TypeConverter<'a> {
+        TypeConverter {
+            types,
+            definitions: BTreeMap::new(),
+            aliasing: Vec::new(),
+        }
+    }
+
+    /// Returns a container of the declaration with only the definitions it uses
+    fn container(mut self, declaration: Declaration) -> BorshSchemaContainer {
+        let mut used = BTreeMap::new();
+        let mut pending = vec![declaration.clone()];
+        while let Some(next) = pending.pop() {
+            if let Some(definition) = self.definitions.remove(&next) {
+                pending.extend(definition_children(&definition).into_iter().cloned());
+                used.insert(next, definition);
+            }
+        }
+        BorshSchemaContainer::new(declaration, used)
+    }
+
+    fn declaration(&mut self, idl_type: &Value) -> Result<Declaration, IdlError> {
+        let invalid = || IdlError::Invalid(format!("Unknown type {idl_type}"));
+        if let Some(primitive) = idl_type.as_str() {
+            return match primitive {
+                "bool" | "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "u128" | "i128" | "f32" | "f64" => Ok(primitive.to_string()),
+                "string" => Ok("String".to_string()),
+                "bytes" => Ok(self.vec("u8".to_string())),
+                "pubkey" | "publicKey" => Ok(self.define(PUBKEY_DECLARATION.to_string(), Definition::Sequence {
+                    length_width: 0,
+                    length_range: 32..=32,
+                    elements: "u8".to_string(),
+                })),
+                "u256" | "i256" => Err(IdlError::Unsupported(primitive.to_string(), "borsh has no 256 bit integers".to_string())),
+                _ => Err(invalid()),
+            };
+        }
+
+        let object = idl_type.as_object().filter(|object| object.len() == 1).ok_or_else(invalid)?;
+        let (kind, inner) = object.iter().next().expect("Checked to have one entry");
+        match kind.as_str() {
+            "vec" => {
+                let elements = self.declaration(inner)?;
+                Ok(self.vec(elements))
+            }
+            "option" => self.option("Option", 1, inner),
+            "coption" => self.option("COption", 4, inner),
+            "array" => {
+                let [elements, length] = inner.as_array().map(Vec::as_slice).unwrap_or_default() else {
+                    return Err(invalid());
+                };
+                let Some(length) = length.as_u64() else {
+                    return Err(IdlError::Unsupported(idl_type.to_string(), "arrays of generic length are not supported".to_string()));
+                };
+                let elements = self.declaration(elements)?;
+                Ok(self.define(format!("[{elements}; {length}]"), Definition::Sequence {
+                    length_width: 0,
+                    length_range: length..=length,
+                    elements,
+                }))
+            }
+            "defined" => {
+                if inner.get("generics").and_then(Value::as_array).is_some_and(|generics| !generics.is_empty()) {
+                    return Err(IdlError::Unsupported(idl_type.to_string(), "generic types are not supported".to_string()));
+                }
+                match inner.as_str().or_else(|| inner.get("name").and_then(Value::as_str)) {
+                    Some(name) => self.defined(name),
+                    None => Err(invalid()),
+                }
+            }
+            "generic" => Err(IdlError::Unsupported(idl_type.to_string(), "generic types are not supported".to_string())),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns the declaration of a type defined in the IDL, adding its definition
+    fn defined(&mut self, name: &str) -> Result<Declaration, IdlError> {
+        // A type being converted is already defined, so recursive types end here
+        if self.definitions.contains_key(name) {
+            return Ok(name.to_string());
+        }
+        let type_definition = self.types.get(name).ok_or_else(|| IdlError::UndefinedType(name.to_string()))?;
+        let unsupported = |reason: &str| Err(IdlError::Unsupported(name.to_string(), reason.to_string()));
+        match type_definition.get("serialization").and_then(Value::as_str) {
+            None | Some("borsh") => {}
+            Some(serialization) => return unsupported(&format!("{serialization} serialization is not borsh")),
+        }
+        if type_definition.get("generics").and_then(Value::as_array).is_some_and(|generics| !generics.is_empty()) {
+            return unsupported("generic types are not supported");
+        }
+
+        let idl_type = type_of(type_definition)?;
+        match idl_type.get("kind").and_then(Value::as_str) {
+            Some("struct") => {
+                self.definitions.insert(name.to_string(), Definition::Struct { fields: Fields::Empty });
+                let fields = self.fields(idl_type.get("fields"))?;
+                Ok(self.define(name.to_string(), Definition::Struct { fields }))
+            }
+            Some("enum") => {
+                self.definitions.insert(name.to_string(), Definition::Enum { tag_width: 1, variants: Vec::new() });
+                let variants = idl_type
+                    .get("variants")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| IdlError::Invalid(format!("Enum {name} has no variants")))?
+                    .iter()
+                    .enumerate()
+                    .map(|(index, variant)| {
+                        let variant_name = name_of(variant)?;
+                        let fields = self.fields(variant.get("fields"))?;
+                        let declaration = self.define(format!("{name}__{variant_name}"), Definition::Struct { fields });
+                        Ok((index as i64, variant_name.to_string(), declaration))
+                    })
+                    .collect::<Result<Vec<_>, IdlError>>()?;
+                Ok(self.define(name.to_string(), Definition::Enum { tag_width: 1, variants }))
+            }
+            Some("type") | Some("alias") => {
+                if self.aliasing.iter().any(|alias| alias == name) {
+                    return Err(IdlError::Invalid(format!("Type {name} is an alias of itself")));
+                }
+                let alias = idl_type.get("alias").or_else(|| idl_type.get("value")).ok_or_else(|| IdlError::Invalid(format!("Alias {name} has no type")))?;
+                self.aliasing.push(name.to_string());
+                let declaration = self.declaration(alias);
+                self.aliasing.pop();
+                declaration
+            }
+            _ => Err(IdlError::Invalid(format!("Type {name} has an unknown kind"))),
+        }
+    }
+
+    /// Converts struct or variant fields, which are named if they are objects with a name and unnamed types otherwise
+    fn fields(&mut self, fields: Option<&Value>) -> Result<Fields, IdlError> {
+        let fields = match fields {
+            None | Some(Value::Null) => return Ok(Fields::Empty),
+            Some(fields) => fields.as_array().ok_or_else(|| IdlError::Invalid(format!("Fields {fields} are not a list")))?,
+        };
+        if fields.is_empty() {
+            return Ok(Fields::Empty);
+        }
+        if fields.iter().all(|field| field.get("name").is_some() && field.get("type").is_some()) {
+            let fields = fields
+                .iter()
+                .map(|field| Ok((name_of(field)?.to_string(), self.declaration(type_of(field)?)?)))
+                .collect::<Result<Vec<_>, IdlError>>()?;
+            return Ok(named_fields(fields));
+        }
+        Ok(Fields::UnnamedFields(fields.iter().map(|field| self.declaration(field)).collect::<Result<Vec<_>, IdlError>>()?))
+    }
+
+    fn vec(&mut self, elements: Declaration) -> Declaration {
+        self.define(format!("Vec<{elements}>"), Definition::Sequence {
+            length_width: Definition::DEFAULT_LENGTH_WIDTH,
+            length_range: Definition::DEFAULT_LENGTH_RANGE,
+            elements,
+        })
+    }
+
+    fn option(&mut self, name: &str, tag_width: u8, some: &Value) -> Result<Declaration, IdlError> {
+        let some = self.declaration(some)?;
+        let none = self.define("()".to_string(), Definition::Primitive(0));
+        Ok(self.define(format!("{name}<{some}>"), Definition::Enum {
+            tag_width,
+            variants: vec![(0, "None".to_string(), none), (1, "Some".to_string(), some)],
+        }))
+    }
+
+    fn define(&mut self, declaration: Declaration, definition: Definition) -> Declaration {
+        self.definitions.insert(declaration.clone(), definition);
+        declaration
+    }
+}
+
+fn named_fields(fields: Vec<(String, Declaration)>) -> Fields {
+    match fields.is_empty() {
+        true => Fields::Empty,
+        false => Fields::NamedFields(fields),
+    }
+}
+
+fn encode(discriminator: [u8; DISCRIMINATOR_LENGTH], value: &Value, schema: &BorshSchemaContainer) -> Result<Vec<u8>, IdlError> {
+    let mut bytes = discriminator.to_vec();
+    serialize_serde_json_to_borsh(&mut bytes, value, schema).map_err(|error| IdlError::Encode(format!("{error:#}")))?;
+    Ok(bytes)
+}
+
+fn split_discriminator(data: &[u8]) -> Result<([u8; DISCRIMINATOR_LENGTH], &[u8]), IdlError> {
+    if data.len() < DISCRIMINATOR_LENGTH {
+        return Err(IdlError::MissingDiscriminator(data.len()));
+    }
+    let (discriminator, rest) = data.split_at(DISCRIMINATOR_LENGTH);
+    Ok((discriminator.try_into().expect("Split at the discriminator length"), rest))
+}
+
+fn discriminator(preimage: &str) -> [u8; DISCRIMINATOR_LENGTH] {
+    Sha256::digest(preimage.as_bytes())[..DISCRIMINATOR_LENGTH].try_into().expect("SHA-256 is longer than a discriminator")
+}
+
+fn parse_discriminator(discriminator: &Value, name: &str) -> Result<[u8; DISCRIMINATOR_LENGTH], IdlError> {
+    serde_json::from_value(discriminator.clone()).map_err(|_| IdlError::Invalid(format!("Discriminator of {name} is not 8 bytes")))
+}
+
+/// Returns a list in the IDL, which is empty if it isn't there
+fn list<'v>(value: &'v Value, key: &str) -> Result<&'v [Value], IdlError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(list) => list.as_array().map(Vec::as_slice).ok_or_else(|| IdlError::Invalid(format!("{key} is not a list"))),
+    }
+}
+
+fn name_of(value: &Value) -> Result<&str, IdlError> {
+    value.get("name").and_then(Value::as_str).ok_or_else(|| IdlError::Invalid(format!("{value} has no name")))
+}
+
+fn type_of(value: &Value) -> Result<&Value, IdlError> {
+    value.get("type").ok_or_else(|| IdlError::Invalid(format!("{value} has no type")))
+}
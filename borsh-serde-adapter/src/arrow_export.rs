@@ -0,0 +1,696 @@
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    ArrayRef, ArrowPrimitiveType, BinaryArray, BooleanArray, FixedSizeBinaryArray, FixedSizeListArray, ListArray, NullArray,
+    PrimitiveArray, RecordBatch, StringArray, StructArray, UnionArray,
+};
+use arrow_buffer::{BooleanBuffer, Buffer, NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Fields, Schema, SchemaRef, UnionFields, UnionMode};
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition};
+use borsh::BorshDeserialize;
+
+use crate::borsh_schema_util::{EncodedSizes, PrimitiveType};
+use crate::deserialize_adapter::read_width;
+use crate::errors::ArrowExportError;
+
+/// How enums other than Option are represented in Arrow. Options are always nullable values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnumEncoding {
+    /// A dense union with a child per variant, whose type ids are the indices of the variants
+    #[default]
+    DenseUnion,
+    /// A struct with the name of the active variant in a `variant` field, and a nullable field per variant holding its
+    /// contents. Parquet has no unions, so this is the encoding to use for batches written to Parquet.
+    Struct,
+}
+
+/// Options for mapping a schema to Arrow
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArrowOptions {
+    pub enum_encoding: EnumEncoding,
+}
+
+/// Returns the Arrow schema of record batches decoded from values of the schema. A struct with named fields gives a
+/// column per field, and any other declaration a single column named `value`.
+///
+/// Structs and tuples map to struct arrays, sequences to list arrays, Vec<u8> and [u8; N] to binary arrays, enums as
+/// given by options.enum_encoding and Options to nullable values. u128 and i128 map to decimal strings, as they do in
+/// JSON, and `()` and unit variants to null arrays. Recursive types have no Arrow equivalent and are an error.
+pub fn arrow_schema(schema: &BorshSchemaContainer, options: &ArrowOptions) -> Result<Schema, ArrowExportError> {
+    Ok(RecordBatchDecoder::new(schema, options)?.schema.as_ref().clone())
+}
+
+/// Decodes borsh serialized records into the columns of a record batch, without going through JSON
+///
+/// ```ignore
+/// let mut decoder = RecordBatchDecoder::new(&container, &ArrowOptions::default())?;
+/// for record in records {
+///     decoder.append(&record)?;
+/// }
+/// let batch = decoder.finish()?;
+/// ```
+pub struct RecordBatchDecoder {
+    schema: SchemaRef,
+    columns: Vec<Column>,
+    rows: usize,
+}
+
+impl RecordBatchDecoder {
+    pub fn new(schema: &BorshSchemaContainer, options: &ArrowOptions) -> Result<RecordBatchDecoder, ArrowExportError> {
+        let mut builder = ColumnBuilder {
+            schema,
+            sizes: EncodedSizes::new(schema),
+            options,
+            visiting: Vec::new(),
+        };
+        let declaration = schema.declaration();
+        let columns = match schema.get_definition(declaration) {
+            Some(Definition::Struct { fields: borsh::schema::Fields::NamedFields(fields) })
+                if !fields.is_empty() && PrimitiveType::from_declaration(declaration).is_none() =>
+            {
+                builder.visiting.push(declaration);
+                fields
+                    .iter()
+                    .map(|(name, field)| Ok((name.clone(), builder.column(field)?)))
+                    .collect::<Result<Vec<_>, ArrowExportError>>()?
+            }
+            _ => vec![("value".to_string(), builder.column(declaration)?)],
+        };
+
+        let fields: Vec<Field> = columns.iter().map(|(name, column)| column.field(name)).collect();
+        Ok(RecordBatchDecoder {
+            schema: Arc::new(Schema::new(fields)),
+            columns: columns.into_iter().map(|(_, column)| column).collect(),
+            rows: 0,
+        })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// The number of records appended since the last batch was finished
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Decodes a record and appends it to the batch. The record must be exactly one value of the schema. An invalid
+    /// record leaves the batch as it was.
+    pub fn append(&mut self, record: &[u8]) -> Result<(), ArrowExportError> {
+        let mut buffer = record;
+        let result = self.columns.iter_mut().try_for_each(|column| column.append(&mut buffer)).and_then(|_| match buffer.len() {
+            0 => Ok(()),
+            left => Err(Error::new(ErrorKind::InvalidData, format!("{left} bytes left over"))),
+        });
+        if let Err(error) = result {
+            self.columns.iter_mut().for_each(|column| column.truncate(self.rows));
+            return Err(ArrowExportError::InvalidRecord(self.rows, error));
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Returns the records appended so far as a record batch, and starts a new batch
+    pub fn finish(&mut self) -> Result<RecordBatch, ArrowExportError> {
+        self.rows = 0;
+        let arrays = self.columns.iter_mut().map(Column::finish).collect::<Result<Vec<_>, ArrowError>>()?;
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+}
+
+/// Decodes borsh serialized records of the schema into a record batch
+pub fn decode_record_batch<R: AsRef<[u8]>>(
+    schema: &BorshSchemaContainer,
+    records: impl IntoIterator<Item = R>,
+    options: &ArrowOptions,
+) -> Result<RecordBatch, ArrowExportError> {
+    let mut decoder = RecordBatchDecoder::new(schema, options)?;
+    for record in records {
+        decoder.append(record.as_ref())?;
+    }
+    decoder.finish()
+}
+
+/// Writes record batches to a Parquet file and returns the writer. Batches must use EnumEncoding::Struct if the schema
+/// has enums other than Option, since Parquet has no unions.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: std::io::Write + Send>(writer: W, schema: SchemaRef, batches: &[RecordBatch]) -> Result<W, ArrowExportError> {
+    // The Parquet writer panics on unions rather than failing
+    if schema.fields().iter().any(|field| contains_union(field.data_type())) {
+        return Err(ArrowExportError::Parquet("Parquet has no unions, use EnumEncoding::Struct".to_string()));
+    }
+    let parquet_error = |error: parquet::errors::ParquetError| ArrowExportError::Parquet(error.to_string());
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, schema, None).map_err(parquet_error)?;
+    for batch in batches {
+        writer.write(batch).map_err(parquet_error)?;
+    }
+    writer.into_inner().map_err(parquet_error)
+}
+
+#[cfg(feature = "parquet")]
+fn contains_union(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Union(..) => true,
+        DataType::Struct(fields) => fields.iter().any(|field| contains_union(field.data_type())),
+        DataType::List(field) | DataType::FixedSizeList(field, _) => contains_union(field.data_type()),
+        _ => false,
+    }
+}
+
+/// The tag of an Option wrapping a column's values
+#[derive(Debug, Clone)]
+struct OptionTag {
+    declaration: Declaration,
+    width: u8,
+    none: u64,
+    some: u64,
+}
+
+/// Values decoded so far for one Arrow array, along with what is needed to decode more
+struct Column {
+    declaration: Declaration,
+    data_type: DataType,
+    /// Options the values are wrapped in, outermost first. A None in any of them makes the value null.
+    options: Vec<OptionTag>,
+    validity: Vec<bool>,
+    kind: Kind,
+}
+
+enum Kind {
+    Null,
+    Bool(Vec<bool>),
+    /// Fixed width numbers, whose little-endian borsh encoding is also their Arrow layout
+    Fixed(PrimitiveType, Vec<u8>),
+    /// Strings, and u128 and i128 as decimal strings
+    Utf8 {
+        primitive: PrimitiveType,
+        offsets: Vec<i32>,
+        values: Vec<u8>,
+    },
+    Binary {
+        length_width: u8,
+        offsets: Vec<i32>,
+        values: Vec<u8>,
+    },
+    FixedBinary(usize, Vec<u8>),
+    List {
+        field: FieldRef,
+        length_width: u8,
+        element_size: usize,
+        offsets: Vec<i32>,
+        child: Box<Column>,
+    },
+    FixedList {
+        field: FieldRef,
+        size: usize,
+        child: Box<Column>,
+    },
+    Struct {
+        fields: Fields,
+        children: Vec<Column>,
+    },
+    Union {
+        fields: UnionFields,
+        tag_width: u8,
+        discriminants: Vec<u64>,
+        type_ids: Vec<i8>,
+        offsets: Vec<i32>,
+        children: Vec<Column>,
+    },
+    /// An enum as a struct with the name of the active variant followed by a field per variant with contents
+    VariantStruct {
+        fields: Fields,
+        tag_width: u8,
+        variants: Vec<(u64, String)>,
+        active: Vec<usize>,
+        /// None for variants without contents
+        children: Vec<Option<Column>>,
+    },
+}
+
+impl Column {
+    fn new(declaration: &Declaration, data_type: DataType, kind: Kind) -> Column {
+        Column {
+            declaration: declaration.clone(),
+            data_type,
+            options: Vec::new(),
+            validity: Vec::new(),
+            kind,
+        }
+    }
+
+    fn field(&self, name: &str) -> Field {
+        // Unions take their nulls from their children, so a union with a unit variant has nulls even if it isn't optional
+        let nullable = !self.options.is_empty() || matches!(self.data_type, DataType::Null | DataType::Union(..));
+        Field::new(name, self.data_type.clone(), nullable)
+    }
+
+    fn len(&self) -> usize {
+        self.validity.len()
+    }
+
+    fn append(&mut self, buffer: &mut &[u8]) -> std::io::Result<()> {
+        for tag in &self.options {
+            let discriminant = read_width(buffer, tag.width)?;
+            if discriminant == tag.none {
+                self.append_null();
+                return Ok(());
+            }
+            if discriminant != tag.some {
+                return Err(invalid_data(format!("Unknown discriminant {discriminant} for {}", tag.declaration)));
+            }
+        }
+        self.append_value(buffer)
+    }
+
+    fn append_value(&mut self, buffer: &mut &[u8]) -> std::io::Result<()> {
+        self.validity.push(true);
+        let declaration = &self.declaration;
+        match &mut self.kind {
+            Kind::Null => {}
+            Kind::Bool(values) => values.push(bool::deserialize(buffer).map_err(|_| invalid_value(declaration))?),
+            Kind::Fixed(primitive, values) => {
+                let bytes = take(buffer, primitive.fixed_size().unwrap_or_default(), declaration)?;
+                // Like borsh, refuse NaN
+                let nan = match primitive {
+                    PrimitiveType::F32 => f32::from_le_bytes(bytes.try_into().expect("Took 4 bytes")).is_nan(),
+                    PrimitiveType::F64 => f64::from_le_bytes(bytes.try_into().expect("Took 8 bytes")).is_nan(),
+                    _ => false,
+                };
+                if nan {
+                    return Err(invalid_value(declaration));
+                }
+                values.extend_from_slice(bytes);
+            }
+            Kind::Utf8 { primitive, offsets, values } => {
+                match primitive {
+                    PrimitiveType::U128 => values.extend(u128::deserialize(buffer).map_err(|_| unexpected_end(declaration))?.to_string().bytes()),
+                    PrimitiveType::I128 => values.extend(i128::deserialize(buffer).map_err(|_| unexpected_end(declaration))?.to_string().bytes()),
+                    _ => {
+                        let length = read_width(buffer, 4)? as usize;
+                        let bytes = take(buffer, length, declaration)?;
+                        std::str::from_utf8(bytes).map_err(|_| invalid_value(declaration))?;
+                        values.extend_from_slice(bytes);
+                    }
+                }
+                offsets.push(offset(values.len())?);
+            }
+            Kind::Binary { length_width, offsets, values } => {
+                let length = read_width(buffer, *length_width)?;
+                let bytes = take(buffer, usize::try_from(length).unwrap_or(usize::MAX), declaration)?;
+                values.extend_from_slice(bytes);
+                offsets.push(offset(values.len())?);
+            }
+            Kind::FixedBinary(size, values) => values.extend_from_slice(take(buffer, *size, declaration)?),
+            Kind::List { length_width, element_size, offsets, child, .. } => {
+                let length = read_width(buffer, *length_width)?;
+                if *element_size == 0 && length > 0 {
+                    return Err(invalid_data(format!("Sequence of zero sized type {} is not allowed", child.declaration)));
+                }
+                if length.saturating_mul(*element_size as u64) > buffer.len() as u64 {
+                    return Err(invalid_data(format!("Sequence of {length} {} needs more than the {} bytes remaining", child.declaration, buffer.len())));
+                }
+                for _ in 0..length {
+                    child.append(buffer)?;
+                }
+                offsets.push(offset(child.len())?);
+            }
+            Kind::FixedList { size, child, .. } => {
+                for _ in 0..*size {
+                    child.append(buffer)?;
+                }
+            }
+            Kind::Struct { children, .. } => {
+                for child in children {
+                    child.append(buffer)?;
+                }
+            }
+            Kind::Union { tag_width, discriminants, type_ids, offsets, children, .. } => {
+                let index = read_variant_index(buffer, *tag_width, discriminants.iter().copied(), declaration)?;
+                let child = &mut children[index];
+                offsets.push(offset(child.len())?);
+                type_ids.push(index as i8);
+                child.append(buffer)?;
+            }
+            Kind::VariantStruct { tag_width, variants, active, children, .. } => {
+                let index = read_variant_index(buffer, *tag_width, variants.iter().map(|(discriminant, _)| *discriminant), declaration)?;
+                active.push(index);
+                for (variant_index, child) in children.iter_mut().enumerate() {
+                    match child {
+                        Some(child) if variant_index == index => child.append(buffer)?,
+                        Some(child) => child.append_null(),
+                        None => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a null, along with the placeholder values Arrow needs in the children of a null
+    fn append_null(&mut self) {
+        self.validity.push(false);
+        match &mut self.kind {
+            Kind::Null => {}
+            Kind::Bool(values) => values.push(false),
+            Kind::Fixed(primitive, values) => values.resize(values.len() + primitive.fixed_size().unwrap_or_default(), 0),
+            Kind::Utf8 { offsets, .. } | Kind::Binary { offsets, .. } | Kind::List { offsets, .. } => {
+                offsets.push(*offsets.last().expect("Offsets start with 0"))
+            }
+            Kind::FixedBinary(size, values) => values.resize(values.len() + *size, 0),
+            Kind::FixedList { size, child, .. } => (0..*size).for_each(|_| child.append_null()),
+            Kind::Struct { children, .. } => children.iter_mut().for_each(Column::append_null),
+            // Unions have no nulls of their own, so a null of the first variant stands in
+            Kind::Union { type_ids, offsets, children, .. } => {
+                offsets.push(children[0].len() as i32);
+                type_ids.push(0);
+                children[0].append_null();
+            }
+            Kind::VariantStruct { active, children, .. } => {
+                active.push(0);
+                children.iter_mut().flatten().for_each(Column::append_null);
+            }
+        }
+    }
+
+    /// Drops the values after the first len, undoing a partly decoded record
+    fn truncate(&mut self, len: usize) {
+        self.validity.truncate(len);
+        match &mut self.kind {
+            Kind::Null => {}
+            Kind::Bool(values) => values.truncate(len),
+            Kind::Fixed(primitive, values) => values.truncate(len * primitive.fixed_size().unwrap_or_default()),
+            Kind::Utf8 { offsets, values, .. } | Kind::Binary { offsets, values, .. } => {
+                offsets.truncate(len + 1);
+                values.truncate(offsets[len] as usize);
+            }
+            Kind::FixedBinary(size, values) => values.truncate(len * *size),
+            Kind::List { offsets, child, .. } => {
+                offsets.truncate(len + 1);
+                child.truncate(offsets[len] as usize);
+            }
+            Kind::FixedList { size, child, .. } => child.truncate(len * *size),
+            Kind::Struct { children, .. } => children.iter_mut().for_each(|child| child.truncate(len)),
+            Kind::Union { type_ids, offsets, children, .. } => {
+                type_ids.truncate(len);
+                offsets.truncate(len);
+                for (index, child) in children.iter_mut().enumerate() {
+                    child.truncate(type_ids.iter().filter(|type_id| **type_id as usize == index).count());
+                }
+            }
+            Kind::VariantStruct { active, children, .. } => {
+                active.truncate(len);
+                children.iter_mut().flatten().for_each(|child| child.truncate(len));
+            }
+        }
+    }
+
+    /// Returns the values decoded so far as an array, and empties the column
+    fn finish(&mut self) -> Result<ArrayRef, ArrowError> {
+        let len = self.len();
+        let nulls = Some(NullBuffer::from(std::mem::take(&mut self.validity))).filter(|nulls| nulls.null_count() > 0);
+        let array: ArrayRef = match &mut self.kind {
+            Kind::Null => Arc::new(NullArray::new(len)),
+            Kind::Bool(values) => Arc::new(BooleanArray::new(BooleanBuffer::from(std::mem::take(values)), nulls)),
+            Kind::Fixed(primitive, values) => {
+                let values = std::mem::take(values);
+                match primitive {
+                    PrimitiveType::U8 => primitive_array::<UInt8Type>(&values, nulls),
+                    PrimitiveType::U16 => primitive_array::<UInt16Type>(&values, nulls),
+                    PrimitiveType::U32 => primitive_array::<UInt32Type>(&values, nulls),
+                    PrimitiveType::U64 => primitive_array::<UInt64Type>(&values, nulls),
+                    PrimitiveType::I8 => primitive_array::<Int8Type>(&values, nulls),
+                    PrimitiveType::I16 => primitive_array::<Int16Type>(&values, nulls),
+                    PrimitiveType::I32 => primitive_array::<Int32Type>(&values, nulls),
+                    PrimitiveType::I64 => primitive_array::<Int64Type>(&values, nulls),
+                    PrimitiveType::F32 => primitive_array::<Float32Type>(&values, nulls),
+                    _ => primitive_array::<Float64Type>(&values, nulls),
+                }
+            }
+            Kind::Utf8 { offsets, values, .. } => Arc::new(StringArray::try_new(take_offsets(offsets), Buffer::from_vec(std::mem::take(values)), nulls)?),
+            Kind::Binary { offsets, values, .. } => Arc::new(BinaryArray::try_new(take_offsets(offsets), Buffer::from_vec(std::mem::take(values)), nulls)?),
+            Kind::FixedBinary(size, values) => {
+                Arc::new(FixedSizeBinaryArray::try_new(*size as i32, Buffer::from_vec(std::mem::take(values)), nulls)?)
+            }
+            Kind::List { field, offsets, child, .. } => Arc::new(ListArray::try_new(field.clone(), take_offsets(offsets), child.finish()?, nulls)?),
+            Kind::FixedList { field, size, child } => Arc::new(FixedSizeListArray::try_new(field.clone(), *size as i32, child.finish()?, nulls)?),
+            Kind::Struct { fields, children } => {
+                let arrays = children.iter_mut().map(Column::finish).collect::<Result<Vec<_>, ArrowError>>()?;
+                Arc::new(StructArray::try_new(fields.clone(), arrays, nulls)?)
+            }
+            Kind::Union { fields, type_ids, offsets, children, .. } => {
+                let arrays = children.iter_mut().map(Column::finish).collect::<Result<Vec<_>, ArrowError>>()?;
+                let type_ids = ScalarBuffer::from(std::mem::take(type_ids));
+                Arc::new(UnionArray::try_new(fields.clone(), type_ids, Some(ScalarBuffer::from(std::mem::take(offsets))), arrays)?)
+            }
+            Kind::VariantStruct { fields, variants, active, children, .. } => {
+                let names = StringArray::from_iter_values(std::mem::take(active).into_iter().map(|index| &variants[index].1));
+                let mut arrays: Vec<ArrayRef> = vec![Arc::new(names)];
+                for child in children.iter_mut().flatten() {
+                    arrays.push(child.finish()?);
+                }
+                Arc::new(StructArray::try_new(fields.clone(), arrays, nulls)?)
+            }
+        };
+        Ok(array)
+    }
+}
+
+struct ColumnBuilder<'a> {
+    schema: &'a BorshSchemaContainer,
+    sizes: EncodedSizes<'a>,
+    options: &'a ArrowOptions,
+    visiting: Vec<&'a Declaration>,
+}
+
+impl<'a> ColumnBuilder<'a> {
+    fn column(&mut self, declaration: &'a Declaration) -> Result<Column, ArrowExportError> {
+        if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+            return Ok(primitive_column(declaration, primitive));
+        }
+        // Declarations without a definition are decoded as null from no bytes
+        let Some(definition) = self.schema.get_definition(declaration) else {
+            return Ok(Column::new(declaration, DataType::Null, Kind::Null));
+        };
+        if self.visiting.contains(&declaration) {
+            return Err(unsupported(declaration, "recursive types have no Arrow equivalent"));
+        }
+
+        self.visiting.push(declaration);
+        let column = self.definition_column(declaration, definition);
+        self.visiting.pop();
+        column
+    }
+
+    fn definition_column(&mut self, declaration: &'a Declaration, definition: &'a Definition) -> Result<Column, ArrowExportError> {
+        use borsh::schema::Fields as BorshFields;
+
+        match definition {
+            Definition::Primitive(_) => Err(unsupported(declaration, "the primitive is not supported")),
+            Definition::Sequence { length_width: 0, length_range, elements } => {
+                let size = usize::try_from(*length_range.end())
+                    .ok()
+                    .filter(|size| i32::try_from(*size).is_ok())
+                    .ok_or_else(|| unsupported(declaration, "the array is too long"))?;
+                if elements == "u8" {
+                    return Ok(Column::new(declaration, DataType::FixedSizeBinary(size as i32), Kind::FixedBinary(size, Vec::new())));
+                }
+                let child = self.column(elements)?;
+                let field = Arc::new(child.field("item"));
+                let data_type = DataType::FixedSizeList(field.clone(), size as i32);
+                Ok(Column::new(declaration, data_type, Kind::FixedList { field, size, child: Box::new(child) }))
+            }
+            Definition::Sequence { length_width, elements, .. } => {
+                if elements == "u8" {
+                    let kind = Kind::Binary {
+                        length_width: *length_width,
+                        offsets: vec![0],
+                        values: Vec::new(),
+                    };
+                    return Ok(Column::new(declaration, DataType::Binary, kind));
+                }
+                let child = self.column(elements)?;
+                let field = Arc::new(child.field("item"));
+                let kind = Kind::List {
+                    field: field.clone(),
+                    length_width: *length_width,
+                    element_size: self.sizes.min_size(elements),
+                    offsets: vec![0],
+                    child: Box::new(child),
+                };
+                Ok(Column::new(declaration, DataType::List(field), kind))
+            }
+            Definition::Tuple { elements } | Definition::Struct { fields: BorshFields::UnnamedFields(elements) } => {
+                self.struct_column(declaration, elements.iter().enumerate().map(|(index, element)| (index.to_string(), element)))
+            }
+            Definition::Struct { fields: BorshFields::NamedFields(fields) } => {
+                self.struct_column(declaration, fields.iter().map(|(name, field)| (name.clone(), field)))
+            }
+            Definition::Struct { fields: BorshFields::Empty } => Ok(Column::new(declaration, DataType::Null, Kind::Null)),
+            Definition::Enum { tag_width, variants } => {
+                if let [(none, none_name, none_declaration), (some, some_name, some_declaration)] = variants.as_slice() {
+                    if none_name == "None" && some_name == "Some" && none_declaration == "()" {
+                        let mut column = self.column(some_declaration)?;
+                        column.options.insert(0, OptionTag {
+                            declaration: declaration.clone(),
+                            width: *tag_width,
+                            none: *none as u64,
+                            some: *some as u64,
+                        });
+                        return Ok(column);
+                    }
+                }
+                if variants.is_empty() {
+                    return Err(unsupported(declaration, "enums without variants have no values"));
+                }
+                let children = variants
+                    .iter()
+                    .map(|(_, _, variant)| self.column(variant))
+                    .collect::<Result<Vec<Column>, ArrowExportError>>()?;
+                match self.options.enum_encoding {
+                    EnumEncoding::DenseUnion => {
+                        if variants.len() > i8::MAX as usize + 1 {
+                            return Err(unsupported(declaration, "unions have at most 128 variants"));
+                        }
+                        let fields = UnionFields::new(
+                            0..variants.len() as i8,
+                            variants.iter().zip(&children).map(|((_, name, _), child)| child.field(name)),
+                        );
+                        let kind = Kind::Union {
+                            fields: fields.clone(),
+                            tag_width: *tag_width,
+                            discriminants: variants.iter().map(|(discriminant, _, _)| *discriminant as u64).collect(),
+                            type_ids: Vec::new(),
+                            offsets: Vec::new(),
+                            children,
+                        };
+                        Ok(Column::new(declaration, DataType::Union(fields, UnionMode::Dense), kind))
+                    }
+                    EnumEncoding::Struct => {
+                        let mut fields = vec![Field::new("variant", DataType::Utf8, false)];
+                        let children: Vec<Option<Column>> = children
+                            .into_iter()
+                            .zip(variants)
+                            .map(|(child, (_, name, _))| {
+                                (child.data_type != DataType::Null).then(|| {
+                                    fields.push(Field::new(name, child.data_type.clone(), true));
+                                    child
+                                })
+                            })
+                            .collect();
+                        let fields = Fields::from(fields);
+                        let kind = Kind::VariantStruct {
+                            fields: fields.clone(),
+                            tag_width: *tag_width,
+                            variants: variants.iter().map(|(discriminant, name, _)| (*discriminant as u64, name.clone())).collect(),
+                            active: Vec::new(),
+                            children,
+                        };
+                        Ok(Column::new(declaration, DataType::Struct(fields), kind))
+                    }
+                }
+            }
+        }
+    }
+
+    fn struct_column(
+        &mut self,
+        declaration: &'a Declaration,
+        fields: impl Iterator<Item = (String, &'a Declaration)>,
+    ) -> Result<Column, ArrowExportError> {
+        let mut arrow_fields = Vec::new();
+        let mut children = Vec::new();
+        for (name, field) in fields {
+            let child = self.column(field)?;
+            arrow_fields.push(child.field(&name));
+            children.push(child);
+        }
+        // Arrow has structs without fields, but Parquet doesn't
+        if children.is_empty() {
+            return Ok(Column::new(declaration, DataType::Null, Kind::Null));
+        }
+        let fields = Fields::from(arrow_fields);
+        Ok(Column::new(declaration, DataType::Struct(fields.clone()), Kind::Struct { fields, children }))
+    }
+}
+
+fn primitive_column(declaration: &Declaration, primitive: PrimitiveType) -> Column {
+    let fixed = |data_type| Column::new(declaration, data_type, Kind::Fixed(primitive, Vec::new()));
+    match primitive {
+        PrimitiveType::U8 => fixed(DataType::UInt8),
+        PrimitiveType::U16 => fixed(DataType::UInt16),
+        PrimitiveType::U32 => fixed(DataType::UInt32),
+        PrimitiveType::U64 => fixed(DataType::UInt64),
+        PrimitiveType::I8 => fixed(DataType::Int8),
+        PrimitiveType::I16 => fixed(DataType::Int16),
+        PrimitiveType::I32 => fixed(DataType::Int32),
+        PrimitiveType::I64 => fixed(DataType::Int64),
+        PrimitiveType::F32 => fixed(DataType::Float32),
+        PrimitiveType::F64 => fixed(DataType::Float64),
+        PrimitiveType::U128 | PrimitiveType::I128 | PrimitiveType::String => {
+            let kind = Kind::Utf8 {
+                primitive,
+                offsets: vec![0],
+                values: Vec::new(),
+            };
+            Column::new(declaration, DataType::Utf8, kind)
+        }
+        PrimitiveType::Bool => Column::new(declaration, DataType::Boolean, Kind::Bool(Vec::new())),
+        PrimitiveType::Unit => Column::new(declaration, DataType::Null, Kind::Null),
+    }
+}
+
+fn primitive_array<T: ArrowPrimitiveType>(values: &[u8], nulls: Option<NullBuffer>) -> ArrayRef {
+    // Copied so that the values are aligned for T
+    Arc::new(PrimitiveArray::<T>::new(ScalarBuffer::from(Buffer::from_slice_ref(values)), nulls))
+}
+
+fn take_offsets(offsets: &mut Vec<i32>) -> OffsetBuffer<i32> {
+    OffsetBuffer::new(ScalarBuffer::from(std::mem::replace(offsets, vec![0])))
+}
+
+fn read_variant_index(buffer: &mut &[u8], tag_width: u8, discriminants: impl Iterator<Item = u64>, declaration: &str) -> std::io::Result<usize> {
+    let discriminant = read_width(buffer, tag_width)?;
+    discriminants
+        .into_iter()
+        .position(|candidate| candidate == discriminant)
+        .ok_or_else(|| invalid_data(format!("Unknown discriminant {discriminant} for {declaration}")))
+}
+
+fn take<'b>(buffer: &mut &'b [u8], length: usize, declaration: &str) -> std::io::Result<&'b [u8]> {
+    if length > buffer.len() {
+        return Err(unexpected_end(declaration));
+    }
+    let (bytes, rest) = buffer.split_at(length);
+    *buffer = rest;
+    Ok(bytes)
+}
+
+/// Converts a length to an Arrow offset, which is 32 bits
+fn offset(length: usize) -> std::io::Result<i32> {
+    i32::try_from(length).map_err(|_| invalid_data("The batch has more values than 32 bit offsets can address".to_string()))
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn invalid_value(declaration: &str) -> Error {
+    invalid_data(format!("Invalid value for {declaration}"))
+}
+
+fn unexpected_end(declaration: &str) -> Error {
+    invalid_data(format!("Unexpected end of input reading {declaration}"))
+}
+
+fn unsupported(declaration: &Declaration, reason: &str) -> ArrowExportError {
+    ArrowExportError::Unsupported(declaration.clone(), reason.to_string())
+}
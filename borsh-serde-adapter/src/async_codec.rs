@@ -0,0 +1,184 @@
+use borsh::schema::BorshSchemaContainer;
+use bytes::{Buf, BufMut, BytesMut};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::deserialize_adapter::{deserialize_from_schema_with_limits, DecodeLimits};
+use crate::errors::AsyncCodecError;
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// Size of the little endian u32 length that precedes every frame
+pub const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Longest frame read or written unless another maximum is given, 8 MiB
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Reads the reader to its end and decodes the bytes as a single value of the schema. Bytes left over after the value
+/// are an error.
+pub async fn decode_async<R: AsyncRead + Unpin>(reader: &mut R, schema: &BorshSchemaContainer) -> Result<Value, AsyncCodecError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    decode_frame(&bytes, schema, &DecodeLimits::default())
+}
+
+/// Serializes the value with the schema and writes it to the writer, without a length prefix
+pub async fn encode_async<W: AsyncWrite + Unpin>(writer: &mut W, value: &Value, schema: &BorshSchemaContainer) -> Result<(), AsyncCodecError> {
+    let bytes = encode_value(value, schema)?;
+    writer.write_all(&bytes).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads one length prefixed frame and decodes it as a value of the schema. Returns None if the reader ends before the
+/// next frame starts, and an error if it ends inside a frame or the frame is longer than max_frame_length.
+pub async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    schema: &BorshSchemaContainer,
+    max_frame_length: usize,
+) -> Result<Option<Value>, AsyncCodecError> {
+    let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+    let read = reader.read(&mut prefix).await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut prefix[read..]).await?;
+
+    let length = u32::from_le_bytes(prefix) as usize;
+    if length > max_frame_length {
+        return Err(AsyncCodecError::FrameTooLong(length, max_frame_length));
+    }
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes).await?;
+    decode_frame(&bytes, schema, &DecodeLimits::default()).map(Some)
+}
+
+/// Serializes the value with the schema and writes it as one length prefixed frame
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    value: &Value,
+    schema: &BorshSchemaContainer,
+    max_frame_length: usize,
+) -> Result<(), AsyncCodecError> {
+    let bytes = encode_value(value, schema)?;
+    let prefix = frame_length(bytes.len(), max_frame_length)?;
+    writer.write_all(&prefix.to_le_bytes()).await?;
+    writer.write_all(&bytes).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// A tokio_util codec that frames borsh serialized values of one schema with a little endian u32 length prefix, and
+/// turns every frame into a serde_json::Value. Wrap a stream in `Framed` to read and write values.
+///
+/// ```ignore
+/// let mut framed = Framed::new(stream, BorshJsonCodec::new(container));
+/// framed.send(json!({"first_name": "John", "last_name": "Doe"})).await?;
+/// let value = framed.next().await.transpose()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct BorshJsonCodec {
+    schema: BorshSchemaContainer,
+    max_frame_length: usize,
+    limits: DecodeLimits,
+}
+
+impl BorshJsonCodec {
+    /// Creates a codec that accepts frames up to DEFAULT_MAX_FRAME_LENGTH and decodes them with the default limits
+    pub fn new(schema: BorshSchemaContainer) -> BorshJsonCodec {
+        BorshJsonCodec::with_limits(schema, DEFAULT_MAX_FRAME_LENGTH, DecodeLimits::default())
+    }
+
+    /// Creates a codec that refuses frames longer than max_frame_length, before buffering them, and decodes frames
+    /// within the limits
+    pub fn with_limits(schema: BorshSchemaContainer, max_frame_length: usize, limits: DecodeLimits) -> BorshJsonCodec {
+        BorshJsonCodec {
+            schema,
+            max_frame_length,
+            limits,
+        }
+    }
+
+    pub fn schema(&self) -> &BorshSchemaContainer {
+        &self.schema
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+}
+
+impl Decoder for BorshJsonCodec {
+    type Item = Value;
+    type Error = AsyncCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Value>, AsyncCodecError> {
+        if src.len() < LENGTH_PREFIX_SIZE {
+            return Ok(None);
+        }
+        let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+        prefix.copy_from_slice(&src[..LENGTH_PREFIX_SIZE]);
+        let length = u32::from_le_bytes(prefix) as usize;
+        if length > self.max_frame_length {
+            return Err(AsyncCodecError::FrameTooLong(length, self.max_frame_length));
+        }
+        if src.len() < LENGTH_PREFIX_SIZE + length {
+            src.reserve(LENGTH_PREFIX_SIZE + length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(LENGTH_PREFIX_SIZE);
+        let frame = src.split_to(length);
+        decode_frame(&frame, &self.schema, &self.limits).map(Some)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Value>, AsyncCodecError> {
+        match self.decode(src)? {
+            Some(value) => Ok(Some(value)),
+            None if src.is_empty() => Ok(None),
+            None => Err(AsyncCodecError::IncompleteFrame(src.len())),
+        }
+    }
+}
+
+impl Encoder<&Value> for BorshJsonCodec {
+    type Error = AsyncCodecError;
+
+    fn encode(&mut self, value: &Value, dst: &mut BytesMut) -> Result<(), AsyncCodecError> {
+        let bytes = encode_value(value, &self.schema)?;
+        let prefix = frame_length(bytes.len(), self.max_frame_length)?;
+        dst.reserve(LENGTH_PREFIX_SIZE + bytes.len());
+        dst.put_u32_le(prefix);
+        dst.put_slice(&bytes);
+        Ok(())
+    }
+}
+
+impl Encoder<Value> for BorshJsonCodec {
+    type Error = AsyncCodecError;
+
+    fn encode(&mut self, value: Value, dst: &mut BytesMut) -> Result<(), AsyncCodecError> {
+        Encoder::<&Value>::encode(self, &value, dst)
+    }
+}
+
+fn decode_frame(mut bytes: &[u8], schema: &BorshSchemaContainer, limits: &DecodeLimits) -> Result<Value, AsyncCodecError> {
+    let value = deserialize_from_schema_with_limits(&mut bytes, schema, limits)?;
+    if !bytes.is_empty() {
+        return Err(AsyncCodecError::TrailingBytes(bytes.len()));
+    }
+    Ok(value)
+}
+
+fn encode_value(value: &Value, schema: &BorshSchemaContainer) -> Result<Vec<u8>, AsyncCodecError> {
+    let mut bytes = Vec::new();
+    serialize_serde_json_to_borsh(&mut bytes, value, schema).map_err(|error| AsyncCodecError::Encode(format!("{error:#}")))?;
+    Ok(bytes)
+}
+
+fn frame_length(length: usize, max_frame_length: usize) -> Result<u32, AsyncCodecError> {
+    match u32::try_from(length) {
+        Ok(prefix) if length <= max_frame_length => Ok(prefix),
+        _ => Err(AsyncCodecError::FrameTooLong(length, max_frame_length)),
+    }
+}
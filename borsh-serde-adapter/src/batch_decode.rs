@@ -0,0 +1,67 @@
+use borsh::schema::BorshSchemaContainer;
+use rayon::prelude::*;
+use serde_json::Value;
+
+use crate::deserialize_adapter::{deserialize_from_schema_with_limits, DecodeLimits};
+use crate::errors::{BatchDecodeError, RecordStreamError};
+use crate::record_stream::DEFAULT_MAX_RECORD_LENGTH;
+
+/// Decodes independent records of one schema in parallel on the rayon thread pool. The results are in the order of the
+/// records, and a record that fails to decode, or has bytes left over, gets an error without affecting the others.
+///
+/// ```ignore
+/// let results = decode_batch(&records, &container);
+/// let failed = results.iter().filter(|result| result.is_err()).count();
+/// ```
+pub fn decode_batch<B: AsRef<[u8]> + Sync>(records: &[B], schema: &BorshSchemaContainer) -> Vec<Result<Value, BatchDecodeError>> {
+    decode_batch_with_limits(records, schema, &DecodeLimits::default())
+}
+
+/// Like decode_batch, but each record is decoded within the limits
+pub fn decode_batch_with_limits<B: AsRef<[u8]> + Sync>(
+    records: &[B],
+    schema: &BorshSchemaContainer,
+    limits: &DecodeLimits,
+) -> Vec<Result<Value, BatchDecodeError>> {
+    records
+        .par_iter()
+        .enumerate()
+        .map(|(index, record)| decode_record(index, record.as_ref(), schema, limits))
+        .collect()
+}
+
+/// Splits a buffer of records that are each preceded by their length as a little endian u32, such as a snapshot file
+/// read into memory, and decodes the records in parallel as decode_batch does. Fails as a whole if the framing is
+/// broken, since the records after a broken frame can't be found.
+pub fn decode_framed_batch(data: &[u8], schema: &BorshSchemaContainer) -> Result<Vec<Result<Value, BatchDecodeError>>, RecordStreamError> {
+    Ok(decode_batch(&split_frames(data, DEFAULT_MAX_RECORD_LENGTH)?, schema))
+}
+
+/// Returns the records of a buffer of length prefixed records, without their prefixes
+pub fn split_frames(mut data: &[u8], max_record_length: usize) -> Result<Vec<&[u8]>, RecordStreamError> {
+    let mut frames = Vec::new();
+    let mut offset = 0u64;
+    while !data.is_empty() {
+        let index = frames.len();
+        if data.len() < 4 {
+            return Err(RecordStreamError::Truncated(index, offset));
+        }
+        let length = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        if length > max_record_length {
+            return Err(RecordStreamError::TooLong(index, offset, max_record_length));
+        }
+        let frame = data.get(4..4 + length).ok_or(RecordStreamError::Truncated(index, offset))?;
+        frames.push(frame);
+        data = &data[4 + length..];
+        offset += 4 + length as u64;
+    }
+    Ok(frames)
+}
+
+fn decode_record(index: usize, mut record: &[u8], schema: &BorshSchemaContainer, limits: &DecodeLimits) -> Result<Value, BatchDecodeError> {
+    let value = deserialize_from_schema_with_limits(&mut record, schema, limits).map_err(|error| BatchDecodeError::Invalid(index, error))?;
+    if !record.is_empty() {
+        return Err(BatchDecodeError::TrailingBytes(index, record.len()));
+    }
+    Ok(value)
+}
@@ -1,7 +1,9 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Write};
 use borsh::{BorshSchema, schema_container_of, to_vec};
-use borsh::schema::BorshSchemaContainer;
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
 use crate::deserialize_adapter::deserialize_from_schema;
 
 ///This function takes a Struct with the BorshSchema trait and writes the schema as JSON to a specified file.
@@ -10,17 +12,272 @@ pub fn write_schema_as_json<T: BorshSchema>(_: T, file_path: String) -> std::io:
     let mut defs = Default::default();
     T::add_definitions_recursively(&mut defs);
     let container: BorshSchemaContainer = BorshSchemaContainer::for_type::<T>();
-    let data = to_vec(&container).expect("Failed to serialize BorshSchemaContainer");
+    let result = schema_container_to_json(&container)?;
+
+    let mut file = File::create(file_path).expect("Failed to create borsh schema json file");
+    file.write_all(result.to_string().as_bytes()).expect("Failed to write file");
+    Ok(())
+}
+
+///This function returns the JSON representation of a BorshSchemaContainer, the same JSON written by write_schema_as_json.
+pub fn schema_container_to_json(container: &BorshSchemaContainer) -> std::io::Result<serde_json::Value> {
+    let data = to_vec(container)?;
 
     let mut con_defs = Default::default();
     BorshSchemaContainer::add_definitions_recursively(&mut con_defs);
     let con_container: BorshSchemaContainer = schema_container_of::<BorshSchemaContainer>();
 
-    let result = deserialize_from_schema(&mut data.as_slice(), &con_container).expect("Deserialization failed");
+    deserialize_from_schema(&mut data.as_slice(), &con_container)
+}
 
-    let mut file = File::create(file_path).expect("Failed to create borsh schema json file");
-    file.write_all(result.to_string().as_bytes()).expect("Failed to write file");
-    Ok(())
+/// Returns true if the adapters know how to serialize and deserialize the declaration without a definition
+pub fn is_supported_primitive(declaration: &str) -> bool {
+    PrimitiveType::from_declaration(declaration).is_some()
+}
+
+/// Returns the definition of a declaration, or None for supported primitives. Containers may define String as a
+/// sequence of u8, but the adapters always treat it as a primitive, so definitions are looked up through this.
+pub(crate) fn definition<'a>(schema: &'a BorshSchemaContainer, declaration: &str) -> Option<&'a Definition> {
+    match is_supported_primitive(declaration) {
+        true => None,
+        false => schema.get_definition(declaration),
+    }
+}
+
+/// The primitive types the adapters know how to serialize and deserialize
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    String,
+    Bool,
+    /// The unit type, used for the None variant of Option. It has no bytes and is given as null.
+    Unit,
+}
+
+impl PrimitiveType {
+    /// Every supported primitive type, in the order of their declarations above
+    pub const ALL: [PrimitiveType; 15] = [
+        PrimitiveType::U8,
+        PrimitiveType::U16,
+        PrimitiveType::U32,
+        PrimitiveType::U64,
+        PrimitiveType::U128,
+        PrimitiveType::I8,
+        PrimitiveType::I16,
+        PrimitiveType::I32,
+        PrimitiveType::I64,
+        PrimitiveType::I128,
+        PrimitiveType::F32,
+        PrimitiveType::F64,
+        PrimitiveType::String,
+        PrimitiveType::Bool,
+        PrimitiveType::Unit,
+    ];
+
+    /// Returns the primitive type for a declaration, or None if the declaration is not a supported primitive
+    pub fn from_declaration(declaration: &str) -> Option<PrimitiveType> {
+        PrimitiveType::ALL.into_iter().find(|primitive| primitive.declaration() == declaration)
+    }
+
+    /// Returns the declaration borsh uses for the type in schemas
+    pub fn declaration(&self) -> &'static str {
+        match self {
+            PrimitiveType::U8 => "u8",
+            PrimitiveType::U16 => "u16",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::U128 => "u128",
+            PrimitiveType::I8 => "i8",
+            PrimitiveType::I16 => "i16",
+            PrimitiveType::I32 => "i32",
+            PrimitiveType::I64 => "i64",
+            PrimitiveType::I128 => "i128",
+            PrimitiveType::F32 => "f32",
+            PrimitiveType::F64 => "f64",
+            PrimitiveType::String => "String",
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::Unit => "()",
+        }
+    }
+
+    /// Returns the encoded size in bytes, or None for String whose size depends on its length
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            PrimitiveType::U8 | PrimitiveType::I8 | PrimitiveType::Bool => Some(1),
+            PrimitiveType::U16 | PrimitiveType::I16 => Some(2),
+            PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32 => Some(4),
+            PrimitiveType::U64 | PrimitiveType::I64 | PrimitiveType::F64 => Some(8),
+            PrimitiveType::U128 | PrimitiveType::I128 => Some(16),
+            PrimitiveType::String => None,
+            PrimitiveType::Unit => Some(0),
+        }
+    }
+
+    /// Returns the fewest bytes a value of this type can be encoded in
+    pub fn min_size(&self) -> usize {
+        // A String is at least its u32 length prefix
+        self.fixed_size().unwrap_or(4)
+    }
+}
+
+/// Returns the declarations a definition refers to, in the order they are encoded
+pub(crate) fn definition_children(definition: &Definition) -> Vec<&Declaration> {
+    match definition {
+        Definition::Primitive(_) => Vec::new(),
+        Definition::Sequence { elements, .. } => vec![elements],
+        Definition::Tuple { elements } => elements.iter().collect(),
+        Definition::Enum { variants, .. } => variants.iter().map(|(_, _, declaration)| declaration).collect(),
+        Definition::Struct { fields } => match fields {
+            Fields::NamedFields(fields) => fields.iter().map(|(_, declaration)| declaration).collect(),
+            Fields::UnnamedFields(fields) => fields.iter().collect(),
+            Fields::Empty => Vec::new(),
+        },
+    }
+}
+
+/// The minimum and fixed encoded sizes of the declarations of a schema. Each size is worked out the first time it is
+/// asked for and kept, so looking sizes up while decoding costs nothing once warm, and types shared by several fields,
+/// such as S0 { a: S1, b: S1 }, are walked once rather than once per path to them.
+#[derive(Debug)]
+pub(crate) struct EncodedSizes<'a> {
+    schema: &'a BorshSchemaContainer,
+    min: RefCell<HashMap<&'a str, usize>>,
+    fixed: RefCell<HashMap<&'a str, Option<usize>>>,
+}
+
+impl<'a> EncodedSizes<'a> {
+    pub(crate) fn new(schema: &'a BorshSchemaContainer) -> EncodedSizes<'a> {
+        EncodedSizes {
+            schema,
+            min: RefCell::new(HashMap::new()),
+            fixed: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn schema(&self) -> &'a BorshSchemaContainer {
+        self.schema
+    }
+
+    /// The fewest bytes a value of the declaration can be encoded in. Recursive occurrences of a type are behind an
+    /// enum tag or a length prefix, which are already counted, so they add nothing.
+    pub(crate) fn min_size(&self, declaration: &'a Declaration) -> usize {
+        self.min_size_within(declaration, &mut Vec::new()).0
+    }
+
+    /// The encoded size of every value of the declaration, or None if values of the declaration vary in size.
+    /// Declarations without a definition are decoded as null from no bytes, so their size is 0.
+    pub(crate) fn fixed_size(&self, declaration: &'a Declaration) -> Option<usize> {
+        self.fixed_size_within(declaration, &mut Vec::new()).0
+    }
+
+    /// Returns the minimum size, and the position in visiting of the outermost declaration the size depended on
+    /// through a recursive occurrence. Only sizes that depend on nothing outside their own declaration are kept, so a
+    /// kept size is the same whichever declaration the walk started from.
+    fn min_size_within(&self, declaration: &'a Declaration, visiting: &mut Vec<&'a Declaration>) -> (usize, usize) {
+        if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+            return (primitive.min_size(), usize::MAX);
+        }
+        if let Some(size) = self.min.borrow().get(declaration.as_str()) {
+            return (*size, usize::MAX);
+        }
+        if let Some(position) = visiting.iter().position(|visited| *visited == declaration) {
+            return (0, position);
+        }
+        let Some(definition) = self.schema.get_definition(declaration) else {
+            return (0, usize::MAX);
+        };
+
+        let position = visiting.len();
+        visiting.push(declaration);
+        let mut outermost = usize::MAX;
+        let mut child = |child: &'a Declaration| {
+            let (size, depends_on) = self.min_size_within(child, visiting);
+            outermost = outermost.min(depends_on);
+            size
+        };
+        let size = match definition {
+            Definition::Primitive(size) => *size as usize,
+            Definition::Sequence { length_width: 0, length_range, elements } => (*length_range.end() as usize).saturating_mul(child(elements)),
+            Definition::Sequence { length_width, .. } => *length_width as usize,
+            Definition::Enum { tag_width, .. } => *tag_width as usize,
+            Definition::Tuple { .. } | Definition::Struct { .. } => definition_children(definition).into_iter().map(child).sum(),
+        };
+        visiting.pop();
+
+        if outermost < position {
+            return (size, outermost);
+        }
+        self.min.borrow_mut().insert(declaration, size);
+        (size, usize::MAX)
+    }
+
+    /// Like min_size_within, for fixed sizes
+    fn fixed_size_within(&self, declaration: &'a Declaration, visiting: &mut Vec<&'a Declaration>) -> (Option<usize>, usize) {
+        if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+            return (primitive.fixed_size(), usize::MAX);
+        }
+        if let Some(size) = self.fixed.borrow().get(declaration.as_str()) {
+            return (*size, usize::MAX);
+        }
+        if let Some(position) = visiting.iter().position(|visited| *visited == declaration) {
+            return (None, position);
+        }
+        let Some(definition) = self.schema.get_definition(declaration) else {
+            return (Some(0), usize::MAX);
+        };
+
+        let position = visiting.len();
+        visiting.push(declaration);
+        let mut outermost = usize::MAX;
+        let mut child = |child: &'a Declaration| {
+            let (size, depends_on) = self.fixed_size_within(child, visiting);
+            outermost = outermost.min(depends_on);
+            size
+        };
+        let size = match definition {
+            Definition::Primitive(size) => Some(*size as usize),
+            Definition::Sequence { length_width: 0, length_range, elements } => {
+                child(elements).and_then(|size| size.checked_mul(*length_range.end() as usize))
+            }
+            Definition::Sequence { .. } => None,
+            Definition::Enum { tag_width, variants } => {
+                let mut sizes = variants.iter().map(|(_, _, variant)| child(variant));
+                match sizes.next() {
+                    Some(Some(size)) if sizes.all(|other| other == Some(size)) => Some(*tag_width as usize + size),
+                    _ => None,
+                }
+            }
+            Definition::Tuple { .. } | Definition::Struct { .. } => definition_children(definition).into_iter().map(child).sum(),
+        };
+        visiting.pop();
+
+        if outermost < position {
+            return (size, outermost);
+        }
+        self.fixed.borrow_mut().insert(declaration, size);
+        (size, usize::MAX)
+    }
+}
+
+/// Formats bytes as lowercase hex
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Escapes a JSON pointer segment as described in RFC 6901
+pub(crate) fn escape_pointer_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
 }
 
 //TODO this is a nice to have, but not really needed if your project is using rust, since you can
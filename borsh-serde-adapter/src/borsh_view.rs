@@ -0,0 +1,307 @@
+use std::io::ErrorKind;
+use std::rc::Rc;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use borsh::BorshDeserialize;
+
+use crate::borsh_schema_util::{definition, EncodedSizes};
+use crate::deserialize_adapter::{self, deserialize_to_serde_json, DecodeLimits};
+use crate::errors::ViewError;
+
+/// A borrowed view of a borsh serialized value that reads only what is asked for. Fields, elements and variants are
+/// found by skipping over the values before them, using the fixed sizes and length prefixes in the schema, so nothing
+/// is copied or decoded until one of the as_ accessors is called. Values of a fixed size are skipped in one step, and
+/// the sizes worked out are shared by every view reached from the same one.
+///
+/// ```ignore
+/// let price = BorshView::new(&account_bytes, &container).get("items")?.index(3)?.get("price")?.as_u64()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct BorshView<'a> {
+    bytes: &'a [u8],
+    sizes: Rc<EncodedSizes<'a>>,
+    declaration: &'a Declaration,
+}
+
+impl<'a> BorshView<'a> {
+    /// Creates a view of the value of the schema's declaration at the start of bytes
+    pub fn new(bytes: &'a [u8], schema: &'a BorshSchemaContainer) -> BorshView<'a> {
+        BorshView {
+            bytes,
+            sizes: Rc::new(EncodedSizes::new(schema)),
+            declaration: schema.declaration(),
+        }
+    }
+
+    pub fn declaration(&self) -> &'a str {
+        self.declaration
+    }
+
+    /// Returns the view of a named field of a struct, or of the contents of an enum if name is its active variant
+    pub fn get(&self, name: &str) -> Result<BorshView<'a>, ViewError> {
+        match self.definition() {
+            Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => {
+                let mut bytes = self.bytes;
+                for (field_name, field_declaration) in fields {
+                    if field_name == name {
+                        return Ok(self.at(bytes, field_declaration));
+                    }
+                    skip(&self.sizes, field_declaration, 1, &mut bytes)?;
+                }
+                Err(ViewError::NoField(self.declaration.to_string(), name.to_string()))
+            }
+            Some(Definition::Enum { .. }) => {
+                let (variant_name, contents) = self.read_variant()?;
+                if variant_name != name {
+                    return Err(ViewError::InactiveVariant(name.to_string(), variant_name.to_string()));
+                }
+                Ok(contents)
+            }
+            _ => Err(self.wrong_type("struct")),
+        }
+    }
+
+    /// Returns the view of an element of a sequence or tuple, or of an unnamed field of a struct
+    pub fn index(&self, index: usize) -> Result<BorshView<'a>, ViewError> {
+        match self.definition() {
+            Some(Definition::Sequence { length_width, length_range, elements }) => {
+                let mut bytes = self.bytes;
+                let length = read_length(&mut bytes, *length_width, *length_range.end(), self.declaration)?;
+                if index as u64 >= length {
+                    return Err(ViewError::IndexOutOfBounds(index as u64, self.declaration.to_string(), length));
+                }
+                skip(&self.sizes, elements, index as u64, &mut bytes)?;
+                Ok(self.at(bytes, elements))
+            }
+            Some(Definition::Tuple { elements }) | Some(Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+                let Some(element) = elements.get(index) else {
+                    return Err(ViewError::IndexOutOfBounds(index as u64, self.declaration.to_string(), elements.len() as u64));
+                };
+                let mut bytes = self.bytes;
+                for declaration in &elements[..index] {
+                    skip(&self.sizes, declaration, 1, &mut bytes)?;
+                }
+                Ok(self.at(bytes, element))
+            }
+            _ => Err(self.wrong_type("sequence or tuple")),
+        }
+    }
+
+    /// Returns the number of elements of a sequence or tuple, or of unnamed fields of a struct
+    pub fn len(&self) -> Result<usize, ViewError> {
+        let length = match self.definition() {
+            Some(Definition::Sequence { length_width, length_range, .. }) => {
+                let mut bytes = self.bytes;
+                read_length(&mut bytes, *length_width, *length_range.end(), self.declaration)?
+            }
+            Some(Definition::Tuple { elements }) | Some(Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+                elements.len() as u64
+            }
+            _ => return Err(self.wrong_type("sequence or tuple")),
+        };
+        usize::try_from(length).map_err(|_| ViewError::InvalidValue(self.declaration.to_string()))
+    }
+
+    pub fn is_empty(&self) -> Result<bool, ViewError> {
+        self.len().map(|length| length == 0)
+    }
+
+    /// Returns the name of the active variant of an enum
+    pub fn variant(&self) -> Result<&'a str, ViewError> {
+        self.read_variant().map(|(variant_name, _)| variant_name)
+    }
+
+    /// Returns the view of the contents of the active variant of an enum
+    pub fn variant_value(&self) -> Result<BorshView<'a>, ViewError> {
+        self.read_variant().map(|(_, contents)| contents)
+    }
+
+    /// Reads an unsigned integer of up to 64 bits
+    pub fn as_u64(&self) -> Result<u64, ViewError> {
+        match self.declaration.as_str() {
+            "u8" => self.read::<u8>().map(u64::from),
+            "u16" => self.read::<u16>().map(u64::from),
+            "u32" => self.read::<u32>().map(u64::from),
+            "u64" => self.read::<u64>(),
+            _ => Err(self.wrong_type("unsigned integer")),
+        }
+    }
+
+    /// Reads a signed integer of up to 64 bits
+    pub fn as_i64(&self) -> Result<i64, ViewError> {
+        match self.declaration.as_str() {
+            "i8" => self.read::<i8>().map(i64::from),
+            "i16" => self.read::<i16>().map(i64::from),
+            "i32" => self.read::<i32>().map(i64::from),
+            "i64" => self.read::<i64>(),
+            _ => Err(self.wrong_type("signed integer")),
+        }
+    }
+
+    pub fn as_u128(&self) -> Result<u128, ViewError> {
+        match self.declaration.as_str() {
+            "u128" => self.read::<u128>(),
+            _ => self.as_u64().map(u128::from),
+        }
+    }
+
+    pub fn as_i128(&self) -> Result<i128, ViewError> {
+        match self.declaration.as_str() {
+            "i128" => self.read::<i128>(),
+            _ => self.as_i64().map(i128::from),
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64, ViewError> {
+        match self.declaration.as_str() {
+            "f32" => self.read::<f32>().map(f64::from),
+            "f64" => self.read::<f64>(),
+            _ => Err(self.wrong_type("float")),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, ViewError> {
+        match self.declaration.as_str() {
+            "bool" => self.read::<bool>(),
+            _ => Err(self.wrong_type("bool")),
+        }
+    }
+
+    /// Returns a string borrowed from the underlying bytes
+    pub fn as_str(&self) -> Result<&'a str, ViewError> {
+        if self.declaration != "String" {
+            return Err(self.wrong_type("String"));
+        }
+        let mut bytes = self.bytes;
+        let length = read_length(&mut bytes, 4, 0, self.declaration)? as usize;
+        let string = bytes.get(..length).ok_or_else(|| ViewError::UnexpectedEnd(self.declaration.to_string()))?;
+        std::str::from_utf8(string).map_err(|_| ViewError::InvalidValue(self.declaration.to_string()))
+    }
+
+    /// Returns the borsh serialized bytes of the value
+    pub fn as_bytes(&self) -> Result<&'a [u8], ViewError> {
+        let mut rest = self.bytes;
+        skip(&self.sizes, self.declaration, 1, &mut rest)?;
+        Ok(&self.bytes[..self.bytes.len() - rest.len()])
+    }
+
+    /// Deserializes the value to serde_json::Value, as deserialize_from_schema would
+    pub fn to_json(&self) -> std::io::Result<serde_json::Value> {
+        let mut bytes = self.bytes;
+        deserialize_to_serde_json(&mut bytes, self.sizes.schema(), self.declaration, &DecodeLimits::default())
+    }
+
+    fn at(&self, bytes: &'a [u8], declaration: &'a Declaration) -> BorshView<'a> {
+        BorshView {
+            bytes,
+            sizes: self.sizes.clone(),
+            declaration,
+        }
+    }
+
+    fn definition(&self) -> Option<&'a Definition> {
+        definition(self.sizes.schema(), self.declaration)
+    }
+
+    fn wrong_type(&self, expected: &str) -> ViewError {
+        ViewError::WrongType(expected.to_string(), self.declaration.to_string())
+    }
+
+    fn read<T: BorshDeserialize>(&self) -> Result<T, ViewError> {
+        let size = size_of::<T>();
+        if self.bytes.len() < size {
+            return Err(ViewError::UnexpectedEnd(self.declaration.to_string()));
+        }
+        T::deserialize(&mut &self.bytes[..size]).map_err(|_| ViewError::InvalidValue(self.declaration.to_string()))
+    }
+
+    fn read_variant(&self) -> Result<(&'a str, BorshView<'a>), ViewError> {
+        let Some(Definition::Enum { tag_width, variants }) = self.definition() else {
+            return Err(self.wrong_type("enum"));
+        };
+        let mut bytes = self.bytes;
+        let (variant_name, variant_declaration) = read_variant(&mut bytes, *tag_width, variants, self.declaration)?;
+        Ok((variant_name, self.at(bytes, variant_declaration)))
+    }
+}
+
+fn advance(bytes: &mut &[u8], size: usize, declaration: &str) -> Result<(), ViewError> {
+    *bytes = bytes.get(size..).ok_or_else(|| ViewError::UnexpectedEnd(declaration.to_string()))?;
+    Ok(())
+}
+
+/// Reads a sequence length, or returns the fixed length of a sequence without a length prefix
+fn read_length(bytes: &mut &[u8], width: u8, fixed_length: u64, declaration: &str) -> Result<u64, ViewError> {
+    if width == 0 {
+        return Ok(fixed_length);
+    }
+    read_width(bytes, width, declaration)
+}
+
+fn read_width(bytes: &mut &[u8], width: u8, declaration: &str) -> Result<u64, ViewError> {
+    deserialize_adapter::read_width(bytes, width).map_err(|error| match error.kind() {
+        ErrorKind::UnexpectedEof => ViewError::UnexpectedEnd(declaration.to_string()),
+        _ => ViewError::Unsupported(declaration.to_string()),
+    })
+}
+
+fn read_variant<'a>(
+    bytes: &mut &[u8],
+    tag_width: u8,
+    variants: &'a [(i64, String, Declaration)],
+    declaration: &str,
+) -> Result<(&'a str, &'a Declaration), ViewError> {
+    let tag = *bytes;
+    deserialize_adapter::read_variant(bytes, tag_width, variants, declaration).map_err(|_| {
+        // Reading the tag again tells a tag that names no variant from one that couldn't be read
+        match read_width(&mut &tag[..], tag_width, declaration) {
+            Ok(discriminant) => ViewError::UnknownDiscriminant(discriminant, declaration.to_string()),
+            Err(error) => error,
+        }
+    })
+}
+
+/// Advances bytes past count values of the declaration. Values of a fixed size are skipped in one step, others with an
+/// explicit stack of pending declarations and counts, so a long sequence is one entry rather than one per element.
+pub(crate) fn skip<'a>(sizes: &EncodedSizes<'a>, declaration: &'a Declaration, count: u64, bytes: &mut &[u8]) -> Result<(), ViewError> {
+    let schema = sizes.schema();
+    let mut pending: Vec<(&Declaration, u64)> = vec![(declaration, count)];
+    while let Some((declaration, count)) = pending.pop() {
+        if count == 0 {
+            continue;
+        }
+        if let Some(size) = sizes.fixed_size(declaration) {
+            let total = usize::try_from(count).ok().and_then(|count| count.checked_mul(size));
+            advance(bytes, total.unwrap_or(usize::MAX), declaration)?;
+            continue;
+        }
+        if count > 1 {
+            pending.push((declaration, count - 1));
+        }
+
+        if declaration == "String" {
+            let length = read_width(bytes, 4, declaration)?;
+            advance(bytes, length as usize, declaration)?;
+            continue;
+        }
+        match schema.get_definition(declaration) {
+            Some(Definition::Sequence { length_width, length_range, elements }) => {
+                let length = read_length(bytes, *length_width, *length_range.end(), declaration)?;
+                pending.push((elements, length));
+            }
+            Some(Definition::Tuple { elements }) | Some(Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+                pending.extend(elements.iter().rev().map(|element| (element, 1)));
+            }
+            Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => {
+                pending.extend(fields.iter().rev().map(|(_, field)| (field, 1)));
+            }
+            Some(Definition::Enum { tag_width, variants }) => {
+                let (_, variant_declaration) = read_variant(bytes, *tag_width, variants, declaration)?;
+                pending.push((variant_declaration, 1));
+            }
+            // Primitives, empty structs and declarations without a definition always have a fixed size
+            _ => {}
+        }
+    }
+    Ok(())
+}
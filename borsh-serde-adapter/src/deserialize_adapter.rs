@@ -1,115 +1,536 @@
-use std::collections::HashMap;
-use std::io::{Error};
+use std::io::{Error, ErrorKind};
 
-use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
 use borsh::{BorshDeserialize};
 use log::debug;
-use serde_json::json;
+use serde_json::{json, Map};
+
+use crate::borsh_schema_util::{hex, EncodedSizes, PrimitiveType};
+
+/// Default maximum nesting depth of structs, tuples, sequences and enums
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// Limits applied while deserializing, so that untrusted input can't make the deserializer allocate or loop without bound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum number of elements in a single sequence
+    pub max_sequence_length: usize,
+    /// Maximum number of bytes read from the buffer
+    pub max_total_bytes: usize,
+    /// Maximum length of a single string in bytes
+    pub max_string_length: usize,
+    /// Maximum nesting depth of structs, tuples, sequences and enums
+    pub max_depth: usize,
+    /// Maximum number of values in the result, counting every primitive and composite value
+    pub max_output_nodes: usize,
+}
+
+impl Default for DecodeLimits {
+    /// Allows anything the borsh format can express, except nesting deeper than DEFAULT_MAX_DEPTH
+    fn default() -> Self {
+        DecodeLimits {
+            max_sequence_length: u32::MAX as usize,
+            max_total_bytes: usize::MAX,
+            max_string_length: u32::MAX as usize,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_output_nodes: usize::MAX,
+        }
+    }
+}
+
+/// Tracks how much of the DecodeLimits has been used by a single deserialization
+pub(crate) struct Budget<'l> {
+    pub(crate) limits: &'l DecodeLimits,
+    start_length: usize,
+    nodes: usize,
+    /// Number of composite values the next value is nested in
+    pub(crate) depth: usize,
+}
+
+impl<'l> Budget<'l> {
+    pub(crate) fn new(limits: &'l DecodeLimits, buffer: &[u8]) -> Budget<'l> {
+        Budget {
+            limits,
+            start_length: buffer.len(),
+            nodes: 0,
+            depth: 0,
+        }
+    }
+
+    /// Bytes that may still be read, bounded by both the buffer and max_total_bytes
+    pub(crate) fn remaining(&self, buffer: &[u8]) -> usize {
+        let consumed = self.start_length - buffer.len();
+        buffer.len().min(self.limits.max_total_bytes.saturating_sub(consumed))
+    }
+
+    pub(crate) fn check_consumed(&self, buffer: &[u8]) -> std::io::Result<()> {
+        if self.start_length - buffer.len() > self.limits.max_total_bytes {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Maximum of {} bytes exceeded", self.limits.max_total_bytes)));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add_node(&mut self) -> std::io::Result<()> {
+        self.nodes += 1;
+        if self.nodes > self.limits.max_output_nodes {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Maximum of {} output nodes exceeded", self.limits.max_output_nodes)));
+        }
+        Ok(())
+    }
+
+    /// Fails if a composite value can't be started at the current depth
+    pub(crate) fn check_depth(&self) -> std::io::Result<()> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Maximum nesting depth of {} exceeded", self.limits.max_depth)));
+        }
+        Ok(())
+    }
+}
 
 //Deserializes borsh serialized bytes to serde_json::Value
 fn deserialize_to_serde_json_by_type<T: BorshDeserialize + Into<serde_json::Value>>(buffer: &mut &[u8], type_name: &str) -> std::io::Result<serde_json::Value> {
     T::deserialize(buffer)
         .map(Into::into)
-        .map_err(|_err| Error::new(std::io::ErrorKind::InvalidData, type_name))
+        .map_err(|_err| Error::new(ErrorKind::InvalidData, type_name))
 }
 
-fn deserialize_to_serde_json(buffer: &mut &[u8], schema: &BorshSchemaContainer, declaration: &borsh::schema::Declaration) -> std::io::Result<serde_json::Value> {
-    match &declaration[..] {
-        "u8" => deserialize_to_serde_json_by_type::<u8>(buffer, "u8"),
-        "u16" => deserialize_to_serde_json_by_type::<u16>(buffer, "u16"),
-        "u32" => deserialize_to_serde_json_by_type::<u32>(buffer, "u32"),
-        "u64" => deserialize_to_serde_json_by_type::<u64>(buffer, "u64"),
-        "u128" => u128::deserialize(buffer)
+fn deserialize_string(buffer: &mut &[u8], max_length: usize) -> std::io::Result<serde_json::Value> {
+    let length = u32::deserialize(buffer).map_err(|_| Error::new(ErrorKind::InvalidData, "String"))? as usize;
+    if length > max_length {
+        return Err(Error::new(ErrorKind::InvalidData, format!("String length {length} exceeds the maximum of {max_length}")));
+    }
+    if length > buffer.len() {
+        let remaining = buffer.len();
+        return Err(Error::new(ErrorKind::InvalidData, format!("String of {length} bytes needs more than the {remaining} bytes remaining")));
+    }
+    let (bytes, rest) = buffer.split_at(length);
+    let value = std::str::from_utf8(bytes).map_err(|_| Error::new(ErrorKind::InvalidData, "String"))?.into();
+    *buffer = rest;
+    Ok(value)
+}
+
+pub(crate) fn deserialize_primitive(buffer: &mut &[u8], primitive: PrimitiveType, limits: &DecodeLimits) -> std::io::Result<serde_json::Value> {
+    match primitive {
+        PrimitiveType::U8 => deserialize_to_serde_json_by_type::<u8>(buffer, "u8"),
+        PrimitiveType::U16 => deserialize_to_serde_json_by_type::<u16>(buffer, "u16"),
+        PrimitiveType::U32 => deserialize_to_serde_json_by_type::<u32>(buffer, "u32"),
+        PrimitiveType::U64 => deserialize_to_serde_json_by_type::<u64>(buffer, "u64"),
+        PrimitiveType::U128 => u128::deserialize(buffer)
             .map(|value| value.to_string().into())
-            .map_err(|_| Error::new(std::io::ErrorKind::InvalidData, "u128")),
-        "i8" => deserialize_to_serde_json_by_type::<i8>(buffer, "i8"),
-        "i16" => deserialize_to_serde_json_by_type::<i16>(buffer, "i16"),
-        "i32" => deserialize_to_serde_json_by_type::<i32>(buffer, "i32"),
-        "i64" => deserialize_to_serde_json_by_type::<i64>(buffer, "i64"),
-        "i128" => i128::deserialize(buffer)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "u128")),
+        PrimitiveType::I8 => deserialize_to_serde_json_by_type::<i8>(buffer, "i8"),
+        PrimitiveType::I16 => deserialize_to_serde_json_by_type::<i16>(buffer, "i16"),
+        PrimitiveType::I32 => deserialize_to_serde_json_by_type::<i32>(buffer, "i32"),
+        PrimitiveType::I64 => deserialize_to_serde_json_by_type::<i64>(buffer, "i64"),
+        PrimitiveType::I128 => i128::deserialize(buffer)
             .map(|value| value.to_string().into())
-            .map_err(|_| Error::new(std::io::ErrorKind::InvalidData, "i128")),
-        "f32" => deserialize_to_serde_json_by_type::<f32>(buffer, "f32"),
-        "f64" => deserialize_to_serde_json_by_type::<f64>(buffer, "f64"),
-        "String" => deserialize_to_serde_json_by_type::<String>(buffer, "String"),
-        "bool" => deserialize_to_serde_json_by_type::<bool>(buffer, "bool"),
-
-        _ => {
-            if let Some(d) = schema.get_definition(declaration) {
-                match d {
-                    Definition::Primitive { .. } => {
-                        let value = deserialize_to_serde_json(buffer, schema, declaration)?;
-
-                        Ok(value)
-                    }
-
-                    Definition::Sequence { length_width, length_range, elements } => {
-                        let length_width = *length_width as u32;
-                        let length = if length_width == 0  {
-                            *length_range.end() as usize
-                        } else {
-                            u32::deserialize(buffer)? as usize
-                        };
-
-                        let mut values = Vec::<serde_json::Value>::with_capacity(length);
-                        for _ in 0..length {
-                            let value = deserialize_to_serde_json(buffer, schema, elements)?;
-                            values.push(value);
-                        }
-                        Ok(values.into())
-                    }
-
-                    Definition::Tuple { elements } => {
-                        let mut values = Vec::<serde_json::Value>::with_capacity(elements.len());
-                        for element in elements {
-                            let value = deserialize_to_serde_json(buffer, schema, element)?;
-                            values.push(value);
-                        }
-                        Ok(values.into())
-                    }
-
-                    Definition::Enum { tag_width: _, variants } => {
-                        let variant_index = u8::deserialize(buffer)?;
-                        let (_dicriminator_value, variant_name, variant_declaration) = &variants[variant_index as usize];
-                        deserialize_to_serde_json(buffer, schema, variant_declaration)
-                            .map(|value| json!({ variant_name: value }))
-                    }
-
-                    Definition::Struct { fields } => match fields {
-                        Fields::NamedFields(fields) => {
-                            let mut object = HashMap::<String, serde_json::Value>::new();
-                            for (key, value_declaration) in fields {
-                                let value = deserialize_to_serde_json(
-                                    buffer,
-                                    schema,
-                                    value_declaration,
-                                )?;
-                                object.insert(key.to_string(), value);
-                            }
-                            Ok(serde_json::to_value(object)?)
-                        }
-
-                        Fields::UnnamedFields(elements) => {
-                            let mut values = Vec::<serde_json::Value>::with_capacity(elements.len());
-                            for element in elements {
-                                let value = deserialize_to_serde_json(buffer, schema, element)?;
-                                values.push(value);
-                            }
-                            Ok(values.into())
-                        }
-
-                        Fields::Empty => Ok(Vec::<u8>::new().into()),
-                    }
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "i128")),
+        PrimitiveType::F32 => deserialize_to_serde_json_by_type::<f32>(buffer, "f32"),
+        PrimitiveType::F64 => deserialize_to_serde_json_by_type::<f64>(buffer, "f64"),
+        PrimitiveType::String => deserialize_string(buffer, limits.max_string_length),
+        PrimitiveType::Bool => deserialize_to_serde_json_by_type::<bool>(buffer, "bool"),
+        PrimitiveType::Unit => Ok(serde_json::Value::Null),
+    }
+}
+
+/// Reads a little-endian unsigned integer of the given width, used for sequence lengths and enum tags
+pub(crate) fn read_width(buffer: &mut &[u8], width: u8) -> std::io::Result<u64> {
+    match width {
+        1 => u8::deserialize(buffer).map(u64::from),
+        2 => u16::deserialize(buffer).map(u64::from),
+        4 => u32::deserialize(buffer).map(u64::from),
+        8 => u64::deserialize(buffer),
+        _ => Err(Error::new(ErrorKind::InvalidData, format!("Unsupported width of {width} bytes"))),
+    }
+}
+
+/// Reads the length of a sequence and checks it against the limits and the bytes left, before anything is allocated
+pub(crate) fn read_sequence_length(
+    buffer: &mut &[u8],
+    budget: &Budget,
+    length_width: u8,
+    fixed_length: u64,
+    element_size: usize,
+    elements: &str,
+) -> std::io::Result<usize> {
+    let length = if length_width == 0 {
+        fixed_length
+    } else {
+        read_width(buffer, length_width)?
+    };
+    let max_length = budget.limits.max_sequence_length;
+    let length = usize::try_from(length)
+        .ok()
+        .filter(|length| *length <= max_length)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Sequence length {length} exceeds the maximum of {max_length}")))?;
+
+    let remaining = budget.remaining(buffer);
+    if element_size == 0 && length_width != 0 && length > 0 {
+        // Like borsh, refuse length prefixed sequences of zero sized types, whose length costs no input
+        return Err(Error::new(ErrorKind::InvalidData, format!("Sequence of zero sized type {elements} is not allowed")));
+    }
+    if length.saturating_mul(element_size) > remaining {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Sequence of {length} {elements} needs more than the {remaining} bytes remaining"),
+        ));
+    }
+    Ok(length)
+}
+
+/// Reads an enum tag and returns the name and contents of the variant it selects
+pub(crate) fn read_variant<'a, T>(
+    buffer: &mut &[u8],
+    tag_width: u8,
+    variants: &'a [(i64, String, T)],
+    declaration: &str,
+) -> std::io::Result<(&'a str, &'a T)> {
+    let discriminant = read_width(buffer, tag_width)?;
+    variants
+        .iter()
+        .find(|(discriminant_value, ..)| *discriminant_value as u64 == discriminant)
+        .map(|(_, variant_name, variant)| (variant_name.as_str(), variant))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Unknown discriminant {discriminant} for {declaration}")))
+}
+
+/// A composite value being built while its children are deserialized. Children are described by T, a Declaration when
+/// deserializing against a schema and a node of a compiled SchemaPlan otherwise.
+pub(crate) enum Frame<'a, T> {
+    Sequence {
+        elements: &'a T,
+        remaining: usize,
+        values: Vec<serde_json::Value>,
+    },
+    Elements {
+        elements: &'a [T],
+        values: Vec<serde_json::Value>,
+    },
+    Struct {
+        fields: &'a [(String, T)],
+        next: usize,
+        object: Map<String, serde_json::Value>,
+    },
+    Enum {
+        variant_name: &'a str,
+        variant: Option<&'a T>,
+        value: serde_json::Value,
+    },
+}
+
+impl<'a, T> Frame<'a, T> {
+    pub(crate) fn sequence(elements: &'a T, length: usize, remaining_bytes: usize) -> Self {
+        Frame::Sequence {
+            elements,
+            remaining: length,
+            values: Vec::with_capacity(length.min(remaining_bytes)),
+        }
+    }
+
+    /// A sequence whose elements have all been deserialized already
+    pub(crate) fn decoded_sequence(elements: &'a T, values: Vec<serde_json::Value>) -> Self {
+        Frame::Sequence {
+            elements,
+            remaining: 0,
+            values,
+        }
+    }
+
+    pub(crate) fn elements(elements: &'a [T]) -> Self {
+        Frame::Elements {
+            elements,
+            values: Vec::with_capacity(elements.len()),
+        }
+    }
+
+    pub(crate) fn fields(fields: &'a [(String, T)]) -> Self {
+        Frame::Struct {
+            fields,
+            next: 0,
+            object: Map::new(),
+        }
+    }
+
+    pub(crate) fn variant(variant_name: &'a str, variant: &'a T) -> Self {
+        Frame::Enum {
+            variant_name,
+            variant: Some(variant),
+            value: serde_json::Value::Null,
+        }
+    }
+
+    /// Returns the next child to deserialize, or None when the frame is complete
+    fn next_child(&mut self) -> Option<&'a T> {
+        match self {
+            Frame::Sequence { elements, remaining, .. } => {
+                if *remaining == 0 {
+                    return None;
+                }
+                *remaining -= 1;
+                Some(elements)
+            }
+            Frame::Elements { elements, values } => elements.get(values.len()),
+            Frame::Struct { fields, next, .. } => {
+                let (_, child) = fields.get(*next)?;
+                *next += 1;
+                Some(child)
+            }
+            Frame::Enum { variant, .. } => variant.take(),
+        }
+    }
+
+    fn push(&mut self, value: serde_json::Value) {
+        match self {
+            Frame::Sequence { values, .. } | Frame::Elements { values, .. } => values.push(value),
+            Frame::Struct { fields, next, object } => {
+                object.insert(fields[*next - 1].0.to_string(), value);
+            }
+            Frame::Enum { value: variant_value, .. } => *variant_value = value,
+        }
+    }
+
+    fn finish(self) -> serde_json::Value {
+        match self {
+            Frame::Sequence { values, .. } | Frame::Elements { values, .. } => values.into(),
+            Frame::Struct { object, .. } => object.into(),
+            Frame::Enum { variant_name, value, .. } => json!({ variant_name: value }),
+        }
+    }
+}
+
+/// The result of starting to deserialize a declaration
+pub(crate) enum Step<'a, T> {
+    Value(serde_json::Value),
+    Frame(Frame<'a, T>),
+}
+
+fn start_declaration<'a>(
+    buffer: &mut &[u8],
+    schema: &'a BorshSchemaContainer,
+    sizes: &EncodedSizes<'a>,
+    declaration: &'a Declaration,
+    budget: &Budget,
+) -> std::io::Result<Step<'a, Declaration>> {
+    if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+        return deserialize_primitive(buffer, primitive, budget.limits).map(Step::Value);
+    }
+
+    let Some(definition) = schema.get_definition(declaration) else {
+        debug!("Can't deserialize unknown type: {:?}. Using Value::Null", declaration);
+        return Ok(Step::Value(serde_json::Value::Null));
+    };
+
+    let frame = match definition {
+        Definition::Primitive { .. } => {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported primitive type {declaration}")));
+        }
+
+        Definition::Sequence { length_width, length_range, elements } => {
+            // The length comes from untrusted input, so it is checked against the bytes left before allocating
+            let element_size = sizes.min_size(elements);
+            let length = read_sequence_length(buffer, budget, *length_width, *length_range.end(), element_size, elements)?;
+            Frame::sequence(elements, length, budget.remaining(buffer))
+        }
+
+        Definition::Tuple { elements } => Frame::elements(elements),
+
+        Definition::Enum { tag_width, variants } => {
+            let (variant_name, variant_declaration) = read_variant(buffer, *tag_width, variants, declaration)?;
+            Frame::variant(variant_name, variant_declaration)
+        }
+
+        Definition::Struct { fields } => match fields {
+            Fields::NamedFields(fields) => Frame::fields(fields),
+            Fields::UnnamedFields(elements) => Frame::elements(elements),
+            Fields::Empty => return Ok(Step::Value(Vec::<u8>::new().into())),
+        },
+    };
+    Ok(Step::Frame(frame))
+}
+
+/// Deserializes a value starting at root, calling start for every value to either read it or begin a composite value
+pub(crate) fn deserialize_with<'a, T>(
+    buffer: &mut &[u8],
+    limits: &DecodeLimits,
+    root: &'a T,
+    start: impl FnMut(&mut &[u8], &'a T, &mut Budget) -> std::io::Result<Step<'a, T>>,
+) -> std::io::Result<serde_json::Value> {
+    deserialize_frames(buffer, &mut Budget::new(limits, buffer), root, start).map_err(|interrupted| interrupted.error)
+}
+
+/// Deserializes a value of the declaration within a budget shared with the values around it, such as the other parts
+/// of a projection
+pub(crate) fn deserialize_within<'a>(
+    buffer: &mut &[u8],
+    budget: &mut Budget,
+    schema: &'a BorshSchemaContainer,
+    sizes: &EncodedSizes<'a>,
+    declaration: &'a Declaration,
+) -> std::io::Result<serde_json::Value> {
+    deserialize_frames(buffer, budget, declaration, |buffer, declaration, budget| {
+        start_declaration(buffer, schema, sizes, declaration, budget)
+    })
+    .map_err(|interrupted| interrupted.error)
+}
+
+/// Where deserializing stopped on an error, with the composite values it was inside
+pub(crate) struct Interrupted<'a, T> {
+    pub(crate) error: Error,
+    /// The composite values being built, outermost first
+    pub(crate) stack: Vec<Frame<'a, T>>,
+    /// The value that failed
+    pub(crate) failed: &'a T,
+    /// The offset of the value that failed from the start of the input
+    pub(crate) offset: usize,
+}
+
+impl<T> Interrupted<'_, T> {
+    /// Returns the values built so far, with the marker in place of the value that failed
+    pub(crate) fn partial_value(self, marker: serde_json::Value) -> serde_json::Value {
+        let mut value = marker;
+        for mut frame in self.stack.into_iter().rev() {
+            frame.push(value);
+            value = frame.finish();
+        }
+        value
+    }
+}
+
+fn deserialize_frames<'a, T>(
+    buffer: &mut &[u8],
+    budget: &mut Budget,
+    root: &'a T,
+    mut start: impl FnMut(&mut &[u8], &'a T, &mut Budget) -> std::io::Result<Step<'a, T>>,
+) -> Result<serde_json::Value, Interrupted<'a, T>> {
+    let outer_depth = budget.depth;
+
+    // Composite values are tracked on an explicit stack instead of recursing, so deeply nested input can't overflow
+    // the native stack.
+    let mut stack: Vec<Frame<T>> = Vec::new();
+    let mut next = root;
+    loop {
+        let offset = budget.start_length - buffer.len();
+        let mut value = match start_value(buffer, next, &mut stack, outer_depth, budget, &mut start) {
+            Ok(value) => value,
+            Err(error) => return Err(Interrupted { error, stack, failed: next, offset }),
+        };
+
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return Ok(value.expect("A value is completed when the stack is empty"));
+            };
+            if let Some(value) = value.take() {
+                frame.push(value);
+            }
+            match frame.next_child() {
+                Some(child) => {
+                    next = child;
+                    break;
+                }
+                None => {
+                    let frame = stack.pop().expect("Stack is not empty");
+                    value = Some(frame.finish());
                 }
-            } else {
-                debug!("Can't deserialize unknown type: {:?}. Using Value::Null", declaration);
-                Ok(serde_json::Value::Null)
             }
         }
     }
 }
 
+/// Reads a value, or begins a composite value on the stack and returns None
+fn start_value<'a, T>(
+    buffer: &mut &[u8],
+    next: &'a T,
+    stack: &mut Vec<Frame<'a, T>>,
+    outer_depth: usize,
+    budget: &mut Budget,
+    start: &mut impl FnMut(&mut &[u8], &'a T, &mut Budget) -> std::io::Result<Step<'a, T>>,
+) -> std::io::Result<Option<serde_json::Value>> {
+    budget.add_node()?;
+    budget.depth = outer_depth + stack.len();
+    let step = start(buffer, next, budget)?;
+    budget.check_consumed(buffer)?;
+    match step {
+        Step::Value(value) => Ok(Some(value)),
+        Step::Frame(frame) => {
+            budget.check_depth()?;
+            stack.push(frame);
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) fn deserialize_to_serde_json(
+    buffer: &mut &[u8],
+    schema: &BorshSchemaContainer,
+    declaration: &borsh::schema::Declaration,
+    limits: &DecodeLimits,
+) -> std::io::Result<serde_json::Value> {
+    deserialize_within(buffer, &mut Budget::new(limits, buffer), schema, &EncodedSizes::new(schema), declaration)
+}
+
 /// Deserializes borsh serialized bytes to serde_json::Value using the provided schema
 pub fn deserialize_from_schema(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> std::io::Result<serde_json::Value> {
-    deserialize_to_serde_json(buffer, schema, schema.declaration())
-}
\ No newline at end of file
+    deserialize_to_serde_json(buffer, schema, schema.declaration(), &DecodeLimits::default())
+}
+
+/// Deserializes borsh serialized bytes to serde_json::Value using the provided schema, failing if structs, tuples,
+/// sequences and enums are nested deeper than max_depth
+pub fn deserialize_from_schema_with_max_depth(buffer: &mut &[u8], schema: &BorshSchemaContainer, max_depth: usize) -> std::io::Result<serde_json::Value> {
+    let limits = DecodeLimits {
+        max_depth,
+        ..DecodeLimits::default()
+    };
+    deserialize_to_serde_json(buffer, schema, schema.declaration(), &limits)
+}
+
+/// Deserializes borsh serialized bytes to serde_json::Value using the provided schema, failing as soon as the input
+/// exceeds any of the limits
+pub fn deserialize_from_schema_with_limits(buffer: &mut &[u8], schema: &BorshSchemaContainer, limits: &DecodeLimits) -> std::io::Result<serde_json::Value> {
+    deserialize_to_serde_json(buffer, schema, schema.declaration(), limits)
+}
+
+/// The key of the marker that partial deserialization puts in place of the value that failed
+pub const PARTIAL_ERROR_KEY: &str = "$error";
+
+/// A value deserialized as far as the input allowed
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialValue {
+    /// The value, complete or with a marker in place of the value that failed
+    pub value: serde_json::Value,
+    /// The offset of the value that failed, or None if deserializing succeeded
+    pub failed_at: Option<usize>,
+}
+
+/// Deserializes as much as possible of a value, for inspecting truncated or corrupted data. Where deserializing fails,
+/// the value gets a marker `{"$error": {"error": ..., "expected": ..., "offset": ..., "remaining": ...}}` holding the
+/// error, the declaration being read, its offset and the bytes from there on as hex. Structs hold the fields before the
+/// marker, sequences the elements before it and enums their variant. The buffer is left at the start of the value that
+/// failed.
+pub fn deserialize_from_schema_partial(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> PartialValue {
+    deserialize_from_schema_partial_with_limits(buffer, schema, &DecodeLimits::default())
+}
+
+/// Like deserialize_from_schema_partial, but stops where the input exceeds any of the limits
+pub fn deserialize_from_schema_partial_with_limits(buffer: &mut &[u8], schema: &BorshSchemaContainer, limits: &DecodeLimits) -> PartialValue {
+    let input = *buffer;
+    let sizes = EncodedSizes::new(schema);
+    let result = deserialize_frames(buffer, &mut Budget::new(limits, buffer), schema.declaration(), |buffer, declaration, budget| {
+        start_declaration(buffer, schema, &sizes, declaration, budget)
+    });
+    match result {
+        Ok(value) => PartialValue { value, failed_at: None },
+        Err(interrupted) => {
+            let offset = interrupted.offset;
+            *buffer = &input[offset..];
+            let marker = json!({PARTIAL_ERROR_KEY: {
+                "error": interrupted.error.to_string(),
+                "expected": interrupted.failed,
+                "offset": offset,
+                "remaining": hex(buffer),
+            }});
+            PartialValue {
+                value: interrupted.partial_value(marker),
+                failed_at: Some(offset),
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use borsh::schema::BorshSchemaContainer;
+use serde_json::{json, Value};
+
+use crate::anchor_idl::{account_discriminator, instruction_discriminator};
+use crate::borsh_schema_util::hex;
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::errors::DiscriminatorError;
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// Schemas registered under the discriminator that starts their data, such as the 8 byte discriminators of Anchor
+/// accounts and instructions or the 1 byte tags of native programs. Discriminators may have different lengths, but no
+/// discriminator may start with another, so every input matches at most one schema.
+///
+/// ```ignore
+/// let mut dispatcher = DiscriminatorDispatcher::new();
+/// dispatcher.register_anchor_account("Vault", vault_schema)?;
+/// dispatcher.register("Transfer", [3], transfer_schema)?;
+/// let decoded = dispatcher.decode(&account_data)?; // {"type": "Vault", "value": {...}}
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DiscriminatorDispatcher {
+    entries: Vec<DiscriminatorEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct DiscriminatorEntry {
+    name: String,
+    discriminator: Vec<u8>,
+    schema: BorshSchemaContainer,
+}
+
+impl DiscriminatorDispatcher {
+    pub fn new() -> DiscriminatorDispatcher {
+        DiscriminatorDispatcher::default()
+    }
+
+    /// Registers a schema under a name and the discriminator its data starts with. The registration is refused if the
+    /// name is taken, the discriminator is empty, or it starts with or is the start of a registered discriminator.
+    pub fn register(&mut self, name: &str, discriminator: impl Into<Vec<u8>>, schema: BorshSchemaContainer) -> Result<(), DiscriminatorError> {
+        let discriminator = discriminator.into();
+        if discriminator.is_empty() {
+            return Err(DiscriminatorError::Empty(name.to_string()));
+        }
+        if self.entries.iter().any(|entry| entry.name == name) {
+            return Err(DiscriminatorError::DuplicateName(name.to_string()));
+        }
+        if let Some(entry) = self.entries.iter().find(|entry| entry.discriminator.starts_with(&discriminator) || discriminator.starts_with(&entry.discriminator)) {
+            return Err(DiscriminatorError::Conflict(hex(&discriminator), name.to_string(), entry.name.clone()));
+        }
+        self.entries.push(DiscriminatorEntry {
+            name: name.to_string(),
+            discriminator,
+            schema,
+        });
+        Ok(())
+    }
+
+    /// Registers an Anchor account under the discriminator Anchor computes from its name
+    pub fn register_anchor_account(&mut self, name: &str, schema: BorshSchemaContainer) -> Result<(), DiscriminatorError> {
+        self.register(name, account_discriminator(name), schema)
+    }
+
+    /// Registers the arguments of an Anchor instruction under the discriminator Anchor computes from its name
+    pub fn register_anchor_instruction(&mut self, name: &str, schema: BorshSchemaContainer) -> Result<(), DiscriminatorError> {
+        self.register(name, instruction_discriminator(name), schema)
+    }
+
+    /// Returns the names and discriminators of the registered schemas, in the order they were registered
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries.iter().map(|entry| (entry.name.as_str(), entry.discriminator.as_slice()))
+    }
+
+    pub fn schema(&self, name: &str) -> Option<&BorshSchemaContainer> {
+        self.entry(name).ok().map(|entry| &entry.schema)
+    }
+
+    /// Returns the name and schema of the data by its discriminator
+    pub fn resolve(&self, data: &[u8]) -> Result<(&str, &BorshSchemaContainer), DiscriminatorError> {
+        let entry = self.find(data)?;
+        Ok((entry.name.as_str(), &entry.schema))
+    }
+
+    /// Picks the schema by the discriminator at the start of data and decodes the rest, which may be longer than the
+    /// value as accounts often are. Returns an object with the name of the schema as `type` and the value as `value`.
+    pub fn decode(&self, data: &[u8]) -> Result<Value, DiscriminatorError> {
+        let (name, value) = self.decode_with_name(data)?;
+        Ok(json!({"type": name, "value": value}))
+    }
+
+    /// Like decode, but returns the name and value separately
+    pub fn decode_with_name(&self, data: &[u8]) -> Result<(String, Value), DiscriminatorError> {
+        let entry = self.find(data)?;
+        let value = deserialize_from_schema(&mut &data[entry.discriminator.len()..], &entry.schema)?;
+        Ok((entry.name.clone(), value))
+    }
+
+    /// Serializes a value of the named schema, with its discriminator first
+    pub fn encode(&self, name: &str, value: &Value) -> Result<Vec<u8>, DiscriminatorError> {
+        let entry = self.entry(name)?;
+        let mut bytes = entry.discriminator.clone();
+        serialize_serde_json_to_borsh(&mut bytes, value, &entry.schema).map_err(|error| DiscriminatorError::Encode(format!("{error:#}")))?;
+        Ok(bytes)
+    }
+
+    fn find(&self, data: &[u8]) -> Result<&DiscriminatorEntry, DiscriminatorError> {
+        self.entries.iter().find(|entry| data.starts_with(&entry.discriminator)).ok_or_else(|| {
+            let longest = self.entries.iter().map(|entry| entry.discriminator.len()).max().unwrap_or_default();
+            DiscriminatorError::Unknown(hex(&data[..longest.min(data.len())]))
+        })
+    }
+
+    fn entry(&self, name: &str) -> Result<&DiscriminatorEntry, DiscriminatorError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| DiscriminatorError::NotFound(name.to_string()))
+    }
+}
@@ -1,6 +1,8 @@
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+use crate::schema_compatibility::BreakingChange;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum ExpectationError {
     #[error("Expected null")]
     Null,
@@ -24,6 +26,308 @@ pub enum ExpectationError {
     Object
 }
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ViolationReason {
+    #[error(transparent)]
+    Expected(#[from] ExpectationError),
+
+    #[error("Missing property {0}")]
+    MissingProperty(String),
+
+    #[error("Value {0} is out of range for {1}")]
+    OutOfRange(String, String),
+
+    #[error("Expected array of length {0}, found length {1}")]
+    WrongLength(u64, u64),
+
+    #[error("Length {0} is outside the allowed range {1:?}")]
+    LengthOutOfRange(u64, std::ops::RangeInclusive<u64>),
+
+    #[error("Unknown variant {0}")]
+    UnknownVariant(String),
+
+    #[error("Unknown type {0}")]
+    UnknownType(String),
+
+    #[error("Nested deeper than the maximum depth of {0}")]
+    TooDeep(usize),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SchemaIssue {
+    #[error("Declaration {0} referenced by {1} has no definition")]
+    DanglingReference(String, String),
+
+    #[error("Primitive {0} is not supported")]
+    UnsupportedPrimitive(String),
+
+    #[error("{0} contains itself unconditionally and can't be decoded")]
+    InfiniteRecursion(String),
+
+    #[error("Sequence {0} has no length prefix, but its length range {1:?} is not a single length")]
+    InconsistentFixedLength(String, std::ops::RangeInclusive<u64>),
+
+    #[error("Sequence {0} has an empty length range")]
+    EmptyLengthRange(String),
+
+    #[error("{0} has an unsupported length or tag width of {1} bytes")]
+    UnsupportedWidth(String, u8),
+
+    #[error("Enum {0} has duplicate discriminant {1}")]
+    DuplicateDiscriminant(String, i64),
+
+    #[error("Enum {0} has duplicate variant {1}")]
+    DuplicateVariant(String, String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ViewError {
+    #[error("{0} has no field {1}")]
+    NoField(String, String),
+
+    #[error("Index {0} is out of bounds for {1} of length {2}")]
+    IndexOutOfBounds(u64, String, u64),
+
+    #[error("Variant {0} is not the active variant {1}")]
+    InactiveVariant(String, String),
+
+    #[error("Expected {0}, found {1}")]
+    WrongType(String, String),
+
+    #[error("Unexpected end of input reading {0}")]
+    UnexpectedEnd(String),
+
+    #[error("Unknown discriminant {0} for {1}")]
+    UnknownDiscriminant(u64, String),
+
+    #[error("Invalid value for {0}")]
+    InvalidValue(String),
+
+    #[error("Unsupported type {0}")]
+    Unsupported(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    #[error("Invalid path {0}: {1}")]
+    Invalid(String, String),
+
+    #[error("Path {0} does not exist: {1} has no field or variant {2}")]
+    NoSuchField(String, String, String),
+
+    #[error("Path {0} does not exist: {1} can't be indexed")]
+    NotIndexable(String, String),
+
+    #[error("Path {0} does not exist: {1} is not a sequence, so it can't be indexed with [*]")]
+    NotASequence(String, String),
+
+    #[error("Path {0} does not exist: index {1} is out of bounds for {2}")]
+    IndexOutOfBounds(String, usize, String),
+}
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("Invalid schema name {0}")]
+    InvalidName(String),
+
+    #[error("Schema {0} not found")]
+    NameNotFound(String),
+
+    #[error("Schema {0} version {1} not found")]
+    NotFound(String, u32),
+
+    #[error("Schema with fingerprint {0} not found")]
+    FingerprintNotFound(String),
+
+    #[error("Schema {0} version {1} already exists")]
+    AlreadyExists(String, u32),
+
+    #[error("Schema {name} version {version} has {} breaking change(s) from version {previous}", changes.len())]
+    BreakingChange { name: String, version: u32, previous: u32, changes: Vec<BreakingChange> },
+
+    #[error("Schema registry request failed: {0}")]
+    Http(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum IdlError {
+    #[error("Invalid IDL: {0}")]
+    Invalid(String),
+
+    #[error("Type {0} is not defined in the IDL")]
+    UndefinedType(String),
+
+    #[error("{0} is not supported: {1}")]
+    Unsupported(String, String),
+
+    #[error("Account {0} not found")]
+    AccountNotFound(String),
+
+    #[error("Instruction {0} not found")]
+    InstructionNotFound(String),
+
+    #[error("Data of {0} bytes is too short for a discriminator")]
+    MissingDiscriminator(usize),
+
+    #[error("Unknown discriminator {0}")]
+    UnknownDiscriminator(String),
+
+    #[error("Encoding failed: {0}")]
+    Encode(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum NearAbiError {
+    #[error("Invalid ABI: {0}")]
+    Invalid(String),
+
+    #[error("Function {0} not found")]
+    FunctionNotFound(String),
+
+    #[error("Function {0} takes JSON arguments")]
+    JsonArgs(String),
+
+    #[error("Function {0} returns JSON")]
+    JsonResult(String),
+
+    #[error("Function {0} returns nothing")]
+    NoResult(String),
+
+    #[error("{0} has conflicting definitions in the arguments of {1}")]
+    ConflictingDefinition(String, String),
+
+    #[error("{0} bytes left over after decoding")]
+    TrailingBytes(usize),
+
+    #[error("Encoding failed: {0}")]
+    Encode(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum DiscriminatorError {
+    #[error("Discriminator of {0} is empty")]
+    Empty(String),
+
+    #[error("{0} is already registered")]
+    DuplicateName(String),
+
+    #[error("Discriminator {0} of {1} conflicts with the discriminator of {2}")]
+    Conflict(String, String, String),
+
+    #[error("Unknown discriminator {0}")]
+    Unknown(String),
+
+    #[error("{0} is not registered")]
+    NotFound(String),
+
+    #[error("Encoding failed: {0}")]
+    Encode(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum RecordStreamError {
+    #[error("Record {0} at byte {1} is invalid: {2}")]
+    Invalid(usize, u64, std::io::Error),
+
+    #[error("Record {0} at byte {1} has {2} bytes left over after decoding")]
+    TrailingBytes(usize, u64, usize),
+
+    #[error("Record {0} at byte {1} is longer than the maximum of {2} bytes")]
+    TooLong(usize, u64, usize),
+
+    #[error("Record {0} at byte {1} is truncated")]
+    Truncated(usize, u64),
+
+    #[error("Reading record {0} at byte {1} failed: {2}")]
+    Io(usize, u64, std::io::Error),
+}
+
+impl RecordStreamError {
+    /// Returns the index of the record that failed
+    pub fn index(&self) -> usize {
+        match self {
+            RecordStreamError::Invalid(index, ..)
+            | RecordStreamError::TrailingBytes(index, ..)
+            | RecordStreamError::TooLong(index, ..)
+            | RecordStreamError::Truncated(index, ..)
+            | RecordStreamError::Io(index, ..) => *index,
+        }
+    }
+
+    /// Returns the byte offset in the stream where the record that failed starts
+    pub fn offset(&self) -> u64 {
+        match self {
+            RecordStreamError::Invalid(_, offset, _)
+            | RecordStreamError::TrailingBytes(_, offset, _)
+            | RecordStreamError::TooLong(_, offset, _)
+            | RecordStreamError::Truncated(_, offset)
+            | RecordStreamError::Io(_, offset, _) => *offset,
+        }
+    }
+
+    /// Whether the record itself is corrupt, as opposed to the stream being truncated or unreadable
+    pub fn is_corrupt_record(&self) -> bool {
+        matches!(self, RecordStreamError::Invalid(..) | RecordStreamError::TrailingBytes(..) | RecordStreamError::TooLong(..))
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[derive(Error, Debug)]
+pub enum BatchDecodeError {
+    #[error("Record {0} is invalid: {1}")]
+    Invalid(usize, std::io::Error),
+
+    #[error("Record {0} has {1} bytes left over after decoding")]
+    TrailingBytes(usize, usize),
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Error, Debug)]
+pub enum AsyncCodecError {
+    #[error("Frame of {0} bytes exceeds the maximum of {1} bytes")]
+    FrameTooLong(usize, usize),
+
+    #[error("Stream ended inside a frame, {0} bytes buffered")]
+    IncompleteFrame(usize),
+
+    #[error("{0} bytes left over after decoding")]
+    TrailingBytes(usize),
+
+    #[error("Encoding failed: {0}")]
+    Encode(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "arrow")]
+#[derive(Error, Debug)]
+pub enum ArrowExportError {
+    #[error("{0} can't be represented in Arrow: {1}")]
+    Unsupported(String, String),
+
+    #[error("Invalid record {0}: {1}")]
+    InvalidRecord(usize, std::io::Error),
+
+    #[error(transparent)]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    #[error("Writing Parquet failed: {0}")]
+    Parquet(String),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,5 +341,7 @@ mod tests {
         assert_eq!(format!("{}", ExpectationError::String), "Expected string");
         assert_eq!(format!("{}", ExpectationError::Boolean), "Expected boolean");
         assert_eq!(format!("{}", ExpectationError::Object), "Expected object");
+        assert_eq!(format!("{}", RegistryError::NotFound("person".to_string(), 2)), "Schema person version 2 not found");
+        assert_eq!(format!("{}", RegistryError::AlreadyExists("person".to_string(), 2)), "Schema person version 2 already exists");
     }
 }
\ No newline at end of file
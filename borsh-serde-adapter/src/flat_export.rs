@@ -0,0 +1,307 @@
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde_json::Value;
+
+use crate::borsh_schema_util::definition;
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::schema_path::{format_path, PathSegment};
+
+/// How sequences with a length prefix are flattened. Arrays of a fixed length always get a column per element, and
+/// sequences and arrays of u8 are a single column of hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceHandling {
+    /// A single column, such as `tags`, with the elements joined by the separator
+    Join(String),
+    /// A row per element, with the element's columns under a path such as `tags[*]`. A record with several exploded
+    /// sequences gets a row for every combination of their elements, and a record with an empty one gets a single row
+    /// with those columns empty.
+    Explode,
+    /// Columns for the first n elements, `tags[0]` to `tags[n-1]`, dropping the rest
+    Truncate(usize),
+}
+
+/// How enums other than Option are flattened. An Option is flattened as its contents, which are empty for None.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VariantHandling {
+    /// A column with the name of the active variant, such as `status`, followed by the columns of every variant's
+    /// contents, such as `status.Active.since`, which are empty unless that variant is active
+    #[default]
+    Columns,
+    /// A single column with the enum value as JSON
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatOptions {
+    pub sequences: SequenceHandling,
+    pub variants: VariantHandling,
+}
+
+impl Default for FlatOptions {
+    fn default() -> Self {
+        FlatOptions {
+            sequences: SequenceHandling::Join(";".to_string()),
+            variants: VariantHandling::Columns,
+        }
+    }
+}
+
+/// The columns of a flat table of values of a schema, and how values fill them. Column names are paths as accepted by
+/// parse_path. Values nested in a recursive type are a single column of JSON.
+///
+/// ```ignore
+/// let table = FlatSchema::compile(&container, &FlatOptions::default());
+/// println!("{}", table.columns().join(","));
+/// for row in table.decode_rows(&mut record.as_slice())? {
+///     println!("{}", row.join(","));
+/// }
+/// ```
+pub struct FlatSchema<'a> {
+    schema: &'a BorshSchemaContainer,
+    columns: Vec<String>,
+    root: Flat,
+}
+
+impl<'a> FlatSchema<'a> {
+    pub fn compile(schema: &'a BorshSchemaContainer, options: &FlatOptions) -> FlatSchema<'a> {
+        let mut compiler = Compiler {
+            schema,
+            options,
+            columns: Vec::new(),
+            visiting: Vec::new(),
+        };
+        let root = compiler.compile(schema.declaration(), &mut Vec::new());
+        FlatSchema {
+            schema,
+            columns: compiler.columns,
+            root,
+        }
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Returns the rows of a value of the schema, as produced by deserialize_from_schema. There is one row unless a
+    /// sequence is exploded.
+    pub fn rows(&self, value: &Value) -> Vec<Vec<String>> {
+        let mut rows = vec![vec![String::new(); self.columns.len()]];
+        self.root.fill(value, &mut rows);
+        rows
+    }
+
+    /// Deserializes a borsh serialized value of the schema and returns its rows
+    pub fn decode_rows(&self, buffer: &mut &[u8]) -> std::io::Result<Vec<Vec<String>>> {
+        Ok(self.rows(&deserialize_from_schema(buffer, self.schema)?))
+    }
+}
+
+/// Decodes borsh serialized records of the schema and writes them as CSV with a header of column names. Returns the
+/// number of rows written.
+#[cfg(feature = "csv")]
+pub fn write_csv<W: std::io::Write, R: AsRef<[u8]>>(
+    writer: W,
+    schema: &BorshSchemaContainer,
+    records: impl IntoIterator<Item = R>,
+    options: &FlatOptions,
+) -> anyhow::Result<usize> {
+    use anyhow::Context;
+
+    let table = FlatSchema::compile(schema, options);
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(table.columns())?;
+    let mut written = 0;
+    for (index, record) in records.into_iter().enumerate() {
+        let rows = table.decode_rows(&mut record.as_ref()).with_context(|| format!("Invalid record {index}"))?;
+        for row in &rows {
+            writer.write_record(row)?;
+        }
+        written += rows.len();
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+/// A value of the schema mapped to columns
+enum Flat {
+    /// A single column, holding primitives as they are and anything else as JSON
+    Cell(usize),
+    /// A single column of hex, for sequences of u8
+    Bytes(usize),
+    Joined {
+        column: usize,
+        separator: String,
+    },
+    /// Fields of a struct or elements of a tuple or array, by their name or index
+    Fields(Vec<(PathSegment, Flat)>),
+    Optional(Box<Flat>),
+    Variants {
+        column: usize,
+        variants: Vec<(String, Flat)>,
+    },
+    Exploded(Box<Flat>),
+}
+
+impl Flat {
+    fn fill(&self, value: &Value, rows: &mut Vec<Vec<String>>) {
+        match self {
+            Flat::Cell(column) => set(rows, *column, cell(value)),
+            Flat::Bytes(column) => {
+                let bytes: Option<String> = value
+                    .as_array()
+                    .and_then(|bytes| bytes.iter().map(|byte| Some(format!("{:02x}", byte.as_u64().filter(|byte| *byte <= 0xff)?))).collect());
+                set(rows, *column, bytes.unwrap_or_else(|| cell(value)));
+            }
+            Flat::Joined { column, separator } => {
+                let joined = match value.as_array() {
+                    Some(elements) => elements.iter().map(cell).collect::<Vec<String>>().join(separator),
+                    None => cell(value),
+                };
+                set(rows, *column, joined);
+            }
+            Flat::Fields(fields) => {
+                for (segment, field) in fields {
+                    let field_value = match segment {
+                        PathSegment::Field(name) => value.get(name),
+                        PathSegment::Index(index) => value.get(index),
+                        PathSegment::Wildcard => None,
+                    };
+                    if let Some(field_value) = field_value {
+                        field.fill(field_value, rows);
+                    }
+                }
+            }
+            Flat::Optional(contents) => {
+                if let Some(contents_value) = value.get("Some") {
+                    contents.fill(contents_value, rows);
+                }
+            }
+            Flat::Variants { column, variants } => {
+                let Some((name, contents_value)) = value.as_object().filter(|variant| variant.len() == 1).and_then(|variant| variant.iter().next()) else {
+                    return set(rows, *column, cell(value));
+                };
+                set(rows, *column, name.clone());
+                if let Some((_, contents)) = variants.iter().find(|(variant_name, _)| variant_name == name) {
+                    contents.fill(contents_value, rows);
+                }
+            }
+            Flat::Exploded(element) => {
+                let Some(elements) = value.as_array().filter(|elements| !elements.is_empty()) else {
+                    return;
+                };
+                *rows = rows
+                    .drain(..)
+                    .flat_map(|row| {
+                        elements.iter().flat_map(move |element_value| {
+                            let mut element_rows = vec![row.clone()];
+                            element.fill(element_value, &mut element_rows);
+                            element_rows
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+}
+
+fn set(rows: &mut [Vec<String>], column: usize, cell: String) {
+    for row in rows {
+        row[column].clone_from(&cell);
+    }
+}
+
+/// Strings are given without quotes, null as an empty cell and arrays and objects as JSON
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+struct Compiler<'a> {
+    schema: &'a BorshSchemaContainer,
+    options: &'a FlatOptions,
+    columns: Vec<String>,
+    visiting: Vec<&'a Declaration>,
+}
+
+impl<'a> Compiler<'a> {
+    fn column(&mut self, path: &[PathSegment]) -> usize {
+        self.columns.push(match path.is_empty() {
+            true => "value".to_string(),
+            false => format_path(path),
+        });
+        self.columns.len() - 1
+    }
+
+    fn compile(&mut self, declaration: &'a Declaration, path: &mut Vec<PathSegment>) -> Flat {
+        let Some(definition) = definition(self.schema, declaration) else {
+            return Flat::Cell(self.column(path));
+        };
+        if self.visiting.contains(&declaration) {
+            return Flat::Cell(self.column(path));
+        }
+
+        self.visiting.push(declaration);
+        let flat = match definition {
+            Definition::Sequence { elements, .. } if elements == "u8" => Flat::Bytes(self.column(path)),
+            Definition::Sequence { length_width: 0, length_range, elements } => {
+                self.fields(path, (0..*length_range.end() as usize).map(|index| (PathSegment::Index(index), elements)))
+            }
+            Definition::Sequence { elements, .. } => match &self.options.sequences {
+                SequenceHandling::Join(separator) => Flat::Joined {
+                    column: self.column(path),
+                    separator: separator.clone(),
+                },
+                SequenceHandling::Truncate(count) => self.fields(path, (0..*count).map(|index| (PathSegment::Index(index), elements))),
+                SequenceHandling::Explode => {
+                    path.push(PathSegment::Wildcard);
+                    let element = self.compile(elements, path);
+                    path.pop();
+                    Flat::Exploded(Box::new(element))
+                }
+            },
+            Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) } => {
+                self.fields(path, elements.iter().enumerate().map(|(index, element)| (PathSegment::Index(index), element)))
+            }
+            Definition::Struct { fields: Fields::NamedFields(fields) } => {
+                self.fields(path, fields.iter().map(|(name, field)| (PathSegment::Field(name.clone()), field)))
+            }
+            Definition::Struct { fields: Fields::Empty } => Flat::Fields(Vec::new()),
+            Definition::Enum { variants, .. } => match variants.as_slice() {
+                [(_, none, none_declaration), (_, some, contents)] if none == "None" && some == "Some" && none_declaration == "()" => {
+                    Flat::Optional(Box::new(self.compile(contents, path)))
+                }
+                _ if self.options.variants == VariantHandling::Json => Flat::Cell(self.column(path)),
+                _ => {
+                    let column = self.column(path);
+                    let variants = variants
+                        .iter()
+                        .map(|(_, name, contents)| {
+                            path.push(PathSegment::Field(name.clone()));
+                            let flat = self.compile(contents, path);
+                            path.pop();
+                            (name.clone(), flat)
+                        })
+                        .collect();
+                    Flat::Variants { column, variants }
+                }
+            },
+            Definition::Primitive(_) => Flat::Cell(self.column(path)),
+        };
+        self.visiting.pop();
+        flat
+    }
+
+    fn fields(&mut self, path: &mut Vec<PathSegment>, fields: impl Iterator<Item = (PathSegment, &'a Declaration)>) -> Flat {
+        let fields = fields
+            .map(|(segment, field)| {
+                path.push(segment.clone());
+                let flat = self.compile(field, path);
+                path.pop();
+                (segment, flat)
+            })
+            .collect();
+        Flat::Fields(fields)
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt::Write;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::borsh_schema_util::{hex, EncodedSizes, PrimitiveType};
+use crate::deserialize_adapter::{deserialize_primitive, read_width, DecodeLimits, DEFAULT_MAX_DEPTH};
+use crate::schema_path::{format_path, PathSegment};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// What the bytes of a span hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanKind {
+    /// A primitive value, or a whole sequence of u8
+    Value,
+    /// The length prefix of a sequence
+    Length,
+    /// The tag of an enum, whose value is the name of the variant
+    Tag,
+}
+
+/// A range of bytes and what they decoded to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// The path of the value, as accepted by parse_path, with variant names as fields
+    pub path: String,
+    pub declaration: Declaration,
+    pub kind: SpanKind,
+    /// The decoded value, with sequences of u8 given as a hex string
+    pub value: Value,
+}
+
+/// Where decoding stopped and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeFailure {
+    pub offset: usize,
+    pub path: String,
+    /// What was being read, such as `u64` or `length of Vec<u8>`
+    pub expected: String,
+    pub reason: String,
+}
+
+/// The bytes of a borsh serialized value annotated with the schema. Decoding goes as far as it can, so the spans cover
+/// the bytes up to the failure, if there is one, and the rest are either the failure's or left over.
+///
+/// ```ignore
+/// let dump = Hexdump::annotate(&bytes, &container);
+/// println!("{}", dump.to_text());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hexdump {
+    pub spans: Vec<Span>,
+    pub failure: Option<DecodeFailure>,
+    /// The offset of the bytes left over after the value, if there are any and decoding succeeded
+    pub trailing: Option<usize>,
+    /// The annotated bytes, serialized as hex
+    #[serde(serialize_with = "serialize_hex")]
+    pub bytes: Vec<u8>,
+}
+
+impl Hexdump {
+    pub fn annotate(bytes: &[u8], schema: &BorshSchemaContainer) -> Hexdump {
+        let mut annotator = Annotator {
+            schema,
+            sizes: EncodedSizes::new(schema),
+            bytes,
+            offset: 0,
+            spans: Vec::new(),
+        };
+        let failure = annotator.run().err();
+        let trailing = (failure.is_none() && annotator.offset < bytes.len()).then_some(annotator.offset);
+        Hexdump {
+            spans: annotator.spans,
+            failure,
+            trailing,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Renders a line per span with its offset, bytes and annotation, wrapping bytes after 16 per line. The failure is
+    /// marked with `!!` and shows up to a line of the bytes from where it happened.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for span in &self.spans {
+            let annotation = match span.kind {
+                SpanKind::Value => format!("{}: {} = {}", display_path(&span.path), span.declaration, span.value),
+                SpanKind::Length => format!("{}: length of {} = {}", display_path(&span.path), span.declaration, span.value),
+                SpanKind::Tag => format!("{}: variant of {} = {}", display_path(&span.path), span.declaration, display_value(&span.value)),
+            };
+            for (line, chunk) in self.bytes[span.start..span.end].chunks(BYTES_PER_LINE).enumerate() {
+                let annotation = if line == 0 { annotation.as_str() } else { "" };
+                write_line(&mut text, "  ", span.start + line * BYTES_PER_LINE, chunk, annotation);
+            }
+        }
+        if let Some(failure) = &self.failure {
+            let end = self.bytes.len().min(failure.offset + BYTES_PER_LINE);
+            let annotation = format!("{}: expected {}: {}", display_path(&failure.path), failure.expected, failure.reason);
+            write_line(&mut text, "!!", failure.offset, &self.bytes[failure.offset.min(end)..end], &annotation);
+        }
+        if let Some(trailing) = self.trailing {
+            let end = self.bytes.len().min(trailing + BYTES_PER_LINE);
+            let annotation = format!("{} bytes left over", self.bytes.len() - trailing);
+            write_line(&mut text, "  ", trailing, &self.bytes[trailing..end], &annotation);
+        }
+        text
+    }
+
+    /// Returns the spans, failure and bytes as JSON for tooling
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("Hexdump serializes to JSON")
+    }
+}
+
+fn write_line(text: &mut String, marker: &str, offset: usize, bytes: &[u8], annotation: &str) {
+    let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    let width = BYTES_PER_LINE * 3 - 1;
+    let line = format!("{marker}{offset:08x}  {:width$}  {annotation}", bytes.join(" "));
+    let _ = writeln!(text, "{}", line.trim_end());
+}
+
+fn display_path(path: &str) -> &str {
+    match path.is_empty() {
+        true => "(root)",
+        false => path,
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex(bytes))
+}
+
+enum Task<'a> {
+    Value {
+        declaration: &'a Declaration,
+        path: Vec<PathSegment>,
+        depth: usize,
+    },
+    /// The remaining elements of a sequence, so a long sequence is one task rather than one per element
+    Elements {
+        elements: &'a Declaration,
+        path: Vec<PathSegment>,
+        next: u64,
+        count: u64,
+        depth: usize,
+    },
+}
+
+struct Annotator<'a, 'b> {
+    schema: &'a BorshSchemaContainer,
+    sizes: EncodedSizes<'a>,
+    bytes: &'b [u8],
+    offset: usize,
+    spans: Vec<Span>,
+}
+
+impl<'a> Annotator<'a, '_> {
+    fn run(&mut self) -> Result<(), DecodeFailure> {
+        let mut pending = vec![Task::Value {
+            declaration: self.schema.declaration(),
+            path: Vec::new(),
+            depth: 0,
+        }];
+        while let Some(task) = pending.pop() {
+            let (declaration, path, depth) = match task {
+                Task::Value { declaration, path, depth } => (declaration, path, depth),
+                Task::Elements { elements, path, next, count, depth } => {
+                    if next + 1 < count {
+                        pending.push(Task::Elements {
+                            elements,
+                            path: path.clone(),
+                            next: next + 1,
+                            count,
+                            depth,
+                        });
+                    }
+                    let mut element_path = path;
+                    element_path.push(PathSegment::Index(next as usize));
+                    (elements, element_path, depth)
+                }
+            };
+            if depth > DEFAULT_MAX_DEPTH {
+                return Err(self.failure(&path, declaration, format!("nested deeper than {DEFAULT_MAX_DEPTH}")));
+            }
+
+            if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+                self.primitive(primitive, declaration, &path)?;
+                continue;
+            }
+            let child = |segment: PathSegment| {
+                let mut child_path = path.clone();
+                child_path.push(segment);
+                child_path
+            };
+            match self.schema.get_definition(declaration) {
+                Some(Definition::Sequence { length_width, length_range, elements }) => {
+                    let start = self.offset;
+                    let length = match length_width {
+                        0 => *length_range.end(),
+                        _ => {
+                            let length = self.read_width(*length_width, &path, &format!("length of {declaration}"))?;
+                            self.span(start, &path, declaration, SpanKind::Length, length.into());
+                            length
+                        }
+                    };
+                    if !length_range.contains(&length) {
+                        self.offset = start;
+                        return Err(self.failure(&path, declaration, format!("length {length} is outside the allowed range {length_range:?}")));
+                    }
+                    let element_size = self.sizes.min_size(elements);
+                    let remaining = self.bytes.len() - self.offset;
+                    if (element_size == 0 && *length_width != 0 && length > 0) || length.saturating_mul(element_size as u64) > remaining as u64 {
+                        self.offset = start;
+                        return Err(self.failure(&path, declaration, format!("{length} {elements} need more than the {remaining} bytes remaining")));
+                    }
+                    if elements == "u8" {
+                        let start = self.offset;
+                        self.offset += length as usize;
+                        self.span(start, &path, declaration, SpanKind::Value, hex(&self.bytes[start..self.offset]).into());
+                    } else if length > 0 && self.sizes.fixed_size(elements) != Some(0) {
+                        pending.push(Task::Elements {
+                            elements,
+                            path,
+                            next: 0,
+                            count: length,
+                            depth: depth + 1,
+                        });
+                    }
+                }
+                Some(Definition::Tuple { elements }) | Some(Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+                    pending.extend(elements.iter().enumerate().rev().map(|(index, element)| Task::Value {
+                        declaration: element,
+                        path: child(PathSegment::Index(index)),
+                        depth: depth + 1,
+                    }));
+                }
+                Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => {
+                    pending.extend(fields.iter().rev().map(|(name, field)| Task::Value {
+                        declaration: field,
+                        path: child(PathSegment::Field(name.clone())),
+                        depth: depth + 1,
+                    }));
+                }
+                Some(Definition::Struct { fields: Fields::Empty }) => {}
+                Some(Definition::Enum { tag_width, variants }) => {
+                    let start = self.offset;
+                    let discriminant = self.read_width(*tag_width, &path, &format!("variant of {declaration}"))?;
+                    let Some((_, variant_name, variant)) = variants.iter().find(|(variant_discriminant, ..)| *variant_discriminant as u64 == discriminant) else {
+                        self.offset = start;
+                        return Err(self.failure(&path, &format!("variant of {declaration}"), format!("unknown discriminant {discriminant}")));
+                    };
+                    self.span(start, &path, declaration, SpanKind::Tag, variant_name.clone().into());
+                    pending.push(Task::Value {
+                        declaration: variant,
+                        path: child(PathSegment::Field(variant_name.clone())),
+                        depth: depth + 1,
+                    });
+                }
+                Some(Definition::Primitive(_)) | None => {
+                    return Err(self.failure(&path, declaration, "the type has no definition the adapters can decode".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn primitive(&mut self, primitive: PrimitiveType, declaration: &Declaration, path: &[PathSegment]) -> Result<(), DecodeFailure> {
+        let start = self.offset;
+        let remaining = &self.bytes[start..];
+        let size = match primitive.fixed_size() {
+            Some(size) => size,
+            None => {
+                let length = self.read_width(4, path, &format!("length of {declaration}"))? as usize;
+                self.offset = start;
+                if length > remaining.len() - 4 {
+                    return Err(self.failure(path, declaration, format!("{length} bytes need more than the {} bytes remaining", remaining.len() - 4)));
+                }
+                length + 4
+            }
+        };
+        if size > remaining.len() {
+            return Err(self.failure(path, declaration, format!("needs {size} bytes but {} remain", remaining.len())));
+        }
+        let mut buffer = &remaining[..size];
+        let value = deserialize_primitive(&mut buffer, primitive, &DecodeLimits::default())
+            .map_err(|_| self.failure(path, declaration, format!("{} is not a valid {declaration}", hex(&remaining[..size]))))?;
+        self.offset += size;
+        if size > 0 {
+            self.span(start, path, declaration, SpanKind::Value, value);
+        }
+        Ok(())
+    }
+
+    /// Reads a length or tag, leaving the offset where it was if there aren't enough bytes
+    fn read_width(&mut self, width: u8, path: &[PathSegment], expected: &str) -> Result<u64, DecodeFailure> {
+        let mut buffer = &self.bytes[self.offset..];
+        let value = read_width(&mut buffer, width).map_err(|error| {
+            let remaining = self.bytes.len() - self.offset;
+            match remaining < width as usize {
+                true => self.failure(path, expected, format!("needs {width} bytes but {remaining} remain")),
+                false => self.failure(path, expected, error.to_string()),
+            }
+        })?;
+        self.offset += width as usize;
+        Ok(value)
+    }
+
+    fn span(&mut self, start: usize, path: &[PathSegment], declaration: &Declaration, kind: SpanKind, value: Value) {
+        self.spans.push(Span {
+            start,
+            end: self.offset,
+            path: format_path(path),
+            declaration: declaration.clone(),
+            kind,
+            value,
+        });
+    }
+
+    fn failure(&self, path: &[PathSegment], expected: &str, reason: String) -> DecodeFailure {
+        DecodeFailure {
+            offset: self.offset,
+            path: format_path(path),
+            expected: expected.to_string(),
+            reason,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::str::FromStr;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde_json::Value;
+
+use crate::borsh_schema_util::{escape_pointer_segment, PrimitiveType};
+use crate::deserialize_adapter::DEFAULT_MAX_DEPTH;
+use crate::errors::{ExpectationError, ViolationReason};
+use crate::serialize_adapter::EMPTY_OBJECT;
+
+/// A value that serialize_serde_json_to_borsh would reject or serialize incorrectly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// JSON pointer to the offending value, "" for the root
+    pub path: String,
+    pub reason: ViolationReason,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{}: {}", self.path, self.reason)
+        }
+    }
+}
+
+/// Checks a serde_json::Value against the provided schema without serializing it. Walks the schema the same way as
+/// serialize_serde_json_to_borsh, but collects every violation instead of stopping at the first one. An empty result
+/// means the value can be serialized.
+pub fn validate(value: &Value, schema: &BorshSchemaContainer) -> Vec<Violation> {
+    validate_with_max_depth(value, schema, DEFAULT_MAX_DEPTH)
+}
+
+/// Checks a serde_json::Value against the provided schema as `validate` does, reporting structs, tuples, sequences and
+/// enums nested deeper than max_depth as the serializers would refuse them
+pub fn validate_with_max_depth(value: &Value, schema: &BorshSchemaContainer, max_depth: usize) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    // Values still to be checked are kept on an explicit stack instead of recursing, so deeply nested values can't
+    // overflow the native stack. Violations found along the way are queued with them to keep the order of the walk.
+    let mut stack = vec![Check::Value { value, declaration: schema.declaration(), path: String::new(), depth: 0 }];
+    while let Some(check) = stack.pop() {
+        match check {
+            Check::Value { value, declaration, path, depth } => {
+                let Some(mut children) = validate_declaration(value, schema, declaration, &path, depth, &mut violations) else {
+                    continue;
+                };
+                if depth >= max_depth {
+                    violate(&mut violations, &path, ViolationReason::TooDeep(max_depth));
+                    continue;
+                }
+                children.reverse();
+                stack.extend(children);
+            }
+            Check::Violation { path, reason } => violate(&mut violations, &path, reason),
+        }
+    }
+    violations
+}
+
+/// A value still to be checked, or a violation to be reported once the values before it have been checked
+enum Check<'a> {
+    Value {
+        value: &'a Value,
+        declaration: &'a Declaration,
+        path: String,
+        /// The number of structs, tuples, sequences and enums the value is in
+        depth: usize,
+    },
+    Violation {
+        path: String,
+        reason: ViolationReason,
+    },
+}
+
+fn validate_unsigned<T: TryFrom<u64>>(value: &Value, type_name: &str, path: &str, violations: &mut Vec<Violation>) {
+    match value {
+        Value::Number(number) => {
+            if number.as_u64().is_none_or(|n| T::try_from(n).is_err()) {
+                violate(violations, path, ViolationReason::OutOfRange(number.to_string(), type_name.to_string()));
+            }
+        }
+        _ => violate(violations, path, ExpectationError::Number.into()),
+    }
+}
+
+fn validate_signed<T: TryFrom<i64>>(value: &Value, type_name: &str, path: &str, violations: &mut Vec<Violation>) {
+    match value {
+        Value::Number(number) => {
+            if number.as_i64().is_none_or(|n| T::try_from(n).is_err()) {
+                violate(violations, path, ViolationReason::OutOfRange(number.to_string(), type_name.to_string()));
+            }
+        }
+        _ => violate(violations, path, ExpectationError::Number.into()),
+    }
+}
+
+fn validate_from_str<T: FromStr>(value: &Value, type_name: &str, path: &str, violations: &mut Vec<Violation>) {
+    match value {
+        Value::String(string) => {
+            if T::from_str(string).is_err() {
+                violate(violations, path, ViolationReason::OutOfRange(string.clone(), type_name.to_string()));
+            }
+        }
+        _ => violate(violations, path, ExpectationError::String.into()),
+    }
+}
+
+fn violate(violations: &mut Vec<Violation>, path: &str, reason: ViolationReason) {
+    violations.push(Violation {
+        path: path.to_string(),
+        reason,
+    });
+}
+
+/// Checks the value itself against a declaration. Returns what is left to check of a composite value, or None for values
+/// that have no contents to check, so that only composite values count towards the nesting depth as in the serializers.
+fn validate_declaration<'a>(
+    value: &'a Value,
+    schema: &'a BorshSchemaContainer,
+    declaration: &'a Declaration,
+    path: &str,
+    depth: usize,
+    violations: &mut Vec<Violation>,
+) -> Option<Vec<Check<'a>>> {
+    if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+        validate_primitive(value, primitive, path, violations);
+        return None;
+    }
+    let Some(definition) = schema.get_definition(declaration) else {
+        violate(violations, path, ViolationReason::UnknownType(declaration.clone()));
+        return None;
+    };
+    let child = |value, declaration, path| Check::Value { value, declaration, path, depth: depth + 1 };
+
+    match definition {
+        Definition::Primitive(_) => {
+            violate(violations, path, ViolationReason::UnknownType(declaration.clone()));
+            None
+        }
+
+        Definition::Sequence { length_width, length_range, elements } => {
+            let Some(sequence) = value.as_array() else {
+                violate(violations, path, ExpectationError::Array.into());
+                return None;
+            };
+            let length = sequence.len() as u64;
+            if *length_width == 0 && length_range.start() == length_range.end() {
+                if length != *length_range.start() {
+                    violate(violations, path, ViolationReason::WrongLength(*length_range.start(), length));
+                }
+            } else if !length_range.contains(&length) {
+                violate(violations, path, ViolationReason::LengthOutOfRange(length, length_range.clone()));
+            }
+            Some(
+                sequence
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| child(item, elements, format!("{path}/{index}")))
+                    .collect(),
+            )
+        }
+
+        Definition::Tuple { elements } => validate_elements(value, elements, path, depth, violations),
+
+        Definition::Enum { variants, .. } => {
+            let (input_variant, variant_value) = match value {
+                Value::Object(object) if object.len() == 1 => {
+                    let (key, variant_value) = object.iter().next().expect("Object has one entry");
+                    (key.as_str(), variant_value)
+                }
+                Value::String(variant) => (variant.as_str(), &*EMPTY_OBJECT),
+                _ => {
+                    violate(violations, path, ExpectationError::Object.into());
+                    return None;
+                }
+            };
+
+            match variants.iter().find(|(_, name, _)| name == input_variant) {
+                Some((_, _, variant_declaration)) => {
+                    let variant_path = format!("{path}/{}", escape_pointer_segment(input_variant));
+                    Some(vec![child(variant_value, variant_declaration, variant_path)])
+                }
+                None => {
+                    violate(violations, path, ViolationReason::UnknownVariant(input_variant.to_string()));
+                    None
+                }
+            }
+        }
+
+        Definition::Struct { fields } => match fields {
+            Fields::NamedFields(fields) => {
+                let Some(object) = value.as_object() else {
+                    violate(violations, path, ExpectationError::Object.into());
+                    return None;
+                };
+                let checks = fields.iter().map(|(key, value_declaration)| {
+                    let property_path = format!("{path}/{}", escape_pointer_segment(key));
+                    match object.get(key.as_str()) {
+                        Some(property_value) => child(property_value, value_declaration, property_path),
+                        None => Check::Violation {
+                            path: property_path,
+                            reason: ViolationReason::MissingProperty(key.clone()),
+                        },
+                    }
+                });
+                Some(checks.collect())
+            }
+
+            Fields::UnnamedFields(fields) => {
+                if fields.len() == 1 {
+                    // Given as an array of one element like the deserializer does, or as the value itself
+                    let value = match value.as_array().map(Vec::as_slice) {
+                        Some([field_value]) => field_value,
+                        _ => value,
+                    };
+                    Some(vec![child(value, &fields[0], path.to_string())])
+                } else {
+                    validate_elements(value, fields, path, depth, violations)
+                }
+            }
+
+            Fields::Empty => None,
+        },
+    }
+}
+
+fn validate_primitive(value: &Value, primitive: PrimitiveType, path: &str, violations: &mut Vec<Violation>) {
+    let type_name = primitive.declaration();
+    match primitive {
+        PrimitiveType::U8 => validate_unsigned::<u8>(value, type_name, path, violations),
+        PrimitiveType::U16 => validate_unsigned::<u16>(value, type_name, path, violations),
+        PrimitiveType::U32 => validate_unsigned::<u32>(value, type_name, path, violations),
+        PrimitiveType::U64 => validate_unsigned::<u64>(value, type_name, path, violations),
+        PrimitiveType::U128 => validate_from_str::<u128>(value, type_name, path, violations),
+        PrimitiveType::I8 => validate_signed::<i8>(value, type_name, path, violations),
+        PrimitiveType::I16 => validate_signed::<i16>(value, type_name, path, violations),
+        PrimitiveType::I32 => validate_signed::<i32>(value, type_name, path, violations),
+        PrimitiveType::I64 => validate_signed::<i64>(value, type_name, path, violations),
+        PrimitiveType::I128 => validate_from_str::<i128>(value, type_name, path, violations),
+        PrimitiveType::F32 | PrimitiveType::F64 => {
+            if !value.is_number() {
+                violate(violations, path, ExpectationError::Number.into());
+            }
+        }
+        PrimitiveType::Unit => {}
+        PrimitiveType::String => {
+            if !value.is_string() {
+                violate(violations, path, ExpectationError::String.into());
+            }
+        }
+        PrimitiveType::Bool => {
+            if !value.is_boolean() {
+                violate(violations, path, ExpectationError::Boolean.into());
+            }
+        }
+    }
+}
+
+fn validate_elements<'a>(
+    value: &'a Value,
+    elements: &'a [Declaration],
+    path: &str,
+    depth: usize,
+    violations: &mut Vec<Violation>,
+) -> Option<Vec<Check<'a>>> {
+    let Some(array) = value.as_array() else {
+        violate(violations, path, ExpectationError::Array.into());
+        return None;
+    };
+    if array.len() != elements.len() {
+        violate(violations, path, ViolationReason::WrongLength(elements.len() as u64, array.len() as u64));
+        return None;
+    }
+    let checks = elements.iter().zip(array).enumerate().map(|(index, (declaration, value))| Check::Value {
+        value,
+        declaration,
+        path: format!("{path}/{index}"),
+        depth: depth + 1,
+    });
+    Some(checks.collect())
+}
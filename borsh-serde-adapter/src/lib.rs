@@ -114,4 +114,32 @@
 pub mod deserialize_adapter;
 pub mod serialize_adapter;
 pub mod errors;
-pub mod borsh_schema_util;
\ No newline at end of file
+pub mod borsh_schema_util;
+pub mod schema_compatibility;
+pub mod schema_registry;
+pub mod json_validator;
+pub mod schema_validator;
+pub mod schema_plan;
+pub mod borsh_view;
+pub mod schema_path;
+pub mod projection;
+pub mod patch;
+pub mod value_diff;
+pub mod schema_layout;
+pub mod serde_formats;
+pub mod flat_export;
+pub mod anchor_idl;
+pub mod near_abi;
+pub mod discriminator_dispatch;
+pub mod hexdump;
+pub mod record_stream;
+#[cfg(feature = "generator")]
+pub mod value_generator;
+#[cfg(feature = "http")]
+pub mod schema_registry_http;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+#[cfg(feature = "tokio")]
+pub mod async_codec;
+#[cfg(feature = "rayon")]
+pub mod batch_decode;
\ No newline at end of file
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde_json::Value;
+
+use crate::borsh_schema_util::definition_children;
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::errors::NearAbiError;
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// The borsh schemas in the ABI of a NEAR contract, as generated by cargo-near. Functions whose arguments are borsh
+/// serialized have a schema per argument, which are combined into a struct declared with the function's name, as
+/// near-sdk deserializes them. Schemas are read both as written by ABI version 0.4, with borsh 1 definitions, and as
+/// written by earlier versions, with borsh 0.10 definitions.
+///
+/// ```ignore
+/// let abi = NearAbi::from_json(&serde_json::from_str(&abi_json)?)?;
+/// let args = abi.decode_args("set_status", &call_args)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct NearAbi {
+    functions: Vec<AbiFunction>,
+}
+
+#[derive(Debug, Clone)]
+struct AbiFunction {
+    name: String,
+    /// None if the arguments are JSON serialized
+    args: Option<Vec<(String, BorshSchemaContainer)>>,
+    result: AbiResult,
+}
+
+#[derive(Debug, Clone)]
+enum AbiResult {
+    None,
+    Json,
+    Borsh(BorshSchemaContainer),
+}
+
+impl NearAbi {
+    pub fn from_json(abi: &Value) -> Result<NearAbi, NearAbiError> {
+        let functions = abi
+            .pointer("/body/functions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| NearAbiError::Invalid("body.functions is not a list".to_string()))?
+            .iter()
+            .map(|function| {
+                let name = function.get("name").and_then(Value::as_str).ok_or_else(|| NearAbiError::Invalid(format!("{function} has no name")))?;
+                let args = match function.get("params") {
+                    None | Some(Value::Null) => Some(Vec::new()),
+                    Some(params) => match serialization_type(params, name)? {
+                        "borsh" => Some(
+                            params
+                                .get("args")
+                                .and_then(Value::as_array)
+                                .ok_or_else(|| NearAbiError::Invalid(format!("Parameters of {name} have no args")))?
+                                .iter()
+                                .map(|arg| {
+                                    let arg_name = arg.get("name").and_then(Value::as_str).ok_or_else(|| NearAbiError::Invalid(format!("{arg} has no name")))?;
+                                    Ok((arg_name.to_string(), type_schema(arg)?))
+                                })
+                                .collect::<Result<Vec<_>, NearAbiError>>()?,
+                        ),
+                        _ => None,
+                    },
+                };
+                let result = match function.get("result") {
+                    None | Some(Value::Null) => AbiResult::None,
+                    Some(result) => match serialization_type(result, name)? {
+                        "borsh" => AbiResult::Borsh(type_schema(result)?),
+                        _ => AbiResult::Json,
+                    },
+                };
+                Ok(AbiFunction {
+                    name: name.to_string(),
+                    args,
+                    result,
+                })
+            })
+            .collect::<Result<Vec<_>, NearAbiError>>()?;
+        Ok(NearAbi { functions })
+    }
+
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.iter().map(|function| function.name.as_str())
+    }
+
+    /// Returns true if the function's arguments are borsh serialized
+    pub fn has_borsh_args(&self, name: &str) -> bool {
+        self.function(name).is_ok_and(|function| function.args.is_some())
+    }
+
+    /// Returns the schema of a function's arguments, a struct with a field per argument declared with the name of the
+    /// function. A function without arguments has an empty struct.
+    pub fn args_schema(&self, name: &str) -> Result<BorshSchemaContainer, NearAbiError> {
+        let function = self.function(name)?;
+        let args = function.args.as_ref().ok_or_else(|| NearAbiError::JsonArgs(name.to_string()))?;
+
+        let mut definitions = BTreeMap::new();
+        let mut fields = Vec::with_capacity(args.len());
+        for (arg, schema) in args {
+            for (declaration, definition) in schema.definitions() {
+                match definitions.get(declaration) {
+                    Some(existing) if existing != definition => return Err(NearAbiError::ConflictingDefinition(declaration.clone(), name.to_string())),
+                    Some(_) => {}
+                    None => {
+                        definitions.insert(declaration.clone(), definition.clone());
+                    }
+                }
+            }
+            fields.push((arg.clone(), schema.declaration().clone()));
+        }
+        if definitions.contains_key(name) {
+            return Err(NearAbiError::ConflictingDefinition(name.to_string(), name.to_string()));
+        }
+        let fields = match fields.is_empty() {
+            true => Fields::Empty,
+            false => Fields::NamedFields(fields),
+        };
+        definitions.insert(name.to_string(), Definition::Struct { fields });
+        Ok(BorshSchemaContainer::new(name.to_string(), definitions))
+    }
+
+    /// Returns the schema of the value a function returns
+    pub fn result_schema(&self, name: &str) -> Result<BorshSchemaContainer, NearAbiError> {
+        match &self.function(name)?.result {
+            AbiResult::Borsh(schema) => Ok(schema.clone()),
+            AbiResult::Json => Err(NearAbiError::JsonResult(name.to_string())),
+            AbiResult::None => Err(NearAbiError::NoResult(name.to_string())),
+        }
+    }
+
+    /// Decodes the borsh serialized arguments of a call to an object with a property per argument, or to an empty array
+    /// for a function without arguments as with any empty struct
+    pub fn decode_args(&self, name: &str, data: &[u8]) -> Result<Value, NearAbiError> {
+        decode(data, &self.args_schema(name)?)
+    }
+
+    /// Serializes the arguments of a call, given as an object with a property per argument
+    pub fn encode_args(&self, name: &str, args: &Value) -> Result<Vec<u8>, NearAbiError> {
+        let mut bytes = Vec::new();
+        serialize_serde_json_to_borsh(&mut bytes, args, &self.args_schema(name)?).map_err(|error| NearAbiError::Encode(format!("{error:#}")))?;
+        Ok(bytes)
+    }
+
+    /// Decodes the value a function returned
+    pub fn decode_result(&self, name: &str, data: &[u8]) -> Result<Value, NearAbiError> {
+        decode(data, &self.result_schema(name)?)
+    }
+
+    fn function(&self, name: &str) -> Result<&AbiFunction, NearAbiError> {
+        self.functions
+            .iter()
+            .find(|function| function.name == name)
+            .ok_or_else(|| NearAbiError::FunctionNotFound(name.to_string()))
+    }
+}
+
+/// Converts a borsh schema as serialized in a NEAR ABI to a container. Borsh 0.10 schemas declare String as `string`
+/// and the unit type as `nil`, which are renamed to the borsh 1 declarations, and their sequences, arrays and enums get
+/// the widths borsh 0.10 encoded them with.
+pub fn schema_from_abi_json(schema: &Value) -> Result<BorshSchemaContainer, NearAbiError> {
+    let invalid = |reason: &str| NearAbiError::Invalid(format!("Borsh schema {reason}"));
+    let declaration = schema.get("declaration").and_then(declaration_of).ok_or_else(|| invalid("has no declaration"))?;
+    let definitions: Vec<(&str, &Value)> = match schema.get("definitions") {
+        Some(Value::Object(definitions)) => definitions.iter().map(|(declaration, definition)| (declaration.as_str(), definition)).collect(),
+        Some(Value::Array(definitions)) => definitions
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([Value::String(declaration), definition]) => Ok((declaration.as_str(), definition)),
+                _ => Err(invalid("definitions are not pairs of a declaration and a definition")),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err(invalid("has no definitions")),
+    };
+
+    let mut converted = BTreeMap::new();
+    for (definition_declaration, definition) in definitions {
+        let definition_declaration = abi_declaration(definition_declaration);
+        let definition = abi_definition(definition).ok_or_else(|| invalid(&format!("has an invalid definition of {definition_declaration}: {definition}")))?;
+        converted.insert(definition_declaration, definition);
+    }
+
+    // Only keep what the declaration uses, so argument schemas combine without clashing over unused definitions
+    let mut used = BTreeMap::new();
+    let mut pending = vec![declaration.clone()];
+    while let Some(next) = pending.pop() {
+        if let Some(definition) = converted.remove(&next) {
+            pending.extend(definition_children(&definition).into_iter().cloned());
+            used.insert(next, definition);
+        }
+    }
+    Ok(BorshSchemaContainer::new(declaration, used))
+}
+
+/// Renames the borsh 0.10 declarations of String and the unit type
+fn abi_declaration(declaration: &str) -> Declaration {
+    match declaration {
+        "string" => "String".to_string(),
+        "nil" => "()".to_string(),
+        declaration => declaration.to_string(),
+    }
+}
+
+fn declaration_of(declaration: &Value) -> Option<Declaration> {
+    declaration.as_str().map(abi_declaration)
+}
+
+/// Converts a definition serialized by serde, in the shape of a borsh 1 or borsh 0.10 definition
+fn abi_definition(definition: &Value) -> Option<Definition> {
+    let object = definition.as_object().filter(|object| object.len() == 1)?;
+    let (kind, contents) = object.iter().next()?;
+    let declarations = |value: &Value| -> Option<Vec<Declaration>> { value.as_array()?.iter().map(declaration_of).collect() };
+    let width = |key: &str| -> Option<u8> { contents.get(key)?.as_u64()?.try_into().ok() };
+    Some(match kind.as_str() {
+        "Primitive" => Definition::Primitive(contents.as_u64()?.try_into().ok()?),
+        "Sequence" => {
+            let elements = declaration_of(contents.get("elements")?)?;
+            match contents.get("length_width") {
+                Some(_) => {
+                    let (start, end) = match contents.get("length_range")? {
+                        Value::Array(range) => (range.first()?.as_u64()?, range.get(1)?.as_u64()?),
+                        range => (range.get("start")?.as_u64()?, range.get("end")?.as_u64()?),
+                    };
+                    Definition::Sequence {
+                        length_width: width("length_width")?,
+                        length_range: start..=end,
+                        elements,
+                    }
+                }
+                // Borsh 0.10 sequences always have a u32 length
+                None => Definition::Sequence {
+                    length_width: Definition::DEFAULT_LENGTH_WIDTH,
+                    length_range: Definition::DEFAULT_LENGTH_RANGE,
+                    elements,
+                },
+            }
+        }
+        // Borsh 0.10 arrays of a fixed length
+        "Array" => {
+            let length = contents.get("length")?.as_u64()?;
+            Definition::Sequence {
+                length_width: 0,
+                length_range: length..=length,
+                elements: declaration_of(contents.get("elements")?)?,
+            }
+        }
+        "Tuple" => Definition::Tuple { elements: declarations(contents.get("elements")?)? },
+        "Enum" => {
+            let variants = contents.get("variants")?.as_array()?;
+            let variants = variants
+                .iter()
+                .enumerate()
+                .map(|(index, variant)| match variant.as_array()?.as_slice() {
+                    [discriminant, name, declaration] => Some((discriminant.as_i64()?, name.as_str()?.to_string(), declaration_of(declaration)?)),
+                    // Borsh 0.10 variants have a discriminant of their index
+                    [name, declaration] => Some((index as i64, name.as_str()?.to_string(), declaration_of(declaration)?)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Definition::Enum {
+                tag_width: contents.get("tag_width").map_or(Some(1), |_| width("tag_width"))?,
+                variants,
+            }
+        }
+        "Struct" => Definition::Struct {
+            fields: match contents.get("fields")? {
+                Value::String(empty) if empty == "Empty" => Fields::Empty,
+                fields => {
+                    let fields = fields.as_object().filter(|fields| fields.len() == 1)?;
+                    match fields.iter().next()? {
+                        (kind, named) if kind == "NamedFields" => Fields::NamedFields(
+                            named
+                                .as_array()?
+                                .iter()
+                                .map(|field| match field.as_array()?.as_slice() {
+                                    [name, declaration] => Some((name.as_str()?.to_string(), declaration_of(declaration)?)),
+                                    _ => None,
+                                })
+                                .collect::<Option<Vec<_>>>()?,
+                        ),
+                        (kind, unnamed) if kind == "UnnamedFields" => Fields::UnnamedFields(declarations(unnamed)?),
+                        _ => return None,
+                    }
+                }
+            },
+        },
+        _ => return None,
+    })
+}
+
+fn type_schema(value: &Value) -> Result<BorshSchemaContainer, NearAbiError> {
+    schema_from_abi_json(value.get("type_schema").ok_or_else(|| NearAbiError::Invalid(format!("{value} has no type_schema")))?)
+}
+
+fn serialization_type<'v>(value: &'v Value, function: &str) -> Result<&'v str, NearAbiError> {
+    match value.get("serialization_type").and_then(Value::as_str) {
+        Some(serialization @ ("borsh" | "json")) => Ok(serialization),
+        _ => Err(NearAbiError::Invalid(format!("{function} has an unknown serialization type"))),
+    }
+}
+
+fn decode(mut data: &[u8], schema: &BorshSchemaContainer) -> Result<Value, NearAbiError> {
+    let value = deserialize_from_schema(&mut data, schema)?;
+    if !data.is_empty() {
+        return Err(NearAbiError::TrailingBytes(data.len()));
+    }
+    Ok(value)
+}
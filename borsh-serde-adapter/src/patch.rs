@@ -0,0 +1,71 @@
+use std::ops::Range;
+
+use anyhow::anyhow;
+use borsh::schema::BorshSchemaContainer;
+
+use crate::borsh_view::BorshView;
+use crate::deserialize_adapter::DEFAULT_MAX_DEPTH;
+use crate::errors::PathError;
+use crate::schema_path::{parse_path, resolve_path, PathSegment};
+use crate::serialize_adapter::serialize_serde_json_by_declaration_with_schema;
+
+/// Applies edits to borsh serialized bytes in place. Each edit is a path, as accepted by parse_path, and the new value
+/// of whatever the path refers to. Only the bytes of the edited values are rewritten. When a new value has a different
+/// size, as a longer string or a different enum variant would, the bytes after it are shifted.
+///
+/// Edits are applied in order, so a later edit sees the bytes written by an earlier one. The result is the same as
+/// deserializing, editing the JSON and serializing again. Paths can't contain `[*]`, and a path that names an enum
+/// variant only matches if that variant is active. If any edit fails, bytes are left as they were.
+///
+/// ```ignore
+/// patch_from_schema(&mut account_bytes, &container, &[("owner", json!("bob")), ("items[3].price", json!(12))])?;
+/// ```
+pub fn patch_from_schema(bytes: &mut Vec<u8>, schema: &BorshSchemaContainer, edits: &[(&str, serde_json::Value)]) -> anyhow::Result<()> {
+    // Everything that doesn't depend on the bytes is checked before anything is changed
+    let mut patches = Vec::with_capacity(edits.len());
+    for (path, value) in edits {
+        let segments = parse_path(path)?;
+        if segments.contains(&PathSegment::Wildcard) {
+            return Err(PathError::Invalid(path.to_string(), "[*] can't be used in a patch".to_string()).into());
+        }
+        let declaration = resolve_path(schema, &segments)?;
+        let mut encoded = Vec::new();
+        serialize_serde_json_by_declaration_with_schema(&mut encoded, value, schema, declaration, DEFAULT_MAX_DEPTH)
+            .map_err(|error| anyhow!("Invalid value for {path}: {error}"))?;
+        patches.push((*path, segments, encoded));
+    }
+
+    // The replaced bytes are kept so that the edits already applied can be undone if a later one fails
+    let mut applied: Vec<(Range<usize>, Vec<u8>)> = Vec::with_capacity(patches.len());
+    for (path, segments, encoded) in patches {
+        match locate(bytes, schema, &segments) {
+            Ok(range) => {
+                let new_range = range.start..range.start + encoded.len();
+                let replaced = bytes.splice(range, encoded).collect();
+                applied.push((new_range, replaced));
+            }
+            Err(error) => {
+                for (range, replaced) in applied.into_iter().rev() {
+                    bytes.splice(range, replaced);
+                }
+                return Err(anyhow!("Can't patch {path}: {error}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the range of bytes holding the value a path refers to
+fn locate(bytes: &[u8], schema: &BorshSchemaContainer, segments: &[PathSegment]) -> anyhow::Result<Range<usize>> {
+    let mut view = BorshView::new(bytes, schema);
+    for segment in segments {
+        view = match segment {
+            PathSegment::Field(name) => view.get(name)?,
+            PathSegment::Index(index) => view.index(*index)?,
+            PathSegment::Wildcard => unreachable!("Wildcards are rejected before locating"),
+        };
+    }
+    let value = view.as_bytes()?;
+    let start = value.as_ptr() as usize - bytes.as_ptr() as usize;
+    Ok(start..start + value.len())
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde_json::{json, Map};
+
+use crate::borsh_schema_util::{definition, EncodedSizes};
+use crate::borsh_view::skip;
+use crate::deserialize_adapter::{deserialize_within, read_sequence_length, read_variant, Budget, DecodeLimits};
+use crate::errors::PathError;
+use crate::schema_path::{parse_path, resolve_segment, PathSegment};
+
+/// The parts of a value selected by one or more paths
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Selection {
+    /// The whole value is selected, and the value is decoded as deserialize_from_schema would
+    whole: bool,
+    fields: BTreeMap<String, Selection>,
+    indices: BTreeMap<usize, Selection>,
+    wildcard: Option<Box<Selection>>,
+}
+
+impl Selection {
+    fn insert(&mut self, segments: &[PathSegment]) {
+        let Some((segment, rest)) = segments.split_first() else {
+            self.whole = true;
+            return;
+        };
+        let child = match segment {
+            PathSegment::Field(name) => self.fields.entry(name.clone()).or_default(),
+            PathSegment::Index(index) => self.indices.entry(*index).or_default(),
+            PathSegment::Wildcard => self.wildcard.get_or_insert_with(Default::default),
+        };
+        child.insert(rest);
+    }
+
+    fn merge(&mut self, other: &Selection) {
+        self.whole |= other.whole;
+        for (name, selection) in &other.fields {
+            self.fields.entry(name.clone()).or_default().merge(selection);
+        }
+        for (index, selection) in &other.indices {
+            self.indices.entry(*index).or_default().merge(selection);
+        }
+        if let Some(wildcard) = &other.wildcard {
+            self.wildcard.get_or_insert_with(Default::default).merge(wildcard);
+        }
+    }
+
+    /// Folds the wildcard into every index, so that each index selection is complete on its own
+    fn merge_wildcards(&mut self) {
+        if let Some(wildcard) = &mut self.wildcard {
+            wildcard.merge_wildcards();
+        }
+        for selection in self.fields.values_mut() {
+            selection.merge_wildcards();
+        }
+        for selection in self.indices.values_mut() {
+            if let Some(wildcard) = &self.wildcard {
+                selection.merge(wildcard);
+            }
+            selection.merge_wildcards();
+        }
+    }
+}
+
+/// Selected paths of a schema, decoded from borsh bytes in one pass. Only the selected subtrees are decoded, everything
+/// else is skipped.
+///
+/// Paths are written like `owner` or `balances[*].mint`, see parse_path. The result keeps the shape of the value with
+/// everything not selected left out: objects keep the selected fields and arrays keep the selected elements in order.
+/// A segment naming an enum variant, as in `kind.Token.mint`, acts as a filter. Values with a different active variant
+/// are left out, as are objects and array elements in which nothing was selected because of a filter. Indices past the
+/// end of a sequence are left out the same way, so `history[5]` of a sequence of three elements projects to `[]`.
+#[derive(Debug, Clone)]
+pub struct Projection<'a> {
+    schema: &'a BorshSchemaContainer,
+    selection: Selection,
+}
+
+impl<'a> Projection<'a> {
+    /// Checks every path against the schema, failing on the first path that doesn't exist
+    pub fn new(schema: &'a BorshSchemaContainer, paths: &[&str]) -> Result<Projection<'a>, PathError> {
+        let mut selection = Selection::default();
+        for path in paths {
+            let segments = parse_path(path)?;
+            segments
+                .iter()
+                .try_fold(schema.declaration(), |declaration, segment| resolve_segment(schema, declaration, segment, path))?;
+            selection.insert(&segments);
+        }
+        selection.merge_wildcards();
+        Ok(Projection { schema, selection })
+    }
+
+    /// Decodes the selected paths from a value at the start of buffer, advancing buffer past the whole value
+    pub fn project(&self, buffer: &mut &[u8]) -> std::io::Result<serde_json::Value> {
+        self.project_with_limits(buffer, &DecodeLimits::default())
+    }
+
+    /// Like project, but fails as soon as the input exceeds any of the limits. Skipped bytes count against
+    /// max_total_bytes, but the values in them don't count against max_output_nodes.
+    pub fn project_with_limits(&self, buffer: &mut &[u8], limits: &DecodeLimits) -> std::io::Result<serde_json::Value> {
+        let projector = Projector {
+            schema: self.schema,
+            sizes: EncodedSizes::new(self.schema),
+        };
+        let mut budget = Budget::new(limits, buffer);
+        let value = projector.project(buffer, &mut budget, self.schema.declaration(), &self.selection, 0)?;
+        Ok(value.unwrap_or_else(|| json!({})))
+    }
+}
+
+/// Decodes the selected paths of a value using the provided schema, see Projection
+pub fn project_from_schema(buffer: &mut &[u8], schema: &BorshSchemaContainer, paths: &[&str]) -> std::io::Result<serde_json::Value> {
+    project_from_schema_with_limits(buffer, schema, paths, &DecodeLimits::default())
+}
+
+/// Like project_from_schema, but fails as soon as the input exceeds any of the limits
+pub fn project_from_schema_with_limits(
+    buffer: &mut &[u8],
+    schema: &BorshSchemaContainer,
+    paths: &[&str],
+    limits: &DecodeLimits,
+) -> std::io::Result<serde_json::Value> {
+    Projection::new(schema, paths)
+        .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?
+        .project_with_limits(buffer, limits)
+}
+
+struct Projector<'a> {
+    schema: &'a BorshSchemaContainer,
+    sizes: EncodedSizes<'a>,
+}
+
+impl<'a> Projector<'a> {
+    /// Decodes the selected parts of a value nested in depth composite values, or returns None if a filter left nothing
+    /// selected. The selection has been checked against the schema, so its fields exist.
+    fn project(
+        &self,
+        buffer: &mut &[u8],
+        budget: &mut Budget,
+        declaration: &'a Declaration,
+        selection: &Selection,
+        depth: usize,
+    ) -> std::io::Result<Option<serde_json::Value>> {
+        budget.depth = depth;
+        // Paths can't go into primitives, so they are always selected whole
+        let definition = match selection.whole {
+            true => None,
+            false => definition(self.schema, declaration),
+        };
+        let Some(definition) = definition else {
+            return deserialize_within(buffer, budget, self.schema, &self.sizes, declaration).map(Some);
+        };
+        budget.add_node()?;
+        budget.check_depth()?;
+
+        match definition {
+            Definition::Struct { fields: Fields::NamedFields(fields) } => {
+                let mut object = Map::new();
+                for (name, field) in fields {
+                    match selection.fields.get(name) {
+                        Some(field_selection) => {
+                            if let Some(value) = self.project(buffer, budget, field, field_selection, depth + 1)? {
+                                object.insert(name.clone(), value);
+                            }
+                        }
+                        None => self.skip(buffer, budget, field, 1)?,
+                    }
+                }
+                Ok((!object.is_empty()).then(|| object.into()))
+            }
+
+            Definition::Enum { tag_width, variants } => {
+                let (variant_name, variant) = read_variant(buffer, *tag_width, variants, declaration)?;
+                match selection.fields.get(variant_name) {
+                    Some(variant_selection) => Ok(self
+                        .project(buffer, budget, variant, variant_selection, depth + 1)?
+                        .map(|value| json!({ variant_name: value }))),
+                    None => {
+                        self.skip(buffer, budget, variant, 1)?;
+                        Ok(None)
+                    }
+                }
+            }
+
+            Definition::Sequence { length_width, length_range, elements } => {
+                let element_size = self.sizes.min_size(elements);
+                let length = read_sequence_length(buffer, budget, *length_width, *length_range.end(), element_size, elements)? as u64;
+
+                // Indices past the end of the sequence select nothing
+                let selected: Box<dyn Iterator<Item = u64>> = match selection.wildcard {
+                    Some(_) => Box::new(0..length),
+                    None => Box::new(selection.indices.keys().map(|index| *index as u64).take_while(|index| *index < length)),
+                };
+
+                let mut values = Vec::new();
+                let mut next = 0;
+                for index in selected {
+                    // Unselected elements in between are skipped in one go
+                    self.skip(buffer, budget, elements, index - next)?;
+                    let element_selection = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| selection.indices.get(&index))
+                        .or(selection.wildcard.as_deref())
+                        .expect("Element is selected");
+                    if let Some(value) = self.project(buffer, budget, elements, element_selection, depth + 1)? {
+                        values.push(value);
+                    }
+                    next = index + 1;
+                }
+                self.skip(buffer, budget, elements, length - next)?;
+                Ok(Some(values.into()))
+            }
+
+            Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) } => {
+                let mut values = Vec::new();
+                for (index, element) in elements.iter().enumerate() {
+                    match selection.indices.get(&index) {
+                        Some(element_selection) => {
+                            if let Some(value) = self.project(buffer, budget, element, element_selection, depth + 1)? {
+                                values.push(value);
+                            }
+                        }
+                        None => self.skip(buffer, budget, element, 1)?,
+                    }
+                }
+                Ok((!values.is_empty()).then(|| values.into()))
+            }
+
+            _ => deserialize_within(buffer, budget, self.schema, &self.sizes, declaration).map(Some),
+        }
+    }
+
+    fn skip(&self, buffer: &mut &[u8], budget: &Budget, declaration: &'a Declaration, count: u64) -> std::io::Result<()> {
+        skip(&self.sizes, declaration, count, buffer).map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        budget.check_consumed(buffer)
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{ErrorKind, Read};
+
+use borsh::schema::BorshSchemaContainer;
+use serde_json::Value;
+
+use crate::deserialize_adapter::{deserialize_from_schema_with_limits, DecodeLimits};
+use crate::errors::RecordStreamError;
+
+/// Longest record read unless another maximum is given, 8 MiB
+pub const DEFAULT_MAX_RECORD_LENGTH: usize = 8 * 1024 * 1024;
+
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// How records follow each other in a stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Every record is preceded by its length as a little endian u32
+    #[default]
+    LengthPrefixed,
+    /// Records follow each other directly, and each ends where decoding it under the schema ends
+    SelfDelimiting,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordStreamOptions {
+    pub framing: Framing,
+    /// Records longer than this are an error. Without a length prefix this bounds how much is buffered for one record.
+    pub max_record_length: usize,
+    /// Limits applied to decoding each record
+    pub limits: DecodeLimits,
+    /// Whether to go on with the next record after a record fails to decode. Only length prefixed records can be
+    /// skipped, since without a prefix nothing tells where the record after a corrupt one starts. Truncated streams
+    /// and failing reads always end the iteration.
+    pub skip_corrupt: bool,
+}
+
+impl Default for RecordStreamOptions {
+    fn default() -> Self {
+        RecordStreamOptions {
+            framing: Framing::LengthPrefixed,
+            max_record_length: DEFAULT_MAX_RECORD_LENGTH,
+            limits: DecodeLimits::default(),
+            skip_corrupt: false,
+        }
+    }
+}
+
+/// Iterates over the records of a stream of back to back borsh values of one schema, such as a log file, decoding one
+/// record at a time. Errors carry the index of the record and the byte offset where it starts. The iteration ends after
+/// the first error unless the options allow skipping corrupt records.
+///
+/// ```ignore
+/// let options = RecordStreamOptions { skip_corrupt: true, ..RecordStreamOptions::default() };
+/// for record in RecordReader::new(BufReader::new(File::open("orders.log")?), &container, options) {
+///     match record {
+///         Ok(value) => println!("{value}"),
+///         Err(error) => eprintln!("{error}"),
+///     }
+/// }
+/// ```
+pub struct RecordReader<'a, R> {
+    reader: R,
+    schema: &'a BorshSchemaContainer,
+    options: RecordStreamOptions,
+    buffer: Vec<u8>,
+    reader_ended: bool,
+    index: usize,
+    offset: u64,
+    finished: bool,
+}
+
+impl<'a, R: Read> RecordReader<'a, R> {
+    pub fn new(reader: R, schema: &'a BorshSchemaContainer, options: RecordStreamOptions) -> RecordReader<'a, R> {
+        RecordReader {
+            reader,
+            schema,
+            options,
+            buffer: Vec::new(),
+            reader_ended: false,
+            index: 0,
+            offset: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns the index of the next record, which is also the number of records read or skipped so far
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the byte offset in the stream where the next record starts
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn next_length_prefixed(&mut self) -> Option<Result<Value, RecordStreamError>> {
+        let (index, offset) = (self.index, self.offset);
+        let mut prefix = [0u8; 4];
+        match read_full(&mut self.reader, &mut prefix) {
+            Ok(0) => return None,
+            Ok(4) => {}
+            Ok(_) => return Some(Err(RecordStreamError::Truncated(index, offset))),
+            Err(error) => return Some(Err(RecordStreamError::Io(index, offset, error))),
+        }
+
+        let length = u32::from_le_bytes(prefix) as usize;
+        if length > self.options.max_record_length {
+            let skipped = std::io::copy(&mut (&mut self.reader).take(length as u64), &mut std::io::sink());
+            return match skipped {
+                Ok(skipped) if skipped == length as u64 => {
+                    self.advance(4 + length);
+                    Some(Err(RecordStreamError::TooLong(index, offset, self.options.max_record_length)))
+                }
+                Ok(_) => Some(Err(RecordStreamError::Truncated(index, offset))),
+                Err(error) => Some(Err(RecordStreamError::Io(index, offset, error))),
+            };
+        }
+
+        let mut record = vec![0u8; length];
+        match read_full(&mut self.reader, &mut record) {
+            Ok(read) if read == length => {}
+            Ok(_) => return Some(Err(RecordStreamError::Truncated(index, offset))),
+            Err(error) => return Some(Err(RecordStreamError::Io(index, offset, error))),
+        }
+        self.advance(4 + length);
+
+        let mut buffer = record.as_slice();
+        let result = match deserialize_from_schema_with_limits(&mut buffer, self.schema, &self.options.limits) {
+            Ok(_) if !buffer.is_empty() => Err(RecordStreamError::TrailingBytes(index, offset, buffer.len())),
+            Ok(value) => Ok(value),
+            Err(error) => Err(RecordStreamError::Invalid(index, offset, error)),
+        };
+        Some(result)
+    }
+
+    /// Decodes from the buffered bytes, reading more whenever decoding fails before the reader has ended. A failure is
+    /// only final once the reader has ended or max_record_length bytes are buffered, since until then the record may
+    /// just be incomplete.
+    fn next_self_delimiting(&mut self) -> Option<Result<Value, RecordStreamError>> {
+        let (index, offset) = (self.index, self.offset);
+        loop {
+            if self.buffer.is_empty() {
+                match self.fill() {
+                    Ok(()) if self.buffer.is_empty() => return None,
+                    Ok(()) => {}
+                    Err(error) => return Some(Err(RecordStreamError::Io(index, offset, error))),
+                }
+            }
+
+            let mut buffer = self.buffer.as_slice();
+            let error = match deserialize_from_schema_with_limits(&mut buffer, self.schema, &self.options.limits) {
+                Ok(value) => {
+                    let length = self.buffer.len() - buffer.len();
+                    if length > self.options.max_record_length {
+                        return Some(Err(RecordStreamError::TooLong(index, offset, self.options.max_record_length)));
+                    }
+                    self.buffer.drain(..length);
+                    self.advance(length);
+                    return Some(Ok(value));
+                }
+                Err(error) => error,
+            };
+
+            if self.reader_ended || self.buffer.len() >= self.options.max_record_length {
+                return Some(Err(RecordStreamError::Invalid(index, offset, error)));
+            }
+            if let Err(error) = self.fill() {
+                return Some(Err(RecordStreamError::Io(index, offset, error)));
+            }
+        }
+    }
+
+    /// Reads as many bytes as are buffered, up to max_record_length, so a long record is decoded a logarithmic number of
+    /// times
+    fn fill(&mut self) -> std::io::Result<()> {
+        let wanted = self.buffer.len().max(READ_CHUNK_SIZE).min(self.options.max_record_length.max(1));
+        let read = (&mut self.reader).take(wanted as u64).read_to_end(&mut self.buffer)?;
+        if read < wanted {
+            self.reader_ended = true;
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self, length: usize) {
+        self.index += 1;
+        self.offset += length as u64;
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<'_, R> {
+    type Item = Result<Value, RecordStreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let record = match self.options.framing {
+            Framing::LengthPrefixed => self.next_length_prefixed(),
+            Framing::SelfDelimiting => self.next_self_delimiting(),
+        };
+        let skippable = self.options.skip_corrupt && self.options.framing == Framing::LengthPrefixed;
+        match &record {
+            None => self.finished = true,
+            Some(Err(error)) if !(skippable && error.is_corrupt_record()) => self.finished = true,
+            Some(_) => {}
+        }
+        record
+    }
+}
+
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(read)
+}
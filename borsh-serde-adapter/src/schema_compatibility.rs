@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde::{Deserialize, Serialize};
+
+use crate::borsh_schema_util::escape_pointer_segment;
+
+/// A change between two schemas that would stop data written with the old schema from decoding to the same
+/// value with the new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakingChange {
+    /// JSON pointer to the value affected by the change, "" for the root and "*" for every element of a sequence
+    pub path: String,
+    pub reason: String,
+}
+
+/// Compares two schemas and returns every breaking change found. An empty result means the new schema can read
+/// everything written with the old one. Renaming a type is allowed, renaming a field or variant is not since it
+/// changes the JSON produced by the adapters. Adding enum variants and widening a sequence's length range are allowed.
+pub fn breaking_changes(old: &BorshSchemaContainer, new: &BorshSchemaContainer) -> Vec<BreakingChange> {
+    let mut checker = CompatibilityChecker {
+        old,
+        new,
+        visited: HashSet::new(),
+        changes: Vec::new(),
+    };
+    checker.compare(old.declaration(), new.declaration(), "");
+    checker.changes
+}
+
+/// Returns true if the new schema can read everything written with the old one
+pub fn is_compatible(old: &BorshSchemaContainer, new: &BorshSchemaContainer) -> bool {
+    breaking_changes(old, new).is_empty()
+}
+
+struct CompatibilityChecker<'a> {
+    old: &'a BorshSchemaContainer,
+    new: &'a BorshSchemaContainer,
+    visited: HashSet<(&'a str, &'a str)>,
+    changes: Vec<BreakingChange>,
+}
+
+impl<'a> CompatibilityChecker<'a> {
+    fn breaking(&mut self, path: &str, reason: String) {
+        self.changes.push(BreakingChange {
+            path: path.to_string(),
+            reason,
+        });
+    }
+
+    fn compare(&mut self, old_declaration: &'a Declaration, new_declaration: &'a Declaration, path: &str) {
+        if !self.visited.insert((old_declaration.as_str(), new_declaration.as_str())) {
+            return;
+        }
+
+        let (old_definition, new_definition) = match (
+            self.old.get_definition(old_declaration),
+            self.new.get_definition(new_declaration),
+        ) {
+            (Some(old_definition), Some(new_definition)) => (old_definition, new_definition),
+            (None, None) => {
+                if old_declaration != new_declaration {
+                    self.breaking(path, format!("type changed from {old_declaration} to {new_declaration}"));
+                }
+                return;
+            }
+            (Some(_), None) => {
+                self.breaking(path, format!("definition for {new_declaration} is missing"));
+                return;
+            }
+            (None, Some(_)) => {
+                if old_declaration != new_declaration {
+                    self.breaking(path, format!("type changed from {old_declaration} to {new_declaration}"));
+                }
+                return;
+            }
+        };
+
+        match (old_definition, new_definition) {
+            (Definition::Primitive(old_size), Definition::Primitive(new_size)) => {
+                if old_declaration != new_declaration || old_size != new_size {
+                    self.breaking(path, format!("type changed from {old_declaration} to {new_declaration}"));
+                }
+            }
+
+            (
+                Definition::Sequence { length_width: old_width, length_range: old_range, elements: old_elements },
+                Definition::Sequence { length_width: new_width, length_range: new_range, elements: new_elements },
+            ) => {
+                if old_width != new_width {
+                    self.breaking(path, format!("length width changed from {old_width} to {new_width}"));
+                }
+                if new_range.start() > old_range.start() || new_range.end() < old_range.end() {
+                    self.breaking(path, format!("length range narrowed from {old_range:?} to {new_range:?}"));
+                }
+                self.compare(old_elements, new_elements, &format!("{path}/*"));
+            }
+
+            (Definition::Tuple { elements: old_elements }, Definition::Tuple { elements: new_elements }) => {
+                self.compare_elements(old_elements, new_elements, path);
+            }
+
+            (
+                Definition::Enum { tag_width: old_width, variants: old_variants },
+                Definition::Enum { tag_width: new_width, variants: new_variants },
+            ) => {
+                if old_width != new_width {
+                    self.breaking(path, format!("tag width changed from {old_width} to {new_width}"));
+                }
+                for (discriminant, variant_name, old_variant_declaration) in old_variants {
+                    let variant_path = format!("{path}/{}", escape_pointer_segment(variant_name));
+                    match new_variants.iter().find(|(d, ..)| d == discriminant) {
+                        Some((_, new_name, new_variant_declaration)) => {
+                            if new_name != variant_name {
+                                self.breaking(&variant_path, format!("variant renamed from {variant_name} to {new_name}"));
+                            }
+                            self.compare(old_variant_declaration, new_variant_declaration, &variant_path);
+                        }
+                        None => {
+                            self.breaking(&variant_path, format!("variant {variant_name} was removed"));
+                        }
+                    }
+                }
+            }
+
+            (Definition::Struct { fields: old_fields }, Definition::Struct { fields: new_fields }) => {
+                match (old_fields, new_fields) {
+                    (Fields::NamedFields(old_fields), Fields::NamedFields(new_fields)) => {
+                        if old_fields.len() != new_fields.len() {
+                            self.breaking(path, format!("field count changed from {} to {}", old_fields.len(), new_fields.len()));
+                        }
+                        for ((old_name, old_field), (new_name, new_field)) in old_fields.iter().zip(new_fields) {
+                            let field_path = format!("{path}/{}", escape_pointer_segment(old_name));
+                            if old_name != new_name {
+                                self.breaking(&field_path, format!("field renamed from {old_name} to {new_name}"));
+                            }
+                            self.compare(old_field, new_field, &field_path);
+                        }
+                    }
+                    (Fields::UnnamedFields(old_fields), Fields::UnnamedFields(new_fields)) => {
+                        self.compare_elements(old_fields, new_fields, path);
+                    }
+                    (Fields::Empty, Fields::Empty) => {}
+                    _ => {
+                        self.breaking(path, "struct fields changed kind".to_string());
+                    }
+                }
+            }
+
+            _ => {
+                self.breaking(path, format!("definition kind changed for {old_declaration} to {new_declaration}"));
+            }
+        }
+    }
+
+    fn compare_elements(&mut self, old_elements: &'a [Declaration], new_elements: &'a [Declaration], path: &str) {
+        if old_elements.len() != new_elements.len() {
+            self.breaking(path, format!("element count changed from {} to {}", old_elements.len(), new_elements.len()));
+        }
+        for (index, (old_element, new_element)) in old_elements.iter().zip(new_elements).enumerate() {
+            self.compare(old_element, new_element, &format!("{path}/{index}"));
+        }
+    }
+}
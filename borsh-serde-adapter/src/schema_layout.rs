@@ -0,0 +1,281 @@
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::anyhow;
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde::{Deserialize, Serialize};
+
+use crate::borsh_schema_util::{definition, definition_children, EncodedSizes, PrimitiveType};
+use crate::deserialize_adapter::DEFAULT_MAX_DEPTH;
+use crate::schema_path::{format_path, parse_path, PathSegment};
+use crate::serialize_adapter::serialize_serde_json_by_declaration_with_schema;
+
+/// Bounds on the lengths of sequences and strings, tighter than the length ranges in the schema, used to compute the
+/// largest size of a value. A Vec or String in a schema may hold up to u32::MAX elements, which is rarely useful when
+/// allocating an account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeBounds {
+    /// The most elements of a sequence by its declaration, such as "Vec<u64>", or the most bytes of a "String"
+    pub max_lengths: BTreeMap<Declaration, u64>,
+    /// The most elements of any sequence or bytes of any string not in max_lengths
+    pub default_max_length: Option<u64>,
+}
+
+impl SizeBounds {
+    fn max_length(&self, declaration: &str, schema_max: u64) -> u64 {
+        self.max_lengths
+            .get(declaration)
+            .copied()
+            .or(self.default_max_length)
+            .map_or(schema_max, |max_length| max_length.min(schema_max))
+    }
+}
+
+/// The smallest and largest encoded size of values of a declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeRange {
+    pub min: usize,
+    /// None if values can be arbitrarily large, which is the case for recursive types
+    pub max: Option<usize>,
+}
+
+impl SizeRange {
+    /// Returns the size if every value has the same size
+    pub fn exact(&self) -> Option<usize> {
+        self.max.filter(|max| *max == self.min)
+    }
+}
+
+/// The position of a field in every encoded value of a schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldLayout {
+    /// The path of the field, as accepted by parse_path
+    pub path: String,
+    pub declaration: Declaration,
+    /// The byte offset of the field from the start of the value
+    pub offset: usize,
+    /// The encoded size of the field, or None if it varies
+    pub size: Option<usize>,
+}
+
+/// Returns the encoded size of every value of the schema's declaration, or None if values vary in size
+pub fn fixed_size(schema: &BorshSchemaContainer) -> Option<usize> {
+    EncodedSizes::new(schema).fixed_size(schema.declaration())
+}
+
+/// Returns the smallest and largest encoded size of values of the schema's declaration. Sequences and strings are
+/// bounded by the length ranges in the schema, or by bounds where those are tighter.
+pub fn size_range(schema: &BorshSchemaContainer, bounds: &SizeBounds) -> SizeRange {
+    size_range_of(schema, schema.declaration(), bounds)
+}
+
+/// Returns the smallest and largest encoded size of values of a declaration of the schema
+pub fn size_range_of(schema: &BorshSchemaContainer, declaration: &Declaration, bounds: &SizeBounds) -> SizeRange {
+    SizeCalculator::new(schema, bounds).size_range(declaration)
+}
+
+/// Returns the fields of the schema's declaration whose offset is the same in every value, in the order they are
+/// encoded. Fields of nested structs and tuples are included with paths like `header.version`. The offset of a field
+/// is known as long as every field before it has a fixed size, so the list ends at the first field that varies in size.
+pub fn field_layout(schema: &BorshSchemaContainer) -> Vec<FieldLayout> {
+    let mut layout = Vec::new();
+    add_fields(schema, &EncodedSizes::new(schema), schema.declaration(), &mut Vec::new(), &mut Vec::new(), 0, &mut layout);
+    layout
+}
+
+/// Returns the offset and encoded bytes of a field with the given value, to be compared against account data with a
+/// memcmp filter
+pub fn memcmp_filter(schema: &BorshSchemaContainer, path: &str, value: &serde_json::Value) -> anyhow::Result<(usize, Vec<u8>)> {
+    let path = format_path(&parse_path(path)?);
+    let field = field_layout(schema)
+        .into_iter()
+        .find(|field| field.path == path)
+        .ok_or_else(|| anyhow!("{path} is not a field at a fixed offset of {}", schema.declaration()))?;
+
+    let mut bytes = Vec::new();
+    serialize_serde_json_by_declaration_with_schema(&mut bytes, value, schema, &field.declaration, DEFAULT_MAX_DEPTH)?;
+    Ok((field.offset, bytes))
+}
+
+/// Adds the fields of a struct or tuple at offset to layout, and returns the offset after it if its size is fixed
+fn add_fields<'a>(
+    schema: &'a BorshSchemaContainer,
+    sizes: &EncodedSizes<'a>,
+    declaration: &'a Declaration,
+    visiting: &mut Vec<&'a Declaration>,
+    path: &mut Vec<PathSegment>,
+    offset: usize,
+    layout: &mut Vec<FieldLayout>,
+) -> Option<usize> {
+    // A struct that contains itself can't be encoded, so its fields have no layout
+    if visiting.contains(&declaration) {
+        return None;
+    }
+    let children: Vec<(PathSegment, &Declaration)> = match definition(schema, declaration) {
+        Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => {
+            fields.iter().map(|(name, field)| (PathSegment::Field(name.clone()), field)).collect()
+        }
+        Some(Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+            elements.iter().enumerate().map(|(index, element)| (PathSegment::Index(index), element)).collect()
+        }
+        _ => return sizes.fixed_size(declaration).and_then(|size| offset.checked_add(size)),
+    };
+
+    visiting.push(declaration);
+    let mut offset = Some(offset);
+    for (segment, child) in children {
+        let Some(start) = offset else {
+            break;
+        };
+        path.push(segment);
+        let size = sizes.fixed_size(child);
+        layout.push(FieldLayout {
+            path: format_path(path),
+            declaration: child.clone(),
+            offset: start,
+            size,
+        });
+        offset = add_fields(schema, sizes, child, visiting, path, start, layout);
+        path.pop();
+    }
+    visiting.pop();
+    offset
+}
+
+/// Works out the smallest and largest sizes of the declarations of a schema, each once, so that types shared by several
+/// fields aren't walked once per path to them
+pub(crate) struct SizeCalculator<'a> {
+    schema: &'a BorshSchemaContainer,
+    bounds: &'a SizeBounds,
+    min: OnceCell<HashMap<&'a str, Option<usize>>>,
+    max: RefCell<HashMap<&'a str, Option<usize>>>,
+}
+
+impl<'a> SizeCalculator<'a> {
+    pub(crate) fn new(schema: &'a BorshSchemaContainer, bounds: &'a SizeBounds) -> SizeCalculator<'a> {
+        SizeCalculator {
+            schema,
+            bounds,
+            min: OnceCell::new(),
+            max: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn size_range(&self, declaration: &'a Declaration) -> SizeRange {
+        SizeRange {
+            min: self.min_size(declaration).unwrap_or(usize::MAX),
+            max: self.max_size_within(declaration, &mut Vec::new()),
+        }
+    }
+
+    /// The fewest bytes a value can be encoded in, or None if every value contains itself and none can be encoded
+    pub(crate) fn min_size(&self, declaration: &str) -> Option<usize> {
+        min_size_in(self.min_sizes(), declaration)
+    }
+
+    /// The fewest bytes each declaration of the schema can be encoded in. Sizes start unknown and are lowered until none
+    /// changes. A size is never less than that of any of its parts, so every round settles at least one more size,
+    /// and recursive types settle on the alternatives that end.
+    fn min_sizes(&self) -> &HashMap<&'a str, Option<usize>> {
+        self.min.get_or_init(|| {
+            let mut sizes: HashMap<&'a str, Option<usize>> =
+                self.schema.definitions().map(|(declaration, _)| (declaration.as_str(), None)).collect();
+            loop {
+                let mut changed = false;
+                for (declaration, definition) in self.schema.definitions() {
+                    let size = min_size_of(definition, |child| min_size_in(&sizes, child));
+                    if size.is_some_and(|size| sizes[declaration.as_str()].is_none_or(|known| size < known)) {
+                        sizes.insert(declaration, size);
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    return sizes;
+                }
+            }
+        })
+    }
+
+    /// The most bytes a value can be encoded in, or None if values can be arbitrarily large. Recursive occurrences are
+    /// None, which makes every type they are in None too, so sizes are kept whichever declaration the walk started from.
+    fn max_size_within(&self, declaration: &'a Declaration, visiting: &mut Vec<&'a Declaration>) -> Option<usize> {
+        if declaration == "String" {
+            let length = self.bounds.max_length(declaration, u32::MAX as u64);
+            return usize::try_from(length).ok()?.checked_add(4);
+        }
+        if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+            return primitive.fixed_size();
+        }
+        if let Some(size) = self.max.borrow().get(declaration.as_str()) {
+            return *size;
+        }
+        if visiting.contains(&declaration) {
+            return None;
+        }
+        let Some(definition) = self.schema.get_definition(declaration) else {
+            return Some(0);
+        };
+
+        visiting.push(declaration);
+        let mut child = |child: &'a Declaration| self.max_size_within(child, visiting);
+        let size = match definition {
+            Definition::Primitive(size) => Some(*size as usize),
+            Definition::Sequence { length_width, length_range, elements } => {
+                // Sequences without a length prefix are arrays of a fixed length, which bounds can't shorten
+                let length = match length_width {
+                    0 => *length_range.end(),
+                    _ => self.bounds.max_length(declaration, *length_range.end()),
+                };
+                match length {
+                    0 => Some(*length_width as usize),
+                    length => child(elements)
+                        .and_then(|size| size.checked_mul(usize::try_from(length).ok()?))
+                        .and_then(|size| size.checked_add(*length_width as usize)),
+                }
+            }
+            Definition::Enum { tag_width, variants } => variants
+                .iter()
+                .map(|(_, _, variant)| child(variant))
+                .try_fold(0, |largest, size| size.map(|size| largest.max(size)))
+                .and_then(|size| size.checked_add(*tag_width as usize)),
+            Definition::Tuple { .. } | Definition::Struct { .. } => {
+                let mut sizes = definition_children(definition).into_iter().map(child);
+                sizes.try_fold(0usize, |total, size| total.checked_add(size?))
+            }
+        };
+        visiting.pop();
+
+        self.max.borrow_mut().insert(declaration, size);
+        size
+    }
+}
+
+/// Looks up the smallest size of a declaration in sizes, where declarations without a definition have no bytes
+fn min_size_in(sizes: &HashMap<&str, Option<usize>>, declaration: &str) -> Option<usize> {
+    match PrimitiveType::from_declaration(declaration) {
+        Some(primitive) => Some(primitive.min_size()),
+        None => sizes.get(declaration).copied().unwrap_or(Some(0)),
+    }
+}
+
+/// The fewest bytes a value of a definition can be encoded in, given the smallest sizes of its children
+fn min_size_of(definition: &Definition, child: impl Fn(&str) -> Option<usize>) -> Option<usize> {
+    match definition {
+        Definition::Primitive(size) => Some(*size as usize),
+        Definition::Sequence { length_width, length_range, elements } => match *length_range.start() {
+            0 => Some(*length_width as usize),
+            length => child(elements)
+                .and_then(|size| size.checked_mul(usize::try_from(length).ok()?))
+                .and_then(|size| size.checked_add(*length_width as usize)),
+        },
+        Definition::Enum { tag_width, variants } => variants
+            .iter()
+            .filter_map(|(_, _, variant)| child(variant))
+            .min()
+            .map(|size| size.saturating_add(*tag_width as usize)),
+        Definition::Tuple { .. } | Definition::Struct { .. } => {
+            let mut sizes = definition_children(definition).into_iter().map(|declaration| child(declaration));
+            sizes.try_fold(0usize, |total, size| total.checked_add(size?))
+        }
+    }
+}
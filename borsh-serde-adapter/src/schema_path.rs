@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+
+use crate::borsh_schema_util::definition;
+use crate::errors::PathError;
+
+/// A segment of a path into a value, such as `owner`, `[3]` or `[*]` in `owner.balances[3].mint`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    /// A named field of a struct, or a variant of an enum, which only matches values where that variant is active
+    Field(String),
+    /// An element of a sequence or tuple, or an unnamed field of a struct
+    Index(usize),
+    /// Every element of a sequence
+    Wildcard,
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathSegment::Field(name) => write!(f, ".{name}"),
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+            PathSegment::Wildcard => write!(f, "[*]"),
+        }
+    }
+}
+
+/// Parses a path such as `balances[*].mint`. Segments are separated by dots and indices are written in brackets. An
+/// empty path refers to the whole value.
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, PathError> {
+    let invalid = |reason: &str| PathError::Invalid(path.to_string(), reason.to_string());
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Ok(segments);
+    }
+
+    for (position, part) in path.split('.').enumerate() {
+        let (name, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if name.contains(']') {
+            return Err(invalid("unexpected ]"));
+        }
+        if !name.is_empty() {
+            segments.push(PathSegment::Field(name.to_string()));
+        } else if position > 0 || indices.is_empty() {
+            return Err(invalid("empty field name"));
+        }
+
+        while !indices.is_empty() {
+            let Some(close) = indices.find(']') else {
+                return Err(invalid("missing ]"));
+            };
+            let index = &indices[1..close];
+            segments.push(match index {
+                "*" => PathSegment::Wildcard,
+                _ => PathSegment::Index(index.parse().map_err(|_| invalid(&format!("{index} is not an index")))?),
+            });
+            indices = &indices[close + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return Err(invalid("expected [ or . after ]"));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Formats segments back into a path, the inverse of parse_path
+pub fn format_path(segments: &[PathSegment]) -> String {
+    let path: String = segments.iter().map(ToString::to_string).collect();
+    path.strip_prefix('.').map(str::to_string).unwrap_or(path)
+}
+
+/// Returns the declaration of the values a segment refers to within a value of the declaration
+pub(crate) fn resolve_segment<'a>(
+    schema: &'a BorshSchemaContainer,
+    declaration: &'a Declaration,
+    segment: &PathSegment,
+    path: &str,
+) -> Result<&'a Declaration, PathError> {
+    let definition = definition(schema, declaration);
+    match segment {
+        PathSegment::Field(name) => {
+            let field = match definition {
+                Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => {
+                    fields.iter().find(|(field_name, _)| field_name == name).map(|(_, field)| field)
+                }
+                Some(Definition::Enum { variants, .. }) => {
+                    variants.iter().find(|(_, variant_name, _)| variant_name == name).map(|(_, _, variant)| variant)
+                }
+                _ => None,
+            };
+            field.ok_or_else(|| PathError::NoSuchField(path.to_string(), declaration.to_string(), name.clone()))
+        }
+        PathSegment::Index(index) => match definition {
+            Some(Definition::Sequence { length_width, length_range, elements }) => {
+                if *length_width == 0 && *index as u64 >= *length_range.end() {
+                    return Err(PathError::IndexOutOfBounds(path.to_string(), *index, declaration.to_string()));
+                }
+                Ok(elements)
+            }
+            Some(Definition::Tuple { elements }) | Some(Definition::Struct { fields: Fields::UnnamedFields(elements) }) => {
+                elements.get(*index).ok_or_else(|| PathError::IndexOutOfBounds(path.to_string(), *index, declaration.to_string()))
+            }
+            _ => Err(PathError::NotIndexable(path.to_string(), declaration.to_string())),
+        },
+        PathSegment::Wildcard => match definition {
+            Some(Definition::Sequence { elements, .. }) => Ok(elements),
+            _ => Err(PathError::NotASequence(path.to_string(), declaration.to_string())),
+        },
+    }
+}
+
+/// Checks that a path exists in the schema and returns the declaration of the values it refers to
+pub fn resolve_path<'a>(schema: &'a BorshSchemaContainer, segments: &[PathSegment]) -> Result<&'a Declaration, PathError> {
+    let path = format_path(segments);
+    segments
+        .iter()
+        .try_fold(schema.declaration(), |declaration, segment| resolve_segment(schema, declaration, segment, &path))
+}
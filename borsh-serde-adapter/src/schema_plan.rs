@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Write};
+use std::ops::RangeInclusive;
+
+use anyhow::anyhow;
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use log::debug;
+
+use crate::borsh_schema_util::{definition_children, EncodedSizes, PrimitiveType};
+use crate::deserialize_adapter::{
+    deserialize_primitive, deserialize_with, read_sequence_length, read_variant, Budget, DecodeLimits, Frame, Step, DEFAULT_MAX_DEPTH,
+};
+use crate::serialize_adapter::{
+    element_children, named_field_children, sequence_children, serialize_primitive, serialize_with, unnamed_field_children,
+    variant_children, Children,
+};
+
+/// Index of a node in a SchemaPlan
+pub type NodeId = usize;
+
+/// A definition with every declaration it refers to resolved to a NodeId
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanNode {
+    Primitive(PrimitiveType),
+    Sequence {
+        length_width: u8,
+        length_range: RangeInclusive<u64>,
+        elements: NodeId,
+    },
+    Tuple {
+        elements: Vec<NodeId>,
+    },
+    Enum {
+        tag_width: u8,
+        variants: Vec<(i64, String, NodeId)>,
+    },
+    Struct {
+        fields: Vec<(String, NodeId)>,
+    },
+    UnnamedStruct {
+        fields: Vec<NodeId>,
+    },
+    EmptyStruct,
+    /// A primitive definition the adapters don't support, which fails to serialize and deserialize
+    Unsupported,
+    /// A declaration without a definition, which is deserialized as null and skipped when serializing
+    Unknown,
+}
+
+/// A node of a SchemaPlan along with the declaration it was compiled from and its precomputed sizes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledNode {
+    pub declaration: Declaration,
+    pub node: PlanNode,
+    /// The encoded size of every value of the node, or None if it varies
+    pub fixed_size: Option<usize>,
+    /// The fewest bytes a value of the node can be encoded in
+    pub min_size: usize,
+}
+
+/// A BorshSchemaContainer resolved once into nodes indexed by integer IDs. Deserializing and serializing against a plan
+/// gives the same results as deserialize_from_schema and serialize_serde_json_to_borsh, without matching declaration
+/// strings or looking up definitions for every value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaPlan {
+    nodes: Vec<CompiledNode>,
+    root: NodeId,
+}
+
+impl SchemaPlan {
+    /// Compiles the definitions reachable from the declaration of the schema into a plan
+    pub fn compile(schema: &BorshSchemaContainer) -> SchemaPlan {
+        let mut ids: HashMap<&Declaration, NodeId> = HashMap::new();
+        let mut declarations = vec![schema.declaration()];
+        ids.insert(schema.declaration(), 0);
+
+        let mut next = 0;
+        while let Some(declaration) = declarations.get(next).copied() {
+            next += 1;
+            if PrimitiveType::from_declaration(declaration).is_some() {
+                continue;
+            }
+            if let Some(definition) = schema.get_definition(declaration) {
+                for child in definition_children(definition) {
+                    ids.entry(child).or_insert_with(|| {
+                        declarations.push(child);
+                        declarations.len() - 1
+                    });
+                }
+            }
+        }
+
+        let sizes = EncodedSizes::new(schema);
+        let nodes = declarations
+            .iter()
+            .map(|declaration| CompiledNode {
+                declaration: declaration.to_string(),
+                node: compile_node(schema, declaration, &ids),
+                fixed_size: sizes.fixed_size(declaration),
+                min_size: sizes.min_size(declaration),
+            })
+            .collect();
+
+        SchemaPlan { nodes, root: 0 }
+    }
+
+    /// The node of the declaration of the schema
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn node(&self, id: NodeId) -> &CompiledNode {
+        &self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> &[CompiledNode] {
+        &self.nodes
+    }
+
+    /// Deserializes borsh serialized bytes to serde_json::Value
+    pub fn deserialize(&self, buffer: &mut &[u8]) -> std::io::Result<serde_json::Value> {
+        self.deserialize_with_limits(buffer, &DecodeLimits::default())
+    }
+
+    /// Deserializes borsh serialized bytes to serde_json::Value, failing as soon as the input exceeds any of the limits
+    pub fn deserialize_with_limits(&self, buffer: &mut &[u8], limits: &DecodeLimits) -> std::io::Result<serde_json::Value> {
+        deserialize_with(buffer, limits, &self.root, |buffer, id, budget| self.start_node(buffer, *id, budget))
+    }
+
+    fn start_node<'a>(&'a self, buffer: &mut &[u8], id: NodeId, budget: &mut Budget) -> std::io::Result<Step<'a, NodeId>> {
+        let compiled = &self.nodes[id];
+        let frame = match &compiled.node {
+            PlanNode::Primitive(primitive) => {
+                return deserialize_primitive(buffer, *primitive, budget.limits).map(Step::Value);
+            }
+            PlanNode::Sequence { length_width, length_range, elements } => {
+                let element = &self.nodes[*elements];
+                let length = read_sequence_length(
+                    buffer,
+                    budget,
+                    *length_width,
+                    *length_range.end(),
+                    element.min_size,
+                    &element.declaration,
+                )?;
+                match element.node {
+                    // Elements of a fixed size were all checked to be in the buffer, so they are read in one go
+                    PlanNode::Primitive(primitive) if element.fixed_size.is_some() => {
+                        budget.check_consumed(buffer)?;
+                        budget.check_depth()?;
+                        let mut values = Vec::with_capacity(length);
+                        for _ in 0..length {
+                            budget.add_node()?;
+                            values.push(deserialize_primitive(buffer, primitive, budget.limits)?);
+                        }
+                        Frame::decoded_sequence(elements, values)
+                    }
+                    _ => Frame::sequence(elements, length, budget.remaining(buffer)),
+                }
+            }
+            PlanNode::Tuple { elements } | PlanNode::UnnamedStruct { fields: elements } => Frame::elements(elements),
+            PlanNode::Enum { tag_width, variants } => {
+                let (variant_name, variant) = read_variant(buffer, *tag_width, variants, &compiled.declaration)?;
+                Frame::variant(variant_name, variant)
+            }
+            PlanNode::Struct { fields } => Frame::fields(fields),
+            PlanNode::EmptyStruct => return Ok(Step::Value(Vec::<u8>::new().into())),
+            PlanNode::Unsupported => {
+                return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported primitive type {}", compiled.declaration)));
+            }
+            PlanNode::Unknown => {
+                debug!("Can't deserialize unknown type: {:?}. Using Value::Null", compiled.declaration);
+                return Ok(Step::Value(serde_json::Value::Null));
+            }
+        };
+        Ok(Step::Frame(frame))
+    }
+
+    /// Serializes serde_json::Value to borsh serialized bytes
+    pub fn serialize(&self, writer: &mut impl Write, value: &serde_json::Value) -> anyhow::Result<()> {
+        self.serialize_with_max_depth(writer, value, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Serializes serde_json::Value to borsh serialized bytes, failing if structs, tuples, sequences and enums are nested
+    /// deeper than max_depth
+    pub fn serialize_with_max_depth(&self, writer: &mut impl Write, value: &serde_json::Value, max_depth: usize) -> anyhow::Result<()> {
+        serialize_with(writer, value, &self.root, max_depth, |writer, value, id| {
+            let compiled = &self.nodes[*id];
+            let children: Children<NodeId> = match &compiled.node {
+                PlanNode::Primitive(primitive) => {
+                    serialize_primitive(writer, value, *primitive)?;
+                    return Ok(None);
+                }
+                PlanNode::Sequence { length_width, length_range, elements } => {
+                    sequence_children(writer, value, *length_width, length_range, elements)?
+                }
+                PlanNode::Tuple { elements } => element_children(value, elements)?,
+                PlanNode::Enum { tag_width, variants } => variant_children(writer, value, *tag_width, variants)?,
+                PlanNode::Struct { fields } => named_field_children(value, fields)?,
+                PlanNode::UnnamedStruct { fields } => unnamed_field_children(value, fields)?,
+                PlanNode::EmptyStruct => return Ok(None),
+                PlanNode::Unsupported => return Err(anyhow!("Unsupported primitive type {}", compiled.declaration)),
+                PlanNode::Unknown => {
+                    debug!("Can't serialize unknown type: {:?}. Returning Ok(())", compiled.declaration);
+                    return Ok(None);
+                }
+            };
+            Ok(Some(children))
+        })
+    }
+}
+
+fn compile_node(schema: &BorshSchemaContainer, declaration: &Declaration, ids: &HashMap<&Declaration, NodeId>) -> PlanNode {
+    if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+        return PlanNode::Primitive(primitive);
+    }
+    let Some(definition) = schema.get_definition(declaration) else {
+        return PlanNode::Unknown;
+    };
+
+    let id = |declaration: &Declaration| ids[declaration];
+    match definition {
+        Definition::Primitive(_) => PlanNode::Unsupported,
+        Definition::Sequence { length_width, length_range, elements } => PlanNode::Sequence {
+            length_width: *length_width,
+            length_range: length_range.clone(),
+            elements: id(elements),
+        },
+        Definition::Tuple { elements } => PlanNode::Tuple {
+            elements: elements.iter().map(id).collect(),
+        },
+        Definition::Enum { tag_width, variants } => PlanNode::Enum {
+            tag_width: *tag_width,
+            variants: variants
+                .iter()
+                .map(|(discriminant, name, variant)| (*discriminant, name.clone(), id(variant)))
+                .collect(),
+        },
+        Definition::Struct { fields } => match fields {
+            Fields::NamedFields(fields) => PlanNode::Struct {
+                fields: fields.iter().map(|(name, field)| (name.clone(), id(field))).collect(),
+            },
+            Fields::UnnamedFields(fields) => PlanNode::UnnamedStruct {
+                fields: fields.iter().map(id).collect(),
+            },
+            Fields::Empty => PlanNode::EmptyStruct,
+        },
+    }
+}
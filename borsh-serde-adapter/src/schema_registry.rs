@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use borsh::schema::BorshSchemaContainer;
+use borsh::{to_vec, BorshDeserialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::RegistryError;
+use crate::schema_compatibility::breaking_changes;
+
+const SCHEMA_FILE_SUFFIX: &str = "_schema.dat";
+
+/// Describes a schema stored in a registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaEntry {
+    pub name: String,
+    pub version: u32,
+    pub fingerprint: String,
+}
+
+/// Storage for BorshSchemaContainers registered under a name and version
+pub trait SchemaRegistry {
+    /// Registers a schema under the name and version. The registration is refused if the version already exists, or if
+    /// the schema has breaking changes compared to the closest lower version and force is false.
+    fn register(&self, name: &str, version: u32, schema: &BorshSchemaContainer, force: bool) -> Result<SchemaEntry, RegistryError>;
+
+    /// Returns the schema registered under the name and version
+    fn get(&self, name: &str, version: u32) -> Result<BorshSchemaContainer, RegistryError>;
+
+    /// Returns the entry for the schema with the given fingerprint
+    fn find_by_fingerprint(&self, fingerprint: &str) -> Result<SchemaEntry, RegistryError>;
+
+    /// Returns all registered versions of a schema in ascending order
+    fn versions(&self, name: &str) -> Result<Vec<u32>, RegistryError>;
+
+    /// Returns the names of all registered schemas in ascending order
+    fn names(&self) -> Result<Vec<String>, RegistryError>;
+
+    /// Returns the highest registered version of a schema
+    fn get_latest(&self, name: &str) -> Result<(u32, BorshSchemaContainer), RegistryError> {
+        let version = self.versions(name)?
+            .pop()
+            .ok_or_else(|| RegistryError::NameNotFound(name.to_string()))?;
+        Ok((version, self.get(name, version)?))
+    }
+
+    /// Returns the schema with the given fingerprint
+    fn get_by_fingerprint(&self, fingerprint: &str) -> Result<BorshSchemaContainer, RegistryError> {
+        let entry = self.find_by_fingerprint(fingerprint)?;
+        self.get(&entry.name, entry.version)
+    }
+}
+
+/// Returns the hex encoded SHA-256 of the borsh serialized schema
+pub fn fingerprint(schema: &BorshSchemaContainer) -> String {
+    let data = to_vec(schema).expect("Failed to serialize BorshSchemaContainer");
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Checks that a schema may be registered, given the versions already in the registry. Registry implementations
+/// should call this before storing a schema.
+pub fn check_registration(
+    registry: &(impl SchemaRegistry + ?Sized),
+    name: &str,
+    version: u32,
+    schema: &BorshSchemaContainer,
+    force: bool,
+) -> Result<(), RegistryError> {
+    validate_name(name)?;
+
+    let versions = match registry.versions(name) {
+        Ok(versions) => versions,
+        Err(RegistryError::NameNotFound(_)) => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    check_versions(name, version, schema, force, &versions, |previous| registry.get(name, previous))
+}
+
+/// Checks a registration against the versions already registered under the name, getting the schema of the version
+/// before it with `get_previous` to check for breaking changes
+fn check_versions(
+    name: &str,
+    version: u32,
+    schema: &BorshSchemaContainer,
+    force: bool,
+    versions: &[u32],
+    get_previous: impl FnOnce(u32) -> Result<BorshSchemaContainer, RegistryError>,
+) -> Result<(), RegistryError> {
+    if versions.contains(&version) {
+        return Err(RegistryError::AlreadyExists(name.to_string(), version));
+    }
+
+    if force {
+        return Ok(());
+    }
+    if let Some(previous) = versions.iter().rev().find(|v| **v < version) {
+        let previous_schema = get_previous(*previous)?;
+        let changes = breaking_changes(&previous_schema, schema);
+        if !changes.is_empty() {
+            return Err(RegistryError::BreakingChange {
+                name: name.to_string(),
+                version,
+                previous: *previous,
+                changes,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), RegistryError> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(RegistryError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// A SchemaRegistry storing each schema as a borsh binary file in a directory. A schema named "person" with version 2
+/// is stored as `<root>/person/2_schema.dat`, the same format written by borsh-schema-writer.
+pub struct DirectorySchemaRegistry {
+    root: PathBuf,
+}
+
+impl DirectorySchemaRegistry {
+    /// Opens a registry in the given directory, creating it if it does not exist
+    pub fn new(root: impl AsRef<Path>) -> Result<DirectorySchemaRegistry, RegistryError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        Ok(DirectorySchemaRegistry { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn schema_path(&self, name: &str, version: u32) -> PathBuf {
+        self.root.join(name).join(format!("{version}{SCHEMA_FILE_SUFFIX}"))
+    }
+
+    fn read_schema(path: &Path) -> Result<BorshSchemaContainer, RegistryError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Ok(BorshSchemaContainer::deserialize_reader(&mut reader)?)
+    }
+}
+
+impl SchemaRegistry for DirectorySchemaRegistry {
+    fn register(&self, name: &str, version: u32, schema: &BorshSchemaContainer, force: bool) -> Result<SchemaEntry, RegistryError> {
+        check_registration(self, name, version, schema, force)?;
+
+        fs::create_dir_all(self.root.join(name))?;
+        let data = to_vec(schema)?;
+        // Opened with create_new so that a concurrent registration of the same version can't be overwritten
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.schema_path(name, version))
+            .map_err(|err| match err.kind() {
+                ErrorKind::AlreadyExists => RegistryError::AlreadyExists(name.to_string(), version),
+                _ => err.into(),
+            })?;
+        file.write_all(&data)?;
+
+        Ok(SchemaEntry {
+            name: name.to_string(),
+            version,
+            fingerprint: fingerprint(schema),
+        })
+    }
+
+    fn get(&self, name: &str, version: u32) -> Result<BorshSchemaContainer, RegistryError> {
+        validate_name(name)?;
+        let path = self.schema_path(name, version);
+        if !path.is_file() {
+            return Err(RegistryError::NotFound(name.to_string(), version));
+        }
+        Self::read_schema(&path)
+    }
+
+    fn find_by_fingerprint(&self, fingerprint_hex: &str) -> Result<SchemaEntry, RegistryError> {
+        for name in self.names()? {
+            for version in self.versions(&name)? {
+                let schema = self.get(&name, version)?;
+                let schema_fingerprint = fingerprint(&schema);
+                if schema_fingerprint.eq_ignore_ascii_case(fingerprint_hex) {
+                    return Ok(SchemaEntry {
+                        name,
+                        version,
+                        fingerprint: schema_fingerprint,
+                    });
+                }
+            }
+        }
+        Err(RegistryError::FingerprintNotFound(fingerprint_hex.to_string()))
+    }
+
+    fn versions(&self, name: &str) -> Result<Vec<u32>, RegistryError> {
+        validate_name(name)?;
+        let directory = self.root.join(name);
+        if !directory.is_dir() {
+            return Err(RegistryError::NameNotFound(name.to_string()));
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let file_name = entry?.file_name();
+            let version = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(SCHEMA_FILE_SUFFIX))
+                .and_then(|version| version.parse::<u32>().ok());
+            if let Some(version) = version {
+                versions.push(version);
+            }
+        }
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    fn names(&self) -> Result<Vec<String>, RegistryError> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// A SchemaRegistry kept in memory, useful for tests and as a backend for short lived registry servers
+#[derive(Default)]
+pub struct InMemorySchemaRegistry {
+    schemas: RwLock<BTreeMap<String, BTreeMap<u32, BorshSchemaContainer>>>,
+}
+
+impl InMemorySchemaRegistry {
+    pub fn new() -> InMemorySchemaRegistry {
+        InMemorySchemaRegistry::default()
+    }
+}
+
+impl SchemaRegistry for InMemorySchemaRegistry {
+    fn register(&self, name: &str, version: u32, schema: &BorshSchemaContainer, force: bool) -> Result<SchemaEntry, RegistryError> {
+        validate_name(name)?;
+
+        // Checked and inserted under one lock so that concurrent registrations of a version can't both succeed
+        let mut schemas = self.schemas.write().expect("Schema registry lock poisoned");
+        let versions: Vec<u32> = schemas.get(name).map(|versions| versions.keys().copied().collect()).unwrap_or_default();
+        check_versions(name, version, schema, force, &versions, |previous| Ok(schemas[name][&previous].clone()))?;
+        schemas
+            .entry(name.to_string())
+            .or_default()
+            .insert(version, schema.clone());
+
+        Ok(SchemaEntry {
+            name: name.to_string(),
+            version,
+            fingerprint: fingerprint(schema),
+        })
+    }
+
+    fn get(&self, name: &str, version: u32) -> Result<BorshSchemaContainer, RegistryError> {
+        let schemas = self.schemas.read().expect("Schema registry lock poisoned");
+        schemas
+            .get(name)
+            .and_then(|versions| versions.get(&version))
+            .cloned()
+            .ok_or_else(|| RegistryError::NotFound(name.to_string(), version))
+    }
+
+    fn find_by_fingerprint(&self, fingerprint_hex: &str) -> Result<SchemaEntry, RegistryError> {
+        let schemas = self.schemas.read().expect("Schema registry lock poisoned");
+        for (name, versions) in schemas.iter() {
+            for (version, schema) in versions {
+                let schema_fingerprint = fingerprint(schema);
+                if schema_fingerprint.eq_ignore_ascii_case(fingerprint_hex) {
+                    return Ok(SchemaEntry {
+                        name: name.clone(),
+                        version: *version,
+                        fingerprint: schema_fingerprint,
+                    });
+                }
+            }
+        }
+        Err(RegistryError::FingerprintNotFound(fingerprint_hex.to_string()))
+    }
+
+    fn versions(&self, name: &str) -> Result<Vec<u32>, RegistryError> {
+        let schemas = self.schemas.read().expect("Schema registry lock poisoned");
+        schemas
+            .get(name)
+            .map(|versions| versions.keys().copied().collect())
+            .ok_or_else(|| RegistryError::NameNotFound(name.to_string()))
+    }
+
+    fn names(&self) -> Result<Vec<String>, RegistryError> {
+        let schemas = self.schemas.read().expect("Schema registry lock poisoned");
+        Ok(schemas.keys().cloned().collect())
+    }
+}
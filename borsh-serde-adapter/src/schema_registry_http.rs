@@ -0,0 +1,458 @@
+//! A minimal HTTP service for a SchemaRegistry and a caching client for it. Enabled with the `http` feature.
+//!
+//! The server exposes the following routes:
+//!
+//! | Route                                    | Response                                             |
+//! |------------------------------------------|------------------------------------------------------|
+//! | `GET /schemas`                           | JSON array of schema names                           |
+//! | `GET /schemas/{name}`                    | JSON array of versions                               |
+//! | `GET /schemas/{name}/latest`             | The highest version of the schema                    |
+//! | `GET /schemas/{name}/{version}`          | The schema                                           |
+//! | `GET /fingerprints/{fingerprint}`        | The schema with the fingerprint                      |
+//! | `PUT /schemas/{name}/{version}?force=true` | Registers the borsh serialized schema in the body, returns the SchemaEntry. Bodies over the server's maximum length are refused with a 413 |
+//!
+//! Schemas are returned as borsh binary by default, or as JSON (the format written by `write_schema_as_json`) when
+//! `?format=json` is added or the `Accept` header is `application/json`. Schema responses carry the `X-Schema-Name`,
+//! `X-Schema-Version` and `X-Schema-Fingerprint` headers, with the name percent-encoded. Errors are returned as a JSON object with an `error` message,
+//! and a `kind` of `invalid_name` when the registry refused the schema name. Names are percent-encoded in paths, so a
+//! name may hold characters such as `/`, `?` or spaces and still reach the route above.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+use borsh::schema::BorshSchemaContainer;
+use borsh::{to_vec, BorshDeserialize};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::borsh_schema_util::schema_container_to_json;
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::errors::RegistryError;
+use crate::schema_compatibility::BreakingChange;
+use crate::schema_registry::{fingerprint, SchemaEntry, SchemaRegistry};
+
+const SCHEMA_NAME_HEADER: &str = "X-Schema-Name";
+const SCHEMA_VERSION_HEADER: &str = "X-Schema-Version";
+const SCHEMA_FINGERPRINT_HEADER: &str = "X-Schema-Fingerprint";
+const INVALID_NAME_KIND: &str = "invalid_name";
+/// The largest schema body the server accepts unless told otherwise
+pub const DEFAULT_MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Error body returned by the server
+#[derive(Debug, Serialize, Deserialize)]
+struct ErrorBody {
+    error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    changes: Vec<BreakingChange>,
+}
+
+/// Serves a SchemaRegistry over HTTP
+pub struct SchemaRegistryServer<R> {
+    server: Arc<Server>,
+    registry: Arc<R>,
+    max_body_length: usize,
+}
+
+impl<R: SchemaRegistry + Send + Sync + 'static> SchemaRegistryServer<R> {
+    /// Binds the server to an address such as "127.0.0.1:8080". Use port 0 to pick a free port.
+    pub fn bind(addr: &str, registry: Arc<R>) -> Result<SchemaRegistryServer<R>, RegistryError> {
+        Self::bind_with_max_body_length(addr, registry, DEFAULT_MAX_BODY_LENGTH)
+    }
+
+    /// Binds the server as `bind` does, refusing schema bodies longer than `max_body_length` bytes with a 413
+    pub fn bind_with_max_body_length(addr: &str, registry: Arc<R>, max_body_length: usize) -> Result<SchemaRegistryServer<R>, RegistryError> {
+        let server = Server::http(addr).map_err(|err| RegistryError::Http(err.to_string()))?;
+        Ok(SchemaRegistryServer {
+            server: Arc::new(server),
+            registry,
+            max_body_length,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("Schema registry server is not bound to an IP address")
+    }
+
+    /// Handles requests on the current thread until the server is shut down
+    pub fn run(&self) {
+        for request in self.server.incoming_requests() {
+            handle_request(self.registry.as_ref(), request, self.max_body_length);
+        }
+    }
+
+    /// Handles requests on a background thread. The server stops when the returned handle is shut down or dropped.
+    pub fn spawn(self) -> SchemaRegistryServerHandle {
+        let addr = self.local_addr();
+        let server = self.server.clone();
+        let thread = std::thread::spawn(move || self.run());
+        SchemaRegistryServerHandle {
+            server,
+            addr,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Handle to a SchemaRegistryServer running on a background thread
+pub struct SchemaRegistryServerHandle {
+    server: Arc<Server>,
+    addr: SocketAddr,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SchemaRegistryServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL of the server, e.g. "http://127.0.0.1:8080"
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Stops the server and waits for the background thread to finish
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SchemaRegistryServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn handle_request(registry: &impl SchemaRegistry, mut request: Request, max_body_length: usize) {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (decode_query_component(key), decode_query_component(value)))
+        .collect();
+    let wants_json = query.get("format").is_some_and(|format| format == "json")
+        || request
+            .headers()
+            .iter()
+            .any(|header| header.field.equiv("Accept") && header.value.as_str().contains("application/json"));
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(decode_path_segment).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    debug!("Schema registry request {} {}", request.method(), request.url());
+
+    let response = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["schemas"]) => registry.names().map(|names| json_response(200, &names)),
+        (Method::Get, ["schemas", name]) => registry.versions(name).map(|versions| json_response(200, &versions)),
+        (Method::Get, ["schemas", name, "latest"]) => registry
+            .get_latest(name)
+            .and_then(|(version, schema)| schema_response(name, version, &schema, wants_json)),
+        (Method::Get, ["schemas", name, version]) => match version.parse::<u32>() {
+            Ok(version) => registry
+                .get(name, version)
+                .and_then(|schema| schema_response(name, version, &schema, wants_json)),
+            Err(_) => Ok(error_response(400, format!("Invalid version {version}"))),
+        },
+        (Method::Put, ["schemas", name, version]) => match version.parse::<u32>() {
+            Ok(version) => {
+                let force = query.get("force").is_some_and(|force| force == "true");
+                let mut body = Vec::new();
+                // One byte past the limit is read to tell a body of exactly the limit from a longer one
+                match Read::take(request.as_reader(), max_body_length as u64 + 1).read_to_end(&mut body) {
+                    Ok(_) if body.len() > max_body_length => {
+                        Ok(error_response(413, format!("Schema body is longer than {max_body_length} bytes")))
+                    }
+                    Ok(_) => match BorshSchemaContainer::deserialize(&mut body.as_slice()) {
+                        Ok(schema) => registry
+                            .register(name, version, &schema, force)
+                            .map(|entry| json_response(201, &entry)),
+                        Err(err) => Ok(error_response(400, format!("Invalid schema: {err}"))),
+                    },
+                    Err(err) => Err(err.into()),
+                }
+            }
+            Err(_) => Ok(error_response(400, format!("Invalid version {version}"))),
+        },
+        (Method::Get, ["fingerprints", fingerprint]) => registry.find_by_fingerprint(fingerprint).and_then(|entry| {
+            let schema = registry.get(&entry.name, entry.version)?;
+            schema_response(&entry.name, entry.version, &schema, wants_json)
+        }),
+        _ => Ok(error_response(404, format!("No route for {} {}", request.method(), path))),
+    };
+
+    let response = response.unwrap_or_else(registry_error_response);
+    if let Err(err) = request.respond(response) {
+        debug!("Failed to send schema registry response: {err}");
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
+
+fn json_response(status: u16, body: &impl Serialize) -> Response<std::io::Cursor<Vec<u8>>> {
+    let data = serde_json::to_vec(body).expect("Failed to serialize response");
+    Response::from_data(data)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error_response(status: u16, error: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, &ErrorBody { error, kind: None, previous: None, changes: Vec::new() })
+}
+
+fn registry_error_response(err: RegistryError) -> Response<std::io::Cursor<Vec<u8>>> {
+    let status = match &err {
+        RegistryError::NameNotFound(_) | RegistryError::NotFound(..) | RegistryError::FingerprintNotFound(_) => 404,
+        RegistryError::InvalidName(_) => 400,
+        RegistryError::AlreadyExists(..) | RegistryError::BreakingChange { .. } => 409,
+        RegistryError::Io(_) | RegistryError::Http(_) => 500,
+    };
+    let error = err.to_string();
+    let body = match err {
+        RegistryError::BreakingChange { previous, changes, .. } => ErrorBody { error, kind: None, previous: Some(previous), changes },
+        RegistryError::InvalidName(_) => ErrorBody { error, kind: Some(INVALID_NAME_KIND.to_string()), previous: None, changes: Vec::new() },
+        _ => ErrorBody { error, kind: None, previous: None, changes: Vec::new() },
+    };
+    json_response(status, &body)
+}
+
+fn schema_response(
+    name: &str,
+    version: u32,
+    schema: &BorshSchemaContainer,
+    wants_json: bool,
+) -> Result<Response<std::io::Cursor<Vec<u8>>>, RegistryError> {
+    let response = if wants_json {
+        json_response(200, &schema_container_to_json(schema)?)
+    } else {
+        Response::from_data(to_vec(schema)?).with_header(header("Content-Type", "application/octet-stream"))
+    };
+    Ok(response
+        .with_header(header(SCHEMA_NAME_HEADER, &encode_path_segment(name)))
+        .with_header(header(SCHEMA_VERSION_HEADER, &version.to_string()))
+        .with_header(header(SCHEMA_FINGERPRINT_HEADER, &fingerprint(schema))))
+}
+
+/// A SchemaRegistry backed by a SchemaRegistryServer. Fetched schemas are cached in memory, since a registered
+/// version never changes.
+pub struct SchemaRegistryClient {
+    base_url: String,
+    agent: ureq::Agent,
+    schemas: RwLock<HashMap<(String, u32), Arc<BorshSchemaContainer>>>,
+    fingerprints: RwLock<HashMap<String, SchemaEntry>>,
+}
+
+impl SchemaRegistryClient {
+    /// Creates a client for the server at the base URL, e.g. "http://127.0.0.1:8080"
+    pub fn new(base_url: &str) -> SchemaRegistryClient {
+        SchemaRegistryClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+            schemas: RwLock::new(HashMap::new()),
+            fingerprints: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the schema from the cache, fetching it from the server if it is not cached yet
+    pub fn get_cached(&self, name: &str, version: u32) -> Result<Arc<BorshSchemaContainer>, RegistryError> {
+        let key = (name.to_string(), version);
+        if let Some(schema) = self.schemas.read().expect("Schema cache lock poisoned").get(&key) {
+            return Ok(schema.clone());
+        }
+
+        let response = self.call(self.agent.get(&format!("{}/schemas/{}/{version}", self.base_url, encode_path_segment(name))), None)
+            .map_err(|failure| failure.into_error(|| RegistryError::NotFound(name.to_string(), version)))?;
+        let schema = Arc::new(read_schema(response)?);
+        self.schemas.write().expect("Schema cache lock poisoned").insert(key, schema.clone());
+        Ok(schema)
+    }
+
+    /// Deserializes borsh serialized bytes to serde_json::Value using the named schema version
+    pub fn deserialize(&self, name: &str, version: u32, buffer: &mut &[u8]) -> Result<serde_json::Value, RegistryError> {
+        let schema = self.get_cached(name, version)?;
+        Ok(deserialize_from_schema(buffer, &schema)?)
+    }
+
+    /// Removes all cached schemas
+    pub fn clear_cache(&self) {
+        self.schemas.write().expect("Schema cache lock poisoned").clear();
+        self.fingerprints.write().expect("Schema cache lock poisoned").clear();
+    }
+
+    fn call(&self, request: ureq::Request, body: Option<&[u8]>) -> Result<ureq::Response, HttpFailure> {
+        let result = match body {
+            Some(body) => request
+                .set("Content-Type", "application/octet-stream")
+                .send_bytes(body),
+            None => request.call(),
+        };
+        match result {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response.into_json::<ErrorBody>().unwrap_or_else(|err| ErrorBody {
+                    error: err.to_string(),
+                    kind: None,
+                    previous: None,
+                    changes: Vec::new(),
+                });
+                Err(HttpFailure::Status(status, body))
+            }
+            Err(err) => Err(HttpFailure::Transport(err.to_string())),
+        }
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str, not_found: impl FnOnce() -> RegistryError) -> Result<T, RegistryError> {
+        let response = self
+            .call(self.agent.get(&format!("{}{path}", self.base_url)), None)
+            .map_err(|failure| failure.into_error(not_found))?;
+        Ok(response.into_json()?)
+    }
+}
+
+enum HttpFailure {
+    Status(u16, ErrorBody),
+    Transport(String),
+}
+
+impl HttpFailure {
+    fn into_error(self, not_found: impl FnOnce() -> RegistryError) -> RegistryError {
+        match self {
+            HttpFailure::Status(404, _) => not_found(),
+            HttpFailure::Status(status, body) => RegistryError::Http(format!("{status}: {}", body.error)),
+            HttpFailure::Transport(err) => RegistryError::Http(err),
+        }
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986, so the segment can't end the path early
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Reverses encode_path_segment. Malformed escapes are kept as they are.
+fn decode_path_segment(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Decodes a query string key or value, where `+` stands for a space
+fn decode_query_component(component: &str) -> String {
+    decode_path_segment(&component.replace('+', " "))
+}
+
+fn read_schema(response: ureq::Response) -> Result<BorshSchemaContainer, RegistryError> {
+    let mut reader = response.into_reader();
+    Ok(BorshSchemaContainer::deserialize_reader(&mut reader)?)
+}
+
+impl SchemaRegistry for SchemaRegistryClient {
+    fn register(&self, name: &str, version: u32, schema: &BorshSchemaContainer, force: bool) -> Result<SchemaEntry, RegistryError> {
+        let url = format!("{}/schemas/{}/{version}?force={force}", self.base_url, encode_path_segment(name));
+        let data = to_vec(schema)?;
+        match self.call(self.agent.put(&url), Some(&data)) {
+            Ok(response) => {
+                self.schemas
+                    .write()
+                    .expect("Schema cache lock poisoned")
+                    .insert((name.to_string(), version), Arc::new(schema.clone()));
+                Ok(response.into_json()?)
+            }
+            Err(HttpFailure::Status(400, body)) if body.kind.as_deref() == Some(INVALID_NAME_KIND) => {
+                Err(RegistryError::InvalidName(name.to_string()))
+            }
+            Err(HttpFailure::Status(409, body)) => match body.previous {
+                Some(previous) => Err(RegistryError::BreakingChange {
+                    name: name.to_string(),
+                    version,
+                    previous,
+                    changes: body.changes,
+                }),
+                None => Err(RegistryError::AlreadyExists(name.to_string(), version)),
+            },
+            Err(failure) => Err(failure.into_error(|| RegistryError::NameNotFound(name.to_string()))),
+        }
+    }
+
+    fn get(&self, name: &str, version: u32) -> Result<BorshSchemaContainer, RegistryError> {
+        self.get_cached(name, version).map(|schema| schema.as_ref().clone())
+    }
+
+    fn find_by_fingerprint(&self, fingerprint_hex: &str) -> Result<SchemaEntry, RegistryError> {
+        let key = fingerprint_hex.to_ascii_lowercase();
+        if let Some(entry) = self.fingerprints.read().expect("Schema cache lock poisoned").get(&key) {
+            return Ok(entry.clone());
+        }
+
+        let response = self
+            .call(self.agent.get(&format!("{}/fingerprints/{}", self.base_url, encode_path_segment(&key))), None)
+            .map_err(|failure| failure.into_error(|| RegistryError::FingerprintNotFound(fingerprint_hex.to_string())))?;
+        let name = response.header(SCHEMA_NAME_HEADER).map(decode_path_segment);
+        let version = response.header(SCHEMA_VERSION_HEADER).and_then(|version| version.parse::<u32>().ok());
+        let (name, version) = name
+            .zip(version)
+            .ok_or_else(|| RegistryError::Http("Missing schema headers in response".to_string()))?;
+        let schema = read_schema(response)?;
+
+        let entry = SchemaEntry {
+            name: name.clone(),
+            version,
+            fingerprint: key.clone(),
+        };
+        self.schemas.write().expect("Schema cache lock poisoned").insert((name, version), Arc::new(schema));
+        self.fingerprints.write().expect("Schema cache lock poisoned").insert(key, entry.clone());
+        Ok(entry)
+    }
+
+    fn versions(&self, name: &str) -> Result<Vec<u32>, RegistryError> {
+        self.get_json(&format!("/schemas/{}", encode_path_segment(name)), || RegistryError::NameNotFound(name.to_string()))
+    }
+
+    fn names(&self) -> Result<Vec<String>, RegistryError> {
+        self.get_json("/schemas", || RegistryError::Http("Schema listing not found".to_string()))
+    }
+}
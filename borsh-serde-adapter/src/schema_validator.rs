@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition};
+
+use crate::borsh_schema_util::{definition_children, is_supported_primitive};
+use crate::errors::SchemaIssue;
+
+/// Checks a BorshSchemaContainer for problems that would make the adapters misbehave: declarations without a
+/// definition, primitives the adapters do not know, types that contain themselves unconditionally, sequences whose
+/// length can't be determined and enums with duplicate discriminants or variant names. Every definition in the
+/// container is checked, not only the ones reachable from its declaration. An empty result means the schema is usable.
+pub fn validate_schema(schema: &BorshSchemaContainer) -> Vec<SchemaIssue> {
+    let mut issues = Vec::new();
+
+    check_reference(schema, schema.declaration(), "container", &mut issues);
+    for (declaration, definition) in schema.definitions() {
+        for child in definition_children(definition) {
+            check_reference(schema, child, declaration, &mut issues);
+        }
+
+        match definition {
+            Definition::Primitive(_) => {
+                if !is_supported_primitive(declaration) {
+                    issues.push(SchemaIssue::UnsupportedPrimitive(declaration.clone()));
+                }
+            }
+
+            Definition::Sequence { length_width, length_range, .. } => {
+                if !matches!(length_width, 0 | 1 | 2 | 4 | 8) {
+                    issues.push(SchemaIssue::UnsupportedWidth(declaration.clone(), *length_width));
+                }
+                if length_range.is_empty() {
+                    issues.push(SchemaIssue::EmptyLengthRange(declaration.clone()));
+                } else if *length_width == 0 && length_range.start() != length_range.end() {
+                    issues.push(SchemaIssue::InconsistentFixedLength(declaration.clone(), length_range.clone()));
+                }
+            }
+
+            Definition::Enum { tag_width, variants } => {
+                if !matches!(tag_width, 1 | 2 | 4 | 8) {
+                    issues.push(SchemaIssue::UnsupportedWidth(declaration.clone(), *tag_width));
+                }
+                let mut discriminants = HashSet::new();
+                let mut names = HashSet::new();
+                for (discriminant, name, _) in variants {
+                    if !discriminants.insert(discriminant) {
+                        issues.push(SchemaIssue::DuplicateDiscriminant(declaration.clone(), *discriminant));
+                    }
+                    if !names.insert(name) {
+                        issues.push(SchemaIssue::DuplicateVariant(declaration.clone(), name.clone()));
+                    }
+                }
+            }
+
+            Definition::Tuple { .. } | Definition::Struct { .. } => {}
+        }
+
+        if contains_itself_unconditionally(schema, declaration) {
+            issues.push(SchemaIssue::InfiniteRecursion(declaration.clone()));
+        }
+    }
+
+    issues
+}
+
+fn check_reference(schema: &BorshSchemaContainer, declaration: &Declaration, referenced_by: &str, issues: &mut Vec<SchemaIssue>) {
+    if schema.get_definition(declaration).is_none() && !is_supported_primitive(declaration) {
+        issues.push(SchemaIssue::DanglingReference(declaration.clone(), referenced_by.to_string()));
+    }
+}
+
+/// Children that are always decoded when their parent is. Enum variants and length prefixed sequences are only
+/// decoded depending on the data, so recursion through them terminates.
+fn unconditional_children(definition: &Definition) -> Vec<&Declaration> {
+    match definition {
+        Definition::Sequence { length_width: 0, length_range, elements } if *length_range.end() > 0 => vec![elements],
+        Definition::Sequence { .. } | Definition::Enum { .. } => Vec::new(),
+        _ => definition_children(definition),
+    }
+}
+
+fn contains_itself_unconditionally(schema: &BorshSchemaContainer, declaration: &Declaration) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack: Vec<&Declaration> = schema
+        .get_definition(declaration)
+        .map(unconditional_children)
+        .unwrap_or_default();
+
+    while let Some(current) = stack.pop() {
+        if current == declaration {
+            return true;
+        }
+        if !visited.insert(current) {
+            continue;
+        }
+        if let Some(definition) = schema.get_definition(current) {
+            stack.extend(unconditional_children(definition));
+        }
+    }
+    false
+}
@@ -0,0 +1,83 @@
+use std::io::Write;
+
+use borsh::schema::BorshSchemaContainer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::deserialize_adapter::deserialize_from_schema;
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// Deserializes borsh serialized bytes using the provided schema and writes the value with any serde serializer, in the
+/// same form deserialize_from_schema produces
+pub fn transcode_from_borsh<S>(buffer: &mut &[u8], schema: &BorshSchemaContainer, serializer: S) -> anyhow::Result<S::Ok>
+where
+    S: Serializer,
+    S::Error: Send + Sync + 'static,
+{
+    let value = deserialize_from_schema(buffer, schema)?;
+    Ok(value.serialize(serializer)?)
+}
+
+/// Reads a value with any serde deserializer, in the form deserialize_from_schema produces, and writes it as borsh
+/// serialized bytes using the provided schema
+pub fn transcode_to_borsh<'de, D>(writer: &mut impl Write, deserializer: D, schema: &BorshSchemaContainer) -> anyhow::Result<()>
+where
+    D: Deserializer<'de>,
+    D::Error: Send + Sync + 'static,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    serialize_serde_json_to_borsh(writer, &value, schema)
+}
+
+/// Deserializes borsh serialized bytes to a YAML document using the provided schema
+#[cfg(feature = "yaml")]
+pub fn deserialize_to_yaml(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<String> {
+    Ok(serde_yaml::to_string(&deserialize_from_schema(buffer, schema)?)?)
+}
+
+/// Serializes a YAML document to borsh serialized bytes using the provided schema
+#[cfg(feature = "yaml")]
+pub fn serialize_yaml_to_borsh(writer: &mut impl Write, yaml: &str, schema: &BorshSchemaContainer) -> anyhow::Result<()> {
+    transcode_to_borsh(writer, serde_yaml::Deserializer::from_str(yaml), schema)
+}
+
+/// Deserializes borsh serialized bytes to a TOML document using the provided schema. TOML can only express a subset of
+/// values: the schema's declaration must be a struct with named fields, and values can't contain null, which is how
+/// `()` and `None` are given, or integers above i64::MAX.
+#[cfg(feature = "toml")]
+pub fn deserialize_to_toml(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<String> {
+    Ok(toml::to_string(&deserialize_from_schema(buffer, schema)?)?)
+}
+
+/// Serializes a TOML document to borsh serialized bytes using the provided schema
+#[cfg(feature = "toml")]
+pub fn serialize_toml_to_borsh(writer: &mut impl Write, toml: &str, schema: &BorshSchemaContainer) -> anyhow::Result<()> {
+    transcode_to_borsh(writer, toml::Deserializer::new(toml), schema)
+}
+
+/// Deserializes borsh serialized bytes to CBOR using the provided schema
+#[cfg(feature = "cbor")]
+pub fn deserialize_to_cbor(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<Vec<u8>> {
+    let mut cbor = Vec::new();
+    ciborium::into_writer(&deserialize_from_schema(buffer, schema)?, &mut cbor)?;
+    Ok(cbor)
+}
+
+/// Serializes CBOR to borsh serialized bytes using the provided schema
+#[cfg(feature = "cbor")]
+pub fn serialize_cbor_to_borsh(writer: &mut impl Write, cbor: &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<()> {
+    let value: serde_json::Value = ciborium::from_reader(cbor)?;
+    serialize_serde_json_to_borsh(writer, &value, schema)
+}
+
+/// Deserializes borsh serialized bytes to MessagePack using the provided schema. Structs are written as maps, so the
+/// field names are kept.
+#[cfg(feature = "msgpack")]
+pub fn deserialize_to_msgpack(buffer: &mut &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<Vec<u8>> {
+    Ok(rmp_serde::to_vec(&deserialize_from_schema(buffer, schema)?)?)
+}
+
+/// Serializes MessagePack to borsh serialized bytes using the provided schema
+#[cfg(feature = "msgpack")]
+pub fn serialize_msgpack_to_borsh(writer: &mut impl Write, msgpack: &[u8], schema: &BorshSchemaContainer) -> anyhow::Result<()> {
+    transcode_to_borsh(writer, &mut rmp_serde::Deserializer::new(msgpack), schema)
+}
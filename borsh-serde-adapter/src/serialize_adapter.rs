@@ -1,18 +1,31 @@
-#![allow(clippy::unnecessary_find_map)]
-
 use std::io::{Write};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::sync::LazyLock;
 
 use anyhow::anyhow;
-use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
 use borsh::{BorshSerialize};
 use log::debug;
 use serde_json::{json};
+use crate::borsh_schema_util::PrimitiveType;
+use crate::deserialize_adapter::DEFAULT_MAX_DEPTH;
 use crate::errors::ExpectationError;
 
 /// Serializes serde_json::Value to borsh serialized bytes using the provided schema
 pub fn serialize_serde_json_to_borsh(writer: &mut impl Write, value: &serde_json::Value, schema: &BorshSchemaContainer) -> anyhow::Result<()> {
-    serialize_serde_json_by_declaration_with_schema(writer, value, schema, schema.declaration())
+    serialize_serde_json_by_declaration_with_schema(writer, value, schema, schema.declaration(), DEFAULT_MAX_DEPTH)
+}
+
+/// Serializes serde_json::Value to borsh serialized bytes using the provided schema, failing if structs, tuples,
+/// sequences and enums are nested deeper than max_depth
+pub fn serialize_serde_json_to_borsh_with_max_depth(
+    writer: &mut impl Write,
+    value: &serde_json::Value,
+    schema: &BorshSchemaContainer,
+    max_depth: usize,
+) -> anyhow::Result<()> {
+    serialize_serde_json_by_declaration_with_schema(writer, value, schema, schema.declaration(), max_depth)
 }
 
 fn serialize_signed_to_borsh<T: BorshSerialize + TryFrom<i64>>(writer: &mut impl Write, value: &serde_json::Value) -> anyhow::Result<()>
@@ -45,155 +58,211 @@ fn serialize_serde_json_to_borsh_by_type<T: BorshSerialize + FromStr>(writer: &m
     Ok(())
 }
 
-fn serialize_serde_json_by_declaration_with_schema(
-    writer: &mut impl Write,
-    value: &serde_json::Value,
-    schema: &BorshSchemaContainer,
-    declaration: &borsh::schema::Declaration,
-) -> anyhow::Result<()> {
-    match &declaration[..] {
-        "u8" => serialize_unsigned_to_borsh::<u8>(writer, value),
-        "u16" => serialize_unsigned_to_borsh::<u16>(writer, value),
-        "u32" => serialize_unsigned_to_borsh::<u32>(writer, value),
-        "u64" => serialize_unsigned_to_borsh::<u64>(writer, value),
-        "u128" => serialize_serde_json_to_borsh_by_type::<u128>(writer, value),
-        "i8" => serialize_signed_to_borsh::<i8>(writer, value),
-        "i16" => serialize_signed_to_borsh::<i16>(writer, value),
-        "i32" => serialize_signed_to_borsh::<i32>(writer, value),
-        "i64" => serialize_signed_to_borsh::<i64>(writer, value),
-        "i128" => serialize_serde_json_to_borsh_by_type::<i128>(writer, value),
-        "f32" => {
+/// Writes a little-endian unsigned integer of the given width, used for sequence lengths and enum tags
+pub(crate) fn write_width(writer: &mut impl Write, width: u8, value: u64) -> anyhow::Result<()> {
+    match width {
+        1 => BorshSerialize::serialize(&u8::try_from(value)?, writer)?,
+        2 => BorshSerialize::serialize(&u16::try_from(value)?, writer)?,
+        4 => BorshSerialize::serialize(&u32::try_from(value)?, writer)?,
+        8 => BorshSerialize::serialize(&value, writer)?,
+        _ => return Err(anyhow!("Unsupported width of {width} bytes")),
+    }
+    Ok(())
+}
+
+pub(crate) fn serialize_primitive(writer: &mut impl Write, value: &serde_json::Value, primitive: PrimitiveType) -> anyhow::Result<()> {
+    match primitive {
+        PrimitiveType::U8 => serialize_unsigned_to_borsh::<u8>(writer, value),
+        PrimitiveType::U16 => serialize_unsigned_to_borsh::<u16>(writer, value),
+        PrimitiveType::U32 => serialize_unsigned_to_borsh::<u32>(writer, value),
+        PrimitiveType::U64 => serialize_unsigned_to_borsh::<u64>(writer, value),
+        PrimitiveType::U128 => serialize_serde_json_to_borsh_by_type::<u128>(writer, value),
+        PrimitiveType::I8 => serialize_signed_to_borsh::<i8>(writer, value),
+        PrimitiveType::I16 => serialize_signed_to_borsh::<i16>(writer, value),
+        PrimitiveType::I32 => serialize_signed_to_borsh::<i32>(writer, value),
+        PrimitiveType::I64 => serialize_signed_to_borsh::<i64>(writer, value),
+        PrimitiveType::I128 => serialize_serde_json_to_borsh_by_type::<i128>(writer, value),
+        PrimitiveType::F32 => {
             //TODO Is there a better way to do this?
             let value = value.as_f64().ok_or(ExpectationError::Number)? as f32;
             BorshSerialize::serialize(&value, writer)?;
             Ok(())
-        },
-        "f64" => {
+        }
+        PrimitiveType::F64 => {
             let value = value.as_f64().ok_or(ExpectationError::Number)?;
             BorshSerialize::serialize(&value, writer)?;
             Ok(())
-        },
-        "String" => serialize_serde_json_to_borsh_by_type::<String>(writer, value),
-        "bool" => {
+        }
+        PrimitiveType::String => serialize_serde_json_to_borsh_by_type::<String>(writer, value),
+        PrimitiveType::Bool => {
             let value = value.as_bool().ok_or(ExpectationError::Boolean)?;
             BorshSerialize::serialize(&value, writer)?;
             Ok(())
         }
-        _ => {
-            if let Some(definition) = schema.get_definition(declaration) {
-                match definition {
-
-                    Definition::Primitive { .. } => {
-                        serialize_serde_json_by_declaration_with_schema(writer, value, schema, declaration)?;
-                        Ok(())
-                    }
-
-                    Definition::Sequence { length_width, length_range: _, elements } => {
-                        let sequence = value.as_array().ok_or(ExpectationError::Array)?;
-                        let length_width = *length_width as u32;
-                        if length_width != 0  {
-                            let length = sequence.len();
-                            BorshSerialize::serialize(&(length as u32), writer)?;
-                        };
-                        for item in sequence {
-                            serialize_serde_json_by_declaration_with_schema(writer, item, schema, elements)?;
-                        }
-                        Ok(())
-                    }
-
-                    Definition::Tuple { elements } => {
-                        let tuple = value.as_array().ok_or(ExpectationError::Array)?;
-                        if tuple.len() != elements.len() {
-                            return Err(
-                                ExpectationError::ArrayOfLength(elements.len() as u32).into()
-                            );
-                        }
-                        for (declaration, value) in elements.iter().zip(tuple) {
-                            serialize_serde_json_by_declaration_with_schema(writer, value, schema, declaration)?;
-                        }
-                        Ok(())
-                    }
-
-                    Definition::Enum { variants, .. } => {
-                        let (input_variant, variant_values) = value
-                            .as_object()
-                            .and_then(|o| o.keys().next().map(|s| (s.as_str(), Some(&o[s]))))
-                            .or_else(|| value.as_str().map(|s| (s, None)))
-                            .ok_or(ExpectationError::Object)?;
-
-                        let (variant_index, variant_declaration) = variants
-                            .iter()
-                            .enumerate()
-                            .find_map(|(i, (.., v))| {
-                                Some((i, v))
-                            })
-                            .ok_or_else(|| {
-                                anyhow!(
-                                    "Variant {input_variant} does not exist in schema"
-                                )
-                            })?;
-
-                        BorshSerialize::serialize(&(variant_index as u8), writer)?;
-                        serialize_serde_json_by_declaration_with_schema(
-                            writer,
-                            variant_values.unwrap_or(&json!({})),
-                            schema,
-                            variant_declaration,
-                        )?;
-                        Ok(())
-                    }
-
-                    Definition::Struct { fields } => match fields {
-                        Fields::NamedFields(fields) => {
-                            let object = value.as_object().ok_or(ExpectationError::Object)?;
-                            for (key, value_declaration) in fields {
-                                let property_value = object
-                                    .get(key.as_str())
-                                    .ok_or_else(|| anyhow!("Expected property {key}"))?;
-                                serialize_serde_json_by_declaration_with_schema(
-                                    writer,
-                                    property_value,
-                                    schema,
-                                    value_declaration,
-                                )?;
-                            }
-                            Ok(())
-                        }
-
-                        Fields::UnnamedFields(fields) => {
-                            if fields.len() == 1 {
-                                serialize_serde_json_by_declaration_with_schema(
-                                    writer, value, schema, &fields[0],
-                                )?;
-                                return Ok(());
-                            }
-
-                            let array = value.as_array().ok_or(ExpectationError::Array)?;
-                            if array.len() != fields.len() {
-                                return Err(
-                                    ExpectationError::ArrayOfLength(fields.len() as u32).into()
-                                );
-                            }
-                            for (declaration, value) in fields.iter().zip(array) {
-                                serialize_serde_json_by_declaration_with_schema(
-                                    writer,
-                                    value,
-                                    schema,
-                                    declaration,
-                                )?;
-                            }
-                            Ok(())
-                        }
-
-                        Fields::Empty => {
-                            Ok(())
-                        }
-                    },
-                }
-            } else {
-                debug!("Can't serialize unknown type: {:?}. Returning Ok(())", declaration);
-                Ok(())
+        // The unit type has no bytes, and like an empty struct any value is accepted for it
+        PrimitiveType::Unit => Ok(()),
+    }
+}
+
+/// The children of a composite value, paired with whatever describes how to serialize them
+pub(crate) type Children<'a, T> = Vec<(&'a serde_json::Value, &'a T)>;
+
+/// Contents used for enum variants given as a plain string, such as "Ping" instead of {"Ping": {}}
+pub(crate) static EMPTY_OBJECT: LazyLock<serde_json::Value> = LazyLock::new(|| json!({}));
+
+/// Writes the length of a sequence and returns its elements
+pub(crate) fn sequence_children<'a, T>(
+    writer: &mut impl Write,
+    value: &'a serde_json::Value,
+    length_width: u8,
+    length_range: &RangeInclusive<u64>,
+    elements: &'a T,
+) -> anyhow::Result<Children<'a, T>> {
+    let sequence = value.as_array().ok_or(ExpectationError::Array)?;
+    if length_width != 0 {
+        write_width(writer, length_width, sequence.len() as u64)?;
+    } else if length_range.start() == length_range.end() && sequence.len() as u64 != *length_range.end() {
+        return Err(ExpectationError::ArrayOfLength(*length_range.end() as u32).into());
+    }
+    Ok(sequence.iter().map(|item| (item, elements)).collect())
+}
+
+/// Returns the elements of a tuple, or of a struct with unnamed fields, which are given as an array
+pub(crate) fn element_children<'a, T>(value: &'a serde_json::Value, elements: &'a [T]) -> anyhow::Result<Children<'a, T>> {
+    let array = value.as_array().ok_or(ExpectationError::Array)?;
+    if array.len() != elements.len() {
+        return Err(
+            ExpectationError::ArrayOfLength(elements.len() as u32).into()
+        );
+    }
+    Ok(array.iter().zip(elements).collect())
+}
+
+/// Returns the fields of a struct with unnamed fields. A single unnamed field is given either as an array of one
+/// element, as the deserializer gives it, or as the value itself.
+pub(crate) fn unnamed_field_children<'a, T>(value: &'a serde_json::Value, fields: &'a [T]) -> anyhow::Result<Children<'a, T>> {
+    if fields.len() == 1 {
+        let value = match value.as_array().map(Vec::as_slice) {
+            Some([field_value]) => field_value,
+            _ => value,
+        };
+        return Ok(vec![(value, &fields[0])]);
+    }
+    element_children(value, fields)
+}
+
+/// Returns the fields of a struct with named fields, in the order of the schema
+pub(crate) fn named_field_children<'a, T>(value: &'a serde_json::Value, fields: &'a [(String, T)]) -> anyhow::Result<Children<'a, T>> {
+    let object = value.as_object().ok_or(ExpectationError::Object)?;
+    fields
+        .iter()
+        .map(|(key, field)| {
+            object
+                .get(key.as_str())
+                .map(|property_value| (property_value, field))
+                .ok_or_else(|| anyhow!("Expected property {key}"))
+        })
+        .collect()
+}
+
+/// Writes the tag of the variant named by the value and returns the variant contents
+pub(crate) fn variant_children<'a, T>(
+    writer: &mut impl Write,
+    value: &'a serde_json::Value,
+    tag_width: u8,
+    variants: &'a [(i64, String, T)],
+) -> anyhow::Result<Children<'a, T>> {
+    let (input_variant, variant_values) = value
+        .as_object()
+        .and_then(|o| o.keys().next().map(|s| (s.as_str(), Some(&o[s]))))
+        .or_else(|| value.as_str().map(|s| (s, None)))
+        .ok_or(ExpectationError::Object)?;
+
+    let (discriminant, _, variant) = variants
+        .iter()
+        .find(|(_, variant_name, _)| variant_name == input_variant)
+        .ok_or_else(|| {
+            anyhow!(
+                "Variant {input_variant} does not exist in schema"
+            )
+        })?;
+
+    write_width(writer, tag_width, *discriminant as u64)?;
+    Ok(vec![(variant_values.unwrap_or(&EMPTY_OBJECT), variant)])
+}
+
+/// Serializes a primitive, or writes the length or tag of a composite value and returns its children in order. Returns
+/// None for values that have no children by definition, so only composite values count towards the nesting depth.
+fn serialize_declaration<'a>(
+    writer: &mut impl Write,
+    value: &'a serde_json::Value,
+    schema: &'a BorshSchemaContainer,
+    declaration: &'a Declaration,
+) -> anyhow::Result<Option<Children<'a, Declaration>>> {
+    if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+        serialize_primitive(writer, value, primitive)?;
+        return Ok(None);
+    }
+
+    let Some(definition) = schema.get_definition(declaration) else {
+        debug!("Can't serialize unknown type: {:?}. Returning Ok(())", declaration);
+        return Ok(None);
+    };
+
+    let children = match definition {
+        Definition::Primitive { .. } => {
+            return Err(anyhow!("Unsupported primitive type {declaration}"));
+        }
+        Definition::Sequence { length_width, length_range, elements } => {
+            sequence_children(writer, value, *length_width, length_range, elements)?
+        }
+        Definition::Tuple { elements } => element_children(value, elements)?,
+        Definition::Enum { tag_width, variants } => variant_children(writer, value, *tag_width, variants)?,
+        Definition::Struct { fields } => match fields {
+            Fields::NamedFields(fields) => named_field_children(value, fields)?,
+            Fields::UnnamedFields(fields) => unnamed_field_children(value, fields)?,
+            Fields::Empty => return Ok(None),
+        },
+    };
+    Ok(Some(children))
+}
+
+/// Serializes a value starting at root, calling visit for every value to either write it or begin a composite value
+pub(crate) fn serialize_with<'a, T, W: Write>(
+    writer: &mut W,
+    value: &'a serde_json::Value,
+    root: &'a T,
+    max_depth: usize,
+    mut visit: impl FnMut(&mut W, &'a serde_json::Value, &'a T) -> anyhow::Result<Option<Children<'a, T>>>,
+) -> anyhow::Result<()> {
+    // Children of composite values are kept on an explicit stack instead of recursing, so deeply nested values can't
+    // overflow the native stack.
+    let mut stack = vec![vec![(value, root)].into_iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((value, node)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+
+        if let Some(children) = visit(writer, value, node)? {
+            if stack.len() > max_depth {
+                return Err(anyhow!("Maximum nesting depth of {max_depth} exceeded"));
             }
+            stack.push(children.into_iter());
         }
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Serializes a value of the given declaration of the schema
+pub(crate) fn serialize_serde_json_by_declaration_with_schema(
+    writer: &mut impl Write,
+    value: &serde_json::Value,
+    schema: &BorshSchemaContainer,
+    declaration: &borsh::schema::Declaration,
+    max_depth: usize,
+) -> anyhow::Result<()> {
+    serialize_with(writer, value, declaration, max_depth, |writer, value, declaration| {
+        serialize_declaration(writer, value, schema, declaration)
+    })
+}
@@ -0,0 +1,258 @@
+use std::fmt::{Display, Formatter};
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::borsh_schema_util::{definition, escape_pointer_segment};
+use crate::deserialize_adapter::deserialize_from_schema;
+
+/// Sequences whose differing parts would need more cells than this to align are compared element by element instead
+const MAX_ALIGNMENT_CELLS: usize = 1 << 22;
+
+/// A change between two values of the same schema. Paths are JSON pointers into the values produced by
+/// deserialize_from_schema, and are such that the changes can be applied in order, as a JSON Patch would be.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValueChange {
+    /// A primitive value, or a value the schema doesn't describe, changed
+    Changed { path: String, old: Value, new: Value },
+    /// An element was inserted into a sequence
+    Added { path: String, value: Value },
+    /// An element was removed from a sequence
+    Removed { path: String, value: Value },
+    /// The active variant of an enum changed. old and new are the whole enum values.
+    VariantChanged { path: String, old_variant: String, new_variant: String, old: Value, new: Value },
+}
+
+impl ValueChange {
+    pub fn path(&self) -> &str {
+        match self {
+            ValueChange::Changed { path, .. }
+            | ValueChange::Added { path, .. }
+            | ValueChange::Removed { path, .. }
+            | ValueChange::VariantChanged { path, .. } => path,
+        }
+    }
+}
+
+impl Display for ValueChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = match self.path() {
+            "" => "/",
+            path => path,
+        };
+        match self {
+            ValueChange::Changed { old, new, .. } => write!(f, "{path}: {old} -> {new}"),
+            ValueChange::Added { value, .. } => write!(f, "{path}: added {value}"),
+            ValueChange::Removed { value, .. } => write!(f, "{path}: removed {value}"),
+            ValueChange::VariantChanged { old_variant, new_variant, new, .. } => {
+                write!(f, "{path}: variant {old_variant} -> {new_variant} {}", new[new_variant])
+            }
+        }
+    }
+}
+
+/// Decodes two borsh serialized values of the schema, as deserialize_from_schema would, and returns what changed from
+/// old to new
+pub fn diff_from_schema(old: &[u8], new: &[u8], schema: &BorshSchemaContainer) -> std::io::Result<Vec<ValueChange>> {
+    let old = deserialize_from_schema(&mut &old[..], schema)?;
+    let new = deserialize_from_schema(&mut &new[..], schema)?;
+    Ok(diff_values(&old, &new, schema))
+}
+
+/// Returns what changed between two values of the schema, as produced by deserialize_from_schema. Struct fields are
+/// compared one by one and sequences are aligned so that inserted and removed elements are reported as such. Values
+/// that don't match the schema are reported as changed as a whole.
+pub fn diff_values(old: &Value, new: &Value, schema: &BorshSchemaContainer) -> Vec<ValueChange> {
+    let mut changes = Vec::new();
+    diff_declaration(old, new, schema, schema.declaration(), "", &mut changes);
+    changes
+}
+
+/// Formats changes one per line
+pub fn format_diff(changes: &[ValueChange]) -> String {
+    changes.iter().map(|change| format!("{change}\n")).collect()
+}
+
+/// Converts changes to a JSON Patch as described in RFC 6902, which turns the old value into the new one
+pub fn to_json_patch(changes: &[ValueChange]) -> Value {
+    changes
+        .iter()
+        .map(|change| match change {
+            ValueChange::Changed { path, new, .. } | ValueChange::VariantChanged { path, new, .. } => {
+                json!({"op": "replace", "path": path, "value": new})
+            }
+            ValueChange::Added { path, value } => json!({"op": "add", "path": path, "value": value}),
+            ValueChange::Removed { path, .. } => json!({"op": "remove", "path": path}),
+        })
+        .collect()
+}
+
+fn changed(old: &Value, new: &Value, path: &str, changes: &mut Vec<ValueChange>) {
+    changes.push(ValueChange::Changed {
+        path: path.to_string(),
+        old: old.clone(),
+        new: new.clone(),
+    });
+}
+
+// Decoded values are nested no deeper than the decoder's maximum depth, so this recursion is bounded
+fn diff_declaration(
+    old: &Value,
+    new: &Value,
+    schema: &BorshSchemaContainer,
+    declaration: &Declaration,
+    path: &str,
+    changes: &mut Vec<ValueChange>,
+) {
+    if old == new {
+        return;
+    }
+    match (definition(schema, declaration), old, new) {
+        (Some(Definition::Sequence { length_width, elements, .. }), Value::Array(old_items), Value::Array(new_items)) => {
+            // Sequences without a length prefix always have the same length, so elements are compared in place
+            if *length_width == 0 {
+                diff_in_place(old_items, new_items, schema, elements, path, changes);
+            } else {
+                diff_sequence(old_items, new_items, schema, elements, path, changes);
+            }
+        }
+        (
+            Some(Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) }),
+            Value::Array(old_items),
+            Value::Array(new_items),
+        ) if old_items.len() == elements.len() && new_items.len() == elements.len() => {
+            for (index, element) in elements.iter().enumerate() {
+                diff_declaration(&old_items[index], &new_items[index], schema, element, &format!("{path}/{index}"), changes);
+            }
+        }
+        (Some(Definition::Struct { fields: Fields::NamedFields(fields) }), Value::Object(old_fields), Value::Object(new_fields))
+            if fields.iter().all(|(name, _)| old_fields.contains_key(name) && new_fields.contains_key(name)) =>
+        {
+            for (name, field) in fields {
+                let field_path = format!("{path}/{}", escape_pointer_segment(name));
+                diff_declaration(&old_fields[name], &new_fields[name], schema, field, &field_path, changes);
+            }
+        }
+        (Some(Definition::Enum { variants, .. }), Value::Object(old_variant), Value::Object(new_variant))
+            if old_variant.len() == 1 && new_variant.len() == 1 =>
+        {
+            let (old_name, old_contents) = old_variant.iter().next().expect("Checked to have one entry");
+            let (new_name, new_contents) = new_variant.iter().next().expect("Checked to have one entry");
+            if old_name != new_name {
+                changes.push(ValueChange::VariantChanged {
+                    path: path.to_string(),
+                    old_variant: old_name.clone(),
+                    new_variant: new_name.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+                return;
+            }
+            match variants.iter().find(|(_, variant_name, _)| variant_name == old_name) {
+                Some((_, _, variant)) => {
+                    let variant_path = format!("{path}/{}", escape_pointer_segment(old_name));
+                    diff_declaration(old_contents, new_contents, schema, variant, &variant_path, changes);
+                }
+                None => changed(old, new, path, changes),
+            }
+        }
+        _ => changed(old, new, path, changes),
+    }
+}
+
+fn diff_in_place(
+    old_items: &[Value],
+    new_items: &[Value],
+    schema: &BorshSchemaContainer,
+    elements: &Declaration,
+    path: &str,
+    changes: &mut Vec<ValueChange>,
+) {
+    if old_items.len() != new_items.len() {
+        return diff_sequence(old_items, new_items, schema, elements, path, changes);
+    }
+    for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+        diff_declaration(old_item, new_item, schema, elements, &format!("{path}/{index}"), changes);
+    }
+}
+
+/// Aligns two sequences on their longest common subsequence. Between aligned elements, removed and inserted elements
+/// are paired up and compared, and whatever is left over is reported as removed or added. Indices are those of the
+/// sequence as it is after the changes before them are applied.
+fn diff_sequence(
+    old_items: &[Value],
+    new_items: &[Value],
+    schema: &BorshSchemaContainer,
+    elements: &Declaration,
+    path: &str,
+    changes: &mut Vec<ValueChange>,
+) {
+    let prefix = old_items.iter().zip(new_items).take_while(|(old_item, new_item)| old_item == new_item).count();
+    let suffix = old_items[prefix..]
+        .iter()
+        .rev()
+        .zip(new_items[prefix..].iter().rev())
+        .take_while(|(old_item, new_item)| old_item == new_item)
+        .count();
+    let old_rest = &old_items[prefix..old_items.len() - suffix];
+    let new_rest = &new_items[prefix..new_items.len() - suffix];
+
+    // Pairs of aligned indices into old_rest and new_rest, ending with both lengths so the last gap is handled
+    let mut aligned = common_subsequence(old_rest, new_rest);
+    aligned.push((old_rest.len(), new_rest.len()));
+
+    let (mut old_index, mut new_index) = (0, 0);
+    for (old_aligned, new_aligned) in aligned {
+        let removed = &old_rest[old_index..old_aligned];
+        let added = &new_rest[new_index..new_aligned];
+        let paired = removed.len().min(added.len());
+        for (offset, (old_item, new_item)) in removed.iter().zip(added).enumerate() {
+            let item_path = format!("{path}/{}", prefix + new_index + offset);
+            diff_declaration(old_item, new_item, schema, elements, &item_path, changes);
+        }
+        let position = prefix + new_index + paired;
+        for old_item in &removed[paired..] {
+            changes.push(ValueChange::Removed { path: format!("{path}/{position}"), value: old_item.clone() });
+        }
+        for (offset, new_item) in added[paired..].iter().enumerate() {
+            changes.push(ValueChange::Added { path: format!("{path}/{}", position + offset), value: new_item.clone() });
+        }
+        old_index = old_aligned + 1;
+        new_index = new_aligned + 1;
+    }
+}
+
+/// Returns the pairs of indices of a longest common subsequence, or nothing if the sequences are too long to align
+fn common_subsequence(old_items: &[Value], new_items: &[Value]) -> Vec<(usize, usize)> {
+    let columns = new_items.len() + 1;
+    if old_items.is_empty() || new_items.is_empty() || (old_items.len() + 1).saturating_mul(columns) > MAX_ALIGNMENT_CELLS {
+        return Vec::new();
+    }
+
+    // lengths[i * columns + j] is the length of the longest common subsequence of old_items[i..] and new_items[j..]
+    let mut lengths = vec![0u32; (old_items.len() + 1) * columns];
+    for i in (0..old_items.len()).rev() {
+        for j in (0..new_items.len()).rev() {
+            lengths[i * columns + j] = match old_items[i] == new_items[j] {
+                true => lengths[(i + 1) * columns + j + 1] + 1,
+                false => lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1]),
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_items.len() && j < new_items.len() {
+        if old_items[i] == new_items[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * columns + j] >= lengths[i * columns + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
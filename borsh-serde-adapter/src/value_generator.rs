@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, HashMap};
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde_json::{json, Map, Value};
+
+use crate::borsh_schema_util::PrimitiveType;
+use crate::schema_layout::{SizeBounds, SizeCalculator};
+use crate::serialize_adapter::serialize_serde_json_to_borsh;
+
+/// Bounds on the values made by a ValueGenerator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorLimits {
+    /// The most elements of a sequence, unless the schema requires more
+    pub max_sequence_length: usize,
+    /// The most characters of a string
+    pub max_string_length: usize,
+    /// How deep values are nested before sequences are kept as short and enums as small as the schema allows, which
+    /// is what stops values of recursive types from growing without end
+    pub max_depth: usize,
+}
+
+impl Default for GeneratorLimits {
+    fn default() -> Self {
+        GeneratorLimits {
+            max_sequence_length: 8,
+            max_string_length: 16,
+            max_depth: 8,
+        }
+    }
+}
+
+/// Makes random values of a schema, in the form produced by deserialize_from_schema. The same seed always gives the
+/// same values.
+///
+/// ```ignore
+/// let mut generator = ValueGenerator::new(&container, 42, GeneratorLimits::default());
+/// let (value, bytes) = generator.generate()?;
+/// ```
+pub struct ValueGenerator<'a> {
+    schema: &'a BorshSchemaContainer,
+    limits: GeneratorLimits,
+    rng: ChaCha8Rng,
+    /// The index of the smallest variant of every enum, which is the one that leads out of a recursive type
+    smallest_variants: HashMap<&'a str, usize>,
+}
+
+impl<'a> ValueGenerator<'a> {
+    pub fn new(schema: &'a BorshSchemaContainer, seed: u64, limits: GeneratorLimits) -> ValueGenerator<'a> {
+        let bounds = SizeBounds::default();
+        let sizes = SizeCalculator::new(schema, &bounds);
+        let smallest_variants = schema
+            .definitions()
+            .filter_map(|(declaration, definition)| match definition {
+                Definition::Enum { variants, .. } => {
+                    let smallest = (0..variants.len()).min_by_key(|index| sizes.min_size(&variants[*index].2).unwrap_or(usize::MAX))?;
+                    Some((declaration.as_str(), smallest))
+                }
+                _ => None,
+            })
+            .collect();
+        ValueGenerator {
+            schema,
+            limits,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            smallest_variants,
+        }
+    }
+
+    /// Makes a random value of the schema's declaration
+    pub fn generate_value(&mut self) -> Value {
+        self.value_of(self.schema.declaration(), 0)
+    }
+
+    /// Makes a random value of the schema's declaration and serializes it. Fails if the schema has types the
+    /// serializer doesn't support.
+    pub fn generate(&mut self) -> anyhow::Result<(Value, Vec<u8>)> {
+        let value = self.generate_value();
+        let mut bytes = Vec::new();
+        serialize_serde_json_to_borsh(&mut bytes, &value, self.schema)?;
+        Ok((value, bytes))
+    }
+
+    // Recursion ends at max_depth for recursive types, and at the depth of the schema for the rest
+    fn value_of(&mut self, declaration: &Declaration, depth: usize) -> Value {
+        if let Some(primitive) = PrimitiveType::from_declaration(declaration) {
+            return self.primitive(primitive);
+        }
+        let Some(definition) = self.schema.get_definition(declaration) else {
+            return Value::Null;
+        };
+
+        let shallow = depth < self.limits.max_depth;
+        match definition {
+            Definition::Primitive(_) => Value::Null,
+            Definition::Sequence { length_width, length_range, elements } => {
+                let min_length = *length_range.start() as usize;
+                let max_length = match length_width {
+                    0 => *length_range.end() as usize,
+                    _ => (*length_range.end() as usize).min(self.limits.max_sequence_length).max(min_length),
+                };
+                let length = match shallow {
+                    true => self.rng.random_range(min_length..=max_length),
+                    false => min_length,
+                };
+                (0..length).map(|_| self.value_of(elements, depth + 1)).collect()
+            }
+            Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) } => {
+                elements.iter().map(|element| self.value_of(element, depth + 1)).collect()
+            }
+            Definition::Struct { fields: Fields::NamedFields(fields) } => fields
+                .iter()
+                .map(|(name, field)| (name.clone(), self.value_of(field, depth + 1)))
+                .collect::<Map<String, Value>>()
+                .into(),
+            Definition::Struct { fields: Fields::Empty } => json!([]),
+            Definition::Enum { variants, .. } => {
+                if variants.is_empty() {
+                    return Value::Null;
+                }
+                let index = match shallow {
+                    true => self.rng.random_range(0..variants.len()),
+                    false => self.smallest_variants.get(declaration.as_str()).copied().unwrap_or(0),
+                };
+                let (_, variant_name, variant) = &variants[index];
+                json!({ variant_name: self.value_of(variant, depth + 1) })
+            }
+        }
+    }
+
+    fn primitive(&mut self, primitive: PrimitiveType) -> Value {
+        match primitive {
+            PrimitiveType::U8 => self.rng.random::<u8>().into(),
+            PrimitiveType::U16 => self.rng.random::<u16>().into(),
+            PrimitiveType::U32 => self.rng.random::<u32>().into(),
+            PrimitiveType::U64 => self.rng.random::<u64>().into(),
+            PrimitiveType::U128 => self.rng.random::<u128>().to_string().into(),
+            PrimitiveType::I8 => self.rng.random::<i8>().into(),
+            PrimitiveType::I16 => self.rng.random::<i16>().into(),
+            PrimitiveType::I32 => self.rng.random::<i32>().into(),
+            PrimitiveType::I64 => self.rng.random::<i64>().into(),
+            PrimitiveType::I128 => self.rng.random::<i128>().to_string().into(),
+            // NaN and infinity can't be represented in JSON, so floats are drawn from bits until they are finite
+            PrimitiveType::F32 => loop {
+                let float = f32::from_bits(self.rng.random());
+                if float.is_finite() {
+                    break f64::from(float).into();
+                }
+            },
+            PrimitiveType::F64 => loop {
+                let float = f64::from_bits(self.rng.random());
+                if float.is_finite() {
+                    break float.into();
+                }
+            },
+            PrimitiveType::String => {
+                let length = self.rng.random_range(0..=self.limits.max_string_length);
+                (0..length).map(|_| self.rng.random::<char>()).collect::<String>().into()
+            }
+            PrimitiveType::Bool => self.rng.random::<bool>().into(),
+            PrimitiveType::Unit => Value::Null,
+        }
+    }
+}
+
+/// Makes a random value of the schema's declaration from a seed, and serializes it
+pub fn generate_from_schema(schema: &BorshSchemaContainer, seed: u64, limits: &GeneratorLimits) -> anyhow::Result<(Value, Vec<u8>)> {
+    ValueGenerator::new(schema, seed, limits.clone()).generate()
+}
+
+/// Makes a random schema from a seed, for testing code that has to work with any schema. The schema's declaration is a
+/// struct, and types are nested up to max_depth deep. Schemas include every supported primitive, Vecs, arrays, tuples,
+/// Options, enums with unit and struct variants, and recursive types, all defined as the BorshSchema derive would.
+pub fn random_schema(seed: u64, max_depth: usize) -> BorshSchemaContainer {
+    let mut builder = SchemaBuilder {
+        rng: ChaCha8Rng::seed_from_u64(seed),
+        definitions: BTreeMap::new(),
+        max_depth,
+    };
+    let declaration = builder.named_struct("Root".to_string(), 0);
+    BorshSchemaContainer::new(declaration, builder.definitions)
+}
+
+struct SchemaBuilder {
+    rng: ChaCha8Rng,
+    definitions: BTreeMap<Declaration, Definition>,
+    max_depth: usize,
+}
+
+impl SchemaBuilder {
+    /// Returns a random declaration, adding definitions for it as needed. Zero sized types are never returned, since
+    /// borsh doesn't allow sequences of them.
+    fn declaration(&mut self, depth: usize) -> Declaration {
+        if depth >= self.max_depth || self.rng.random_ratio(1, 3) {
+            let primitives: Vec<PrimitiveType> = PrimitiveType::ALL.into_iter().filter(|primitive| *primitive != PrimitiveType::Unit).collect();
+            return primitives[self.rng.random_range(0..primitives.len())].declaration().to_string();
+        }
+        let id = self.definitions.len();
+        match self.rng.random_range(0..8) {
+            0 => self.named_struct(format!("Struct{id}"), depth),
+            1 => {
+                let elements: Vec<Declaration> = (0..self.rng.random_range(1..=3)).map(|_| self.declaration(depth + 1)).collect();
+                self.define(format!("({})", elements.join(", ")), Definition::Tuple { elements })
+            }
+            2 => {
+                let elements = self.declaration(depth + 1);
+                self.define(format!("Vec<{elements}>"), Definition::Sequence {
+                    length_width: Definition::DEFAULT_LENGTH_WIDTH,
+                    length_range: Definition::DEFAULT_LENGTH_RANGE,
+                    elements,
+                })
+            }
+            3 => {
+                let elements = self.declaration(depth + 1);
+                let length = self.rng.random_range(1..=4);
+                self.define(format!("[{elements}; {length}]"), Definition::Sequence {
+                    length_width: 0,
+                    length_range: length..=length,
+                    elements,
+                })
+            }
+            4 => {
+                let some = self.declaration(depth + 1);
+                let none = self.unit();
+                self.define(format!("Option<{some}>"), Definition::Enum {
+                    tag_width: 1,
+                    variants: vec![(0, "None".to_string(), none), (1, "Some".to_string(), some)],
+                })
+            }
+            5 => {
+                let name = format!("Enum{id}");
+                let variants = (0..self.rng.random_range(1..=4))
+                    .map(|index| {
+                        let variant = match self.rng.random_bool(0.5) {
+                            true => self.unit(),
+                            false => self.named_struct(format!("{name}__Variant{index}"), depth + 1),
+                        };
+                        (index as i64, format!("Variant{index}"), variant)
+                    })
+                    .collect();
+                self.define(name, Definition::Enum { tag_width: 1, variants })
+            }
+            6 => {
+                // struct Tree { value: T, children: Vec<Tree> }
+                let name = format!("Tree{id}");
+                let children = format!("Vec<{name}>");
+                self.define(children.clone(), Definition::Sequence {
+                    length_width: Definition::DEFAULT_LENGTH_WIDTH,
+                    length_range: Definition::DEFAULT_LENGTH_RANGE,
+                    elements: name.clone(),
+                });
+                let value = self.declaration(depth + 1);
+                self.define(name, Definition::Struct {
+                    fields: Fields::NamedFields(vec![("value".to_string(), value), ("children".to_string(), children)]),
+                })
+            }
+            _ => {
+                // enum Expr { Leaf { value: T }, Node { left: Box<Expr>, right: Box<Expr> } }
+                let name = format!("Expr{id}");
+                self.define(name.clone(), Definition::Enum { tag_width: 1, variants: Vec::new() });
+                let value = self.declaration(depth + 1);
+                let leaf = self.define(format!("{name}__Leaf"), Definition::Struct {
+                    fields: Fields::NamedFields(vec![("value".to_string(), value)]),
+                });
+                let node = self.define(format!("{name}__Node"), Definition::Struct {
+                    fields: Fields::NamedFields(vec![("left".to_string(), name.clone()), ("right".to_string(), name.clone())]),
+                });
+                self.define(name, Definition::Enum {
+                    tag_width: 1,
+                    variants: vec![(0, "Leaf".to_string(), leaf), (1, "Node".to_string(), node)],
+                })
+            }
+        }
+    }
+
+    fn named_struct(&mut self, name: String, depth: usize) -> Declaration {
+        // The name is taken before the fields are made, so that nested definitions get other names
+        self.define(name.clone(), Definition::Struct { fields: Fields::Empty });
+        let fields = (0..self.rng.random_range(1..=4))
+            .map(|index| (format!("field{index}"), self.declaration(depth + 1)))
+            .collect();
+        self.define(name, Definition::Struct { fields: Fields::NamedFields(fields) })
+    }
+
+    fn unit(&mut self) -> Declaration {
+        self.define("()".to_string(), Definition::Primitive(0))
+    }
+
+    fn define(&mut self, declaration: Declaration, definition: Definition) -> Declaration {
+        self.definitions.insert(declaration.clone(), definition);
+        declaration
+    }
+}
+
+/// A proptest strategy for values of a schema and their borsh serialization. Values are made by a ValueGenerator from a
+/// seed chosen by proptest, so failing cases are reported but not shrunk.
+#[cfg(feature = "proptest")]
+pub fn value_strategy(
+    schema: std::sync::Arc<BorshSchemaContainer>,
+    limits: GeneratorLimits,
+) -> impl proptest::strategy::Strategy<Value = (Value, Vec<u8>)> {
+    use proptest::prelude::*;
+
+    any::<u64>().prop_filter_map("schema has types the serializer doesn't support", move |seed| {
+        generate_from_schema(&schema, seed, &limits).ok()
+    })
+}
@@ -0,0 +1,247 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::anchor_idl::{account_discriminator, instruction_discriminator, schema_to_idl_types, AnchorIdl};
+use borsh_serde_adapter::errors::IdlError;
+use serde_json::{json, Value};
+
+const VAULT_DISCRIMINATOR: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum Status {
+    Active { since: i64 },
+    Frozen,
+    Closed(u32),
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Vault {
+    authority: [u8; 32],
+    balance: u64,
+    name: String,
+    memo: Vec<u8>,
+    lots: Vec<u32>,
+    delegate: Option<[u8; 32]>,
+    status: Status,
+    history: [u16; 3],
+}
+
+#[derive(BorshSerialize)]
+pub struct DepositFunds {
+    amount: u64,
+    memo: Option<String>,
+}
+
+fn vault() -> Vault {
+    Vault {
+        authority: [7; 32],
+        balance: 1_000,
+        name: "main".to_string(),
+        memo: vec![1, 2],
+        lots: vec![5, 6],
+        delegate: Some([9; 32]),
+        status: Status::Active { since: -4 },
+        history: [1, 2, 3],
+    }
+}
+
+fn vault_json() -> Value {
+    json!({
+        "authority": ([7u8; 32]),
+        "balance": 1_000,
+        "name": "main",
+        "memo": [1, 2],
+        "lots": [5, 6],
+        "delegate": {"Some": ([9u8; 32])},
+        "status": {"Active": {"since": -4}},
+        "history": [1, 2, 3]
+    })
+}
+
+fn account_data(vault: &Vault) -> Vec<u8> {
+    let mut data = VAULT_DISCRIMINATOR.to_vec();
+    data.extend(to_vec(vault).unwrap());
+    data
+}
+
+/// An IDL in the format of Anchor 0.30 and later
+fn idl() -> Value {
+    json!({
+        "address": "11111111111111111111111111111111",
+        "metadata": {"name": "vault", "version": "0.1.0", "spec": "0.1.0"},
+        "instructions": [{
+            "name": "deposit_funds",
+            "discriminator": [202, 39, 52, 211, 53, 20, 250, 88],
+            "accounts": [{"name": "vault", "writable": true}],
+            "args": [{"name": "amount", "type": "u64"}, {"name": "memo", "type": {"option": "string"}}]
+        }],
+        "accounts": [{"name": "Vault", "discriminator": VAULT_DISCRIMINATOR}],
+        "types": [
+            {"name": "Status", "type": {"kind": "enum", "variants": [
+                {"name": "Active", "fields": [{"name": "since", "type": "i64"}]},
+                {"name": "Frozen"},
+                {"name": "Closed", "fields": ["u32"]}
+            ]}},
+            {"name": "Vault", "type": {"kind": "struct", "fields": [
+                {"name": "authority", "type": "pubkey"},
+                {"name": "balance", "type": "u64"},
+                {"name": "name", "type": "string"},
+                {"name": "memo", "type": "bytes"},
+                {"name": "lots", "type": {"vec": {"defined": {"name": "Lot"}}}},
+                {"name": "delegate", "type": {"option": "pubkey"}},
+                {"name": "status", "type": {"defined": {"name": "Status"}}},
+                {"name": "history", "type": {"array": ["u16", 3]}}
+            ]}},
+            {"name": "Lot", "type": {"kind": "type", "alias": "u32"}}
+        ]
+    })
+}
+
+/// The same program in the format of Anchor before 0.30, without discriminators and with account types in the accounts
+fn legacy_idl() -> Value {
+    json!({
+        "version": "0.1.0",
+        "name": "vault",
+        "instructions": [{
+            "name": "depositFunds",
+            "accounts": [{"name": "vault", "isMut": true, "isSigner": false}],
+            "args": [{"name": "amount", "type": "u64"}, {"name": "memo", "type": {"option": "string"}}]
+        }],
+        "accounts": [{"name": "Vault", "type": {"kind": "struct", "fields": [
+            {"name": "authority", "type": "publicKey"},
+            {"name": "balance", "type": "u64"},
+            {"name": "name", "type": "string"},
+            {"name": "memo", "type": "bytes"},
+            {"name": "lots", "type": {"vec": "u32"}},
+            {"name": "delegate", "type": {"option": "publicKey"}},
+            {"name": "status", "type": {"defined": "Status"}},
+            {"name": "history", "type": {"array": ["u16", 3]}}
+        ]}}],
+        "types": [{"name": "Status", "type": {"kind": "enum", "variants": [
+            {"name": "Active", "fields": [{"name": "since", "type": "i64"}]},
+            {"name": "Frozen"},
+            {"name": "Closed", "fields": ["u32"]}
+        ]}}]
+    })
+}
+
+#[test]
+fn discriminators_test() {
+    assert_eq!(account_discriminator("Vault"), VAULT_DISCRIMINATOR);
+    assert_eq!(instruction_discriminator("initialize"), [175, 175, 109, 31, 13, 152, 155, 237]);
+    assert_eq!(instruction_discriminator("depositFunds"), instruction_discriminator("deposit_funds"));
+    assert_eq!(instruction_discriminator("mintNFT"), instruction_discriminator("mint_nft"));
+    assert_eq!(instruction_discriminator("NFTMint"), instruction_discriminator("nft_mint"));
+
+    let legacy = AnchorIdl::from_json(&legacy_idl()).unwrap();
+    assert_eq!(legacy.account_discriminator("Vault"), Some(VAULT_DISCRIMINATOR));
+    assert_eq!(legacy.instruction_discriminator("depositFunds"), Some([202, 39, 52, 211, 53, 20, 250, 88]));
+}
+
+#[test]
+fn decode_account_test() {
+    for idl in [idl(), legacy_idl()] {
+        let idl = AnchorIdl::from_json(&idl).unwrap();
+        assert_eq!(idl.account_names().collect::<Vec<_>>(), ["Vault"]);
+
+        let mut data = account_data(&vault());
+        // Accounts are often allocated with more space than their value needs
+        data.extend([0; 16]);
+        assert_eq!(idl.decode_account(&data).unwrap(), ("Vault".to_string(), vault_json()));
+
+        let frozen = Vault { delegate: None, status: Status::Frozen, ..vault() };
+        let (_, value) = idl.decode_account(&account_data(&frozen)).unwrap();
+        assert_eq!(value["delegate"], json!({"None": null}));
+        assert_eq!(value["status"], json!({"Frozen": []}));
+    }
+}
+
+#[test]
+fn encode_account_test() {
+    let idl = AnchorIdl::from_json(&idl()).unwrap();
+    assert_eq!(idl.encode_account("Vault", &vault_json()).unwrap(), account_data(&vault()));
+
+    let closed = json!({"Closed": [8]});
+    let mut value = vault_json();
+    value["status"] = closed.clone();
+    let data = idl.encode_account("Vault", &value).unwrap();
+    assert_eq!(data, account_data(&Vault { status: Status::Closed(8), ..vault() }));
+    assert_eq!(idl.decode_account(&data).unwrap().1["status"], closed);
+
+    let error = idl.encode_account("Vault", &json!({"balance": 1})).unwrap_err();
+    assert!(matches!(error, IdlError::Encode(_)), "{error}");
+}
+
+#[test]
+fn instructions_test() {
+    let args = DepositFunds { amount: 50, memo: Some("rent".to_string()) };
+    for (idl, name) in [(idl(), "deposit_funds"), (legacy_idl(), "depositFunds")] {
+        let idl = AnchorIdl::from_json(&idl).unwrap();
+        let mut data = idl.instruction_discriminator(name).unwrap().to_vec();
+        data.extend(to_vec(&args).unwrap());
+
+        let (instruction, value) = idl.decode_instruction(&data).unwrap();
+        assert_eq!(instruction, name);
+        assert_eq!(value, json!({"amount": 50, "memo": {"Some": "rent"}}));
+        assert_eq!(idl.encode_instruction(name, &value).unwrap(), data);
+        assert_eq!(idl.instruction_schema(name).unwrap().declaration(), name);
+    }
+}
+
+#[test]
+fn errors_test() {
+    let idl = AnchorIdl::from_json(&idl()).unwrap();
+    assert_eq!(idl.decode_account(&[1, 2, 3]).unwrap_err().to_string(), "Data of 3 bytes is too short for a discriminator");
+    assert_eq!(idl.decode_account(&[0; 8]).unwrap_err().to_string(), "Unknown discriminator 0000000000000000");
+    assert!(matches!(idl.decode_account(&VAULT_DISCRIMINATOR).unwrap_err(), IdlError::Io(_)));
+    assert_eq!(idl.account_schema("Status").unwrap_err().to_string(), "Account Status not found");
+    assert!(matches!(idl.instruction_schema("withdraw").unwrap_err(), IdlError::InstructionNotFound(_)));
+
+    let unsupported = AnchorIdl::from_json(&json!({
+        "accounts": [{"name": "Pool", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8]}, {"name": "Wrapper", "discriminator": [8, 7, 6, 5, 4, 3, 2, 1]}],
+        "types": [
+            {"name": "Pool", "serialization": "bytemuck", "repr": {"kind": "c"}, "type": {"kind": "struct", "fields": [{"name": "total", "type": "u64"}]}},
+            {"name": "Wrapper", "generics": [{"kind": "type", "name": "T"}], "type": {"kind": "struct", "fields": [{"name": "inner", "type": {"generic": "T"}}]}},
+            {"name": "Missing", "type": {"kind": "struct", "fields": [{"name": "other", "type": {"defined": {"name": "Other"}}}]}}
+        ]
+    }))
+    .unwrap();
+    assert_eq!(unsupported.account_schema("Pool").unwrap_err().to_string(), "Pool is not supported: bytemuck serialization is not borsh");
+    assert_eq!(unsupported.account_schema("Wrapper").unwrap_err().to_string(), "Wrapper is not supported: generic types are not supported");
+    assert_eq!(unsupported.type_schema("Missing").unwrap_err().to_string(), "Type Other is not defined in the IDL");
+
+    let error = AnchorIdl::from_json(&json!({"accounts": [{"name": "Vault", "discriminator": [1, 2]}]})).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid IDL: Discriminator of Vault is not 8 bytes");
+}
+
+#[test]
+fn recursive_type_test() {
+    let idl = AnchorIdl::from_json(&json!({
+        "types": [{"name": "Node", "type": {"kind": "struct", "fields": [
+            {"name": "value", "type": "u8"},
+            {"name": "children", "type": {"vec": {"defined": {"name": "Node"}}}}
+        ]}}]
+    }))
+    .unwrap();
+    let schema = idl.type_schema("Node").unwrap();
+    let value = borsh_serde_adapter::deserialize_adapter::deserialize_from_schema(&mut [1, 1, 0, 0, 0, 2, 0, 0, 0, 0].as_slice(), &schema).unwrap();
+    assert_eq!(value, json!({"value": 1, "children": [{"value": 2, "children": []}]}));
+}
+
+#[test]
+fn schema_to_idl_types_test() {
+    let types = schema_to_idl_types(&schema_container_of::<Vault>()).unwrap();
+    assert_eq!(types.len(), 2);
+    assert_eq!(types[0], json!({"name": "Status", "type": {"kind": "enum", "variants": [
+        {"name": "Active", "fields": [{"name": "since", "type": "i64"}]},
+        {"name": "Frozen"},
+        {"name": "Closed", "fields": ["u32"]}
+    ]}}));
+    assert_eq!(types[1]["type"]["fields"][3], json!({"name": "memo", "type": "bytes"}));
+    assert_eq!(types[1]["type"]["fields"][5], json!({"name": "delegate", "type": {"option": {"array": ["u8", 32]}}}));
+
+    // The exported types decode what the derived implementation serializes
+    let idl = AnchorIdl::from_json(&json!({"accounts": [{"name": "Vault"}], "types": types})).unwrap();
+    assert_eq!(idl.decode_account(&account_data(&vault())).unwrap().1, vault_json());
+
+    let error = schema_to_idl_types(&schema_container_of::<(u8, bool)>()).unwrap_err();
+    assert!(matches!(error, IdlError::Unsupported(..)), "{error}");
+}
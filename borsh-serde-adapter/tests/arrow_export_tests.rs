@@ -0,0 +1,241 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int64Type, UInt16Type, UInt32Type, UInt64Type};
+use arrow_array::Array;
+use arrow_schema::{DataType, UnionMode};
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::arrow_export::{arrow_schema, decode_record_batch, ArrowOptions, EnumEncoding, RecordBatchDecoder};
+use borsh_serde_adapter::errors::ArrowExportError;
+#[cfg(feature = "generator")]
+use borsh_serde_adapter::value_generator::{random_schema, GeneratorLimits, ValueGenerator};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Position {
+    market: [u8; 4],
+    size: i64,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum Status {
+    Active { since: i64 },
+    Frozen,
+    Closed(u32),
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balance: u64,
+    supply: u128,
+    memo: Vec<u8>,
+    lots: Vec<u32>,
+    position: Option<Position>,
+    status: Status,
+    pair: (u16, bool),
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+fn accounts() -> Vec<Account> {
+    vec![
+        Account {
+            owner: "alice".to_string(),
+            balance: 10,
+            supply: u128::MAX,
+            memo: vec![1, 2],
+            lots: vec![5, 6, 7],
+            position: Some(Position { market: *b"SOL1", size: -3 }),
+            status: Status::Active { since: 100 },
+            pair: (1, true),
+        },
+        Account {
+            owner: "bob".to_string(),
+            balance: 20,
+            supply: 0,
+            memo: Vec::new(),
+            lots: Vec::new(),
+            position: None,
+            status: Status::Frozen,
+            pair: (2, false),
+        },
+        Account {
+            owner: "carol".to_string(),
+            balance: 30,
+            supply: 7,
+            memo: vec![3],
+            lots: vec![8],
+            position: Some(Position { market: *b"BTC2", size: 9 }),
+            status: Status::Closed(4),
+            pair: (3, true),
+        },
+    ]
+}
+
+#[test]
+fn arrow_schema_test() {
+    let schema = arrow_schema(&schema_container_of::<Account>(), &ArrowOptions::default()).unwrap();
+    let types: Vec<(&str, &DataType, bool)> = schema.fields().iter().map(|field| (field.name().as_str(), field.data_type(), field.is_nullable())).collect();
+
+    assert_eq!(types[0], ("owner", &DataType::Utf8, false));
+    assert_eq!(types[1], ("balance", &DataType::UInt64, false));
+    assert_eq!(types[2], ("supply", &DataType::Utf8, false));
+    assert_eq!(types[3], ("memo", &DataType::Binary, false));
+    assert!(matches!(types[4], ("lots", DataType::List(item), false) if item.data_type() == &DataType::UInt32));
+    assert!(matches!(types[5], ("position", DataType::Struct(fields), true)
+        if fields[0].data_type() == &DataType::FixedSizeBinary(4) && fields[1].data_type() == &DataType::Int64));
+    let DataType::Union(variants, UnionMode::Dense) = types[6].1 else {
+        panic!("Expected a dense union, found {}", types[6].1);
+    };
+    let variants: Vec<(i8, &str, &DataType)> = variants.iter().map(|(id, field)| (id, field.name().as_str(), field.data_type())).collect();
+    assert_eq!(variants[1], (1, "Frozen", &DataType::Null));
+    assert!(matches!(variants[2], (2, "Closed", DataType::Struct(fields)) if fields[0].name() == "0"));
+    assert!(matches!(types[7], ("pair", DataType::Struct(fields), false) if fields.len() == 2));
+}
+
+#[test]
+fn decode_record_batch_test() {
+    let records: Vec<Vec<u8>> = accounts().iter().map(|account| to_vec(account).unwrap()).collect();
+    let batch = decode_record_batch(&schema_container_of::<Account>(), &records, &ArrowOptions::default()).unwrap();
+    assert_eq!(batch.num_rows(), 3);
+
+    let owners: Vec<&str> = batch.column(0).as_string::<i32>().iter().flatten().collect();
+    assert_eq!(owners, ["alice", "bob", "carol"]);
+    assert_eq!(batch.column(1).as_primitive::<UInt64Type>().values(), &[10, 20, 30]);
+    assert_eq!(batch.column(2).as_string::<i32>().value(0), u128::MAX.to_string());
+    assert_eq!(batch.column(3).as_binary::<i32>().value(0), &[1, 2]);
+
+    let lots = batch.column(4).as_list::<i32>();
+    assert_eq!(lots.value_offsets(), &[0, 3, 3, 4]);
+    assert_eq!(lots.values().as_primitive::<UInt32Type>().values(), &[5, 6, 7, 8]);
+
+    let position = batch.column(5).as_struct();
+    assert_eq!(position.null_count(), 1);
+    assert!(position.is_null(1));
+    assert_eq!(position.column(0).as_fixed_size_binary().value(2), b"BTC2");
+    assert_eq!(position.column(1).as_primitive::<Int64Type>().value(0), -3);
+
+    let status = batch.column(6).as_union();
+    assert_eq!(status.type_ids(), &[0, 1, 2]);
+    assert_eq!(status.value(0).as_struct().column(0).as_primitive::<Int64Type>().value(0), 100);
+    assert_eq!(status.value(2).as_struct().column(0).as_primitive::<UInt32Type>().value(0), 4);
+
+    let pair = batch.column(7).as_struct();
+    assert_eq!(pair.column(0).as_primitive::<UInt16Type>().values(), &[1, 2, 3]);
+    assert_eq!(pair.column(1).as_boolean().iter().flatten().collect::<Vec<bool>>(), [true, false, true]);
+}
+
+#[test]
+fn enum_struct_encoding_test() {
+    let options = ArrowOptions { enum_encoding: EnumEncoding::Struct };
+    let records: Vec<Vec<u8>> = accounts().iter().map(|account| to_vec(account).unwrap()).collect();
+    let batch = decode_record_batch(&schema_container_of::<Account>(), &records, &options).unwrap();
+
+    let status = batch.column(6).as_struct();
+    let field_names: Vec<&str> = status.fields().iter().map(|field| field.name().as_str()).collect();
+    assert_eq!(field_names, ["variant", "Active", "Closed"]);
+    let variants: Vec<&str> = status.column(0).as_string::<i32>().iter().flatten().collect();
+    assert_eq!(variants, ["Active", "Frozen", "Closed"]);
+    let active = status.column(1).as_struct();
+    assert_eq!(active.column(0).as_primitive::<Int64Type>().value(0), 100);
+    assert!(active.is_null(1) && active.is_null(2));
+    assert!(status.column(2).is_valid(2));
+}
+
+#[test]
+fn invalid_record_leaves_batch_unchanged_test() {
+    let accounts = accounts();
+    let mut decoder = RecordBatchDecoder::new(&schema_container_of::<Account>(), &ArrowOptions::default()).unwrap();
+    decoder.append(&to_vec(&accounts[0]).unwrap()).unwrap();
+
+    let record = to_vec(&accounts[2]).unwrap();
+    let error = decoder.append(&record[..record.len() - 1]).unwrap_err();
+    assert!(matches!(error, ArrowExportError::InvalidRecord(1, _)), "{error}");
+    let mut extra = record.clone();
+    extra.push(0);
+    assert_eq!(decoder.append(&extra).unwrap_err().to_string(), "Invalid record 1: 1 bytes left over");
+
+    decoder.append(&record).unwrap();
+    assert_eq!(decoder.len(), 2);
+    let batch = decoder.finish().unwrap();
+    assert_eq!(batch.column(4).as_list::<i32>().value_offsets(), &[0, 3, 4]);
+    assert_eq!(batch.column(6).as_union().type_ids(), &[0, 2]);
+    assert!(decoder.is_empty());
+    assert_eq!(decoder.finish().unwrap().num_rows(), 0);
+}
+
+#[test]
+fn recursive_schema_test() {
+    let error = arrow_schema(&schema_container_of::<Node>(), &ArrowOptions::default()).unwrap_err();
+    assert_eq!(error.to_string(), "Node can't be represented in Arrow: recursive types have no Arrow equivalent");
+}
+
+#[cfg(feature = "generator")]
+#[test]
+fn generated_records_test() {
+    let container = schema_container_of::<Account>();
+    for enum_encoding in [EnumEncoding::DenseUnion, EnumEncoding::Struct] {
+        let mut generator = ValueGenerator::new(&container, 7, GeneratorLimits::default());
+        let records: Vec<Vec<u8>> = (0..200).map(|_| generator.generate().unwrap().1).collect();
+        let batch = decode_record_batch(&container, &records, &ArrowOptions { enum_encoding }).unwrap();
+        assert_eq!(batch.num_rows(), 200);
+    }
+}
+
+#[cfg(feature = "generator")]
+#[test]
+fn random_schemas_test() {
+    for seed in 0..100 {
+        let container = random_schema(seed, 4);
+        for enum_encoding in [EnumEncoding::DenseUnion, EnumEncoding::Struct] {
+            let options = ArrowOptions { enum_encoding };
+            match RecordBatchDecoder::new(&container, &options) {
+                Err(ArrowExportError::Unsupported(..)) => continue,
+                result => assert!(result.is_ok()),
+            }
+            let mut generator = ValueGenerator::new(&container, seed, GeneratorLimits::default());
+            let records: Vec<Vec<u8>> = (0..20).map(|_| generator.generate().unwrap().1).collect();
+            let batch = decode_record_batch(&container, &records, &options).unwrap_or_else(|error| panic!("Seed {seed}: {error}"));
+            assert_eq!(batch.num_rows(), 20);
+            #[cfg(feature = "parquet")]
+            if enum_encoding == EnumEncoding::Struct {
+                borsh_serde_adapter::arrow_export::write_parquet(Vec::new(), batch.schema(), &[batch]).unwrap_or_else(|error| panic!("Seed {seed}: {error}"));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn write_parquet_test() {
+    use borsh_serde_adapter::arrow_export::write_parquet;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let container = schema_container_of::<Account>();
+    let options = ArrowOptions { enum_encoding: EnumEncoding::Struct };
+    let mut decoder = RecordBatchDecoder::new(&container, &options).unwrap();
+    let mut batches = Vec::new();
+    for account in accounts() {
+        decoder.append(&to_vec(&account).unwrap()).unwrap();
+        if decoder.len() == 2 {
+            batches.push(decoder.finish().unwrap());
+        }
+    }
+    batches.push(decoder.finish().unwrap());
+
+    let file = write_parquet(tempfile::tempfile().unwrap(), decoder.schema(), &batches).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+    let read: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    let rows: usize = read.iter().map(|batch| batch.num_rows()).sum();
+    assert_eq!(rows, 3);
+    assert_eq!(read[0].column(1).as_primitive::<UInt64Type>().values(), &[10, 20, 30]);
+    assert_eq!(read[0].column(6).as_struct().column(0).as_string::<i32>().value(2), "Closed");
+
+    let union_schema = arrow_schema(&container, &ArrowOptions::default()).unwrap();
+    let error = write_parquet(Vec::new(), union_schema.into(), &[]).unwrap_err();
+    assert_eq!(error.to_string(), "Writing Parquet failed: Parquet has no unions, use EnumEncoding::Struct");
+}
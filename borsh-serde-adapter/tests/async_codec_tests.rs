@@ -0,0 +1,143 @@
+#![cfg(feature = "tokio")]
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::async_codec::{decode_async, encode_async, read_frame, write_frame, BorshJsonCodec, DEFAULT_MAX_FRAME_LENGTH};
+use borsh_serde_adapter::deserialize_adapter::DecodeLimits;
+use borsh_serde_adapter::errors::AsyncCodecError;
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::io::{duplex, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Framed, FramedRead};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Order {
+    id: u64,
+    side: Side,
+    note: Option<String>,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+fn frame(value: &impl BorshSerialize) -> Vec<u8> {
+    let bytes = to_vec(value).unwrap();
+    let mut data = (bytes.len() as u32).to_le_bytes().to_vec();
+    data.extend(bytes);
+    data
+}
+
+#[tokio::test]
+async fn framed_round_trip_test() {
+    let (client, server) = duplex(64);
+    let mut client = Framed::new(client, BorshJsonCodec::new(schema_container_of::<Order>()));
+    let mut server = Framed::new(server, BorshJsonCodec::new(schema_container_of::<Order>()));
+
+    let orders = [
+        json!({"id": 1, "side": {"Buy": []}, "note": {"None": null}}),
+        json!({"id": 2, "side": {"Sell": []}, "note": {"Some": "a note longer than the duplex buffer of sixty four bytes"}}),
+    ];
+    let sender = tokio::spawn(async move {
+        for order in orders {
+            client.send(order).await.unwrap();
+        }
+    });
+
+    let first = server.next().await.unwrap().unwrap();
+    assert_eq!(first, json!({"id": 1, "side": {"Buy": []}, "note": {"None": null}}));
+    let second = server.next().await.unwrap().unwrap();
+    assert_eq!(second["note"]["Some"], "a note longer than the duplex buffer of sixty four bytes");
+    sender.await.unwrap();
+    assert!(server.next().await.is_none());
+}
+
+#[tokio::test]
+async fn framed_read_of_borsh_frames_test() {
+    let (mut writer, reader) = duplex(1024);
+    let mut data = frame(&Order { id: 7, side: Side::Sell, note: None });
+    data.extend(frame(&Order { id: 8, side: Side::Buy, note: Some("x".to_string()) }));
+    writer.write_all(&data).await.unwrap();
+    drop(writer);
+
+    let values: Vec<_> = FramedRead::new(reader, BorshJsonCodec::new(schema_container_of::<Order>()))
+        .map(|value| value.unwrap()["id"].clone())
+        .collect()
+        .await;
+    assert_eq!(values, [json!(7), json!(8)]);
+}
+
+#[tokio::test]
+async fn read_and_write_frame_test() {
+    let (mut writer, mut reader) = duplex(16);
+    let schema = schema_container_of::<Order>();
+    let value = json!({"id": 3, "side": {"Buy": []}, "note": {"Some": "split across several writes"}});
+
+    let written = value.clone();
+    let writer_schema = schema.clone();
+    let sender = tokio::spawn(async move {
+        write_frame(&mut writer, &written, &writer_schema, DEFAULT_MAX_FRAME_LENGTH).await.unwrap();
+    });
+    assert_eq!(read_frame(&mut reader, &schema, DEFAULT_MAX_FRAME_LENGTH).await.unwrap(), Some(value));
+    sender.await.unwrap();
+    assert_eq!(read_frame(&mut reader, &schema, DEFAULT_MAX_FRAME_LENGTH).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn decode_and_encode_async_test() {
+    let (mut writer, mut reader) = duplex(8);
+    let schema = schema_container_of::<Order>();
+    let value = json!({"id": 4, "side": {"Sell": []}, "note": {"Some": "unframed"}});
+
+    let written = value.clone();
+    let writer_schema = schema.clone();
+    tokio::spawn(async move {
+        encode_async(&mut writer, &written, &writer_schema).await.unwrap();
+    });
+    assert_eq!(decode_async(&mut reader, &schema).await.unwrap(), value);
+}
+
+#[tokio::test]
+async fn errors_test() {
+    let schema = schema_container_of::<Order>();
+
+    let (mut writer, mut reader) = duplex(64);
+    writer.write_all(&frame(&Order { id: 1, side: Side::Buy, note: None })).await.unwrap();
+    let error = read_frame(&mut reader, &schema, 4).await.unwrap_err();
+    assert_eq!(error.to_string(), "Frame of 10 bytes exceeds the maximum of 4 bytes");
+
+    let (mut writer, mut reader) = duplex(64);
+    writer.write_all(&[5, 0, 0, 0, 1]).await.unwrap();
+    drop(writer);
+    assert!(matches!(read_frame(&mut reader, &schema, DEFAULT_MAX_FRAME_LENGTH).await.unwrap_err(), AsyncCodecError::Io(_)));
+
+    let (mut writer, mut reader) = duplex(64);
+    let error = write_frame(&mut writer, &json!({"id": 1}), &schema, DEFAULT_MAX_FRAME_LENGTH).await.unwrap_err();
+    assert!(matches!(error, AsyncCodecError::Encode(_)), "{error}");
+    writer.write_all(&[0; 12]).await.unwrap();
+    drop(writer);
+    assert_eq!(decode_async(&mut reader, &schema).await.unwrap_err().to_string(), "2 bytes left over after decoding");
+}
+
+#[test]
+fn codec_errors_test() {
+    let mut codec = BorshJsonCodec::with_limits(schema_container_of::<Order>(), 32, DecodeLimits { max_string_length: 2, ..DecodeLimits::default() });
+
+    let mut src = BytesMut::from(&[40u8, 0, 0, 0][..]);
+    assert_eq!(codec.decode(&mut src).unwrap_err().to_string(), "Frame of 40 bytes exceeds the maximum of 32 bytes");
+
+    let mut src = BytesMut::from(&frame(&Order { id: 1, side: Side::Buy, note: Some("long".to_string()) })[..]);
+    assert!(matches!(codec.decode(&mut src).unwrap_err(), AsyncCodecError::Io(_)));
+
+    let mut data = frame(&Order { id: 1, side: Side::Buy, note: None });
+    data.truncate(7);
+    let mut src = BytesMut::from(&data[..]);
+    assert!(codec.decode(&mut src).unwrap().is_none());
+    assert_eq!(src.len(), 7);
+    assert_eq!(codec.decode_eof(&mut src).unwrap_err().to_string(), "Stream ended inside a frame, 7 bytes buffered");
+
+    let mut src = BytesMut::from(&[2u8, 0, 0, 0, 0, 0][..]);
+    assert!(matches!(codec.decode(&mut src).unwrap_err(), AsyncCodecError::Io(_)));
+}
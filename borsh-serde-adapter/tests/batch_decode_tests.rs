@@ -0,0 +1,80 @@
+#![cfg(feature = "rayon")]
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::batch_decode::{decode_batch, decode_batch_with_limits, decode_framed_batch, split_frames};
+use borsh_serde_adapter::deserialize_adapter::{deserialize_from_schema, DecodeLimits};
+use borsh_serde_adapter::errors::{BatchDecodeError, RecordStreamError};
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Snapshot {
+    slot: u64,
+    owner: String,
+    balances: Vec<u32>,
+}
+
+fn snapshot(slot: u64) -> Snapshot {
+    Snapshot {
+        slot,
+        owner: format!("owner-{slot}"),
+        balances: (0..slot as u32 % 7).collect(),
+    }
+}
+
+fn framed(records: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for record in records {
+        data.extend((record.len() as u32).to_le_bytes());
+        data.extend(record);
+    }
+    data
+}
+
+#[test]
+fn matches_sequential_decoding_test() {
+    let schema = schema_container_of::<Snapshot>();
+    let records: Vec<Vec<u8>> = (0..2_000).map(|slot| to_vec(&snapshot(slot)).unwrap()).collect();
+
+    let results = decode_batch(&records, &schema);
+    assert_eq!(results.len(), records.len());
+    for (record, result) in records.iter().zip(&results) {
+        assert_eq!(result.as_ref().unwrap(), &deserialize_from_schema(&mut record.as_slice(), &schema).unwrap());
+    }
+    assert_eq!(results[9].as_ref().unwrap(), &json!({"slot": 9, "owner": "owner-9", "balances": [0, 1]}));
+
+    let framed_results = decode_framed_batch(&framed(&records), &schema).unwrap();
+    assert_eq!(framed_results.len(), records.len());
+    assert_eq!(framed_results[1999].as_ref().unwrap()["slot"], 1999);
+}
+
+#[test]
+fn errors_are_collected_per_record_test() {
+    let schema = schema_container_of::<Snapshot>();
+    let mut with_trailing = to_vec(&snapshot(2)).unwrap();
+    with_trailing.push(0);
+    let records = vec![to_vec(&snapshot(1)).unwrap(), vec![1, 2, 3], with_trailing, to_vec(&snapshot(3)).unwrap()];
+
+    let results = decode_batch(&records, &schema);
+    assert!(results[0].is_ok() && results[3].is_ok());
+    assert!(matches!(results[1], Err(BatchDecodeError::Invalid(1, _))));
+    assert_eq!(results[2].as_ref().unwrap_err().to_string(), "Record 2 has 1 bytes left over after decoding");
+
+    let limits = DecodeLimits { max_string_length: 4, ..DecodeLimits::default() };
+    let results = decode_batch_with_limits(&records[..1], &schema, &limits);
+    assert!(matches!(results[..], [Err(BatchDecodeError::Invalid(0, _))]));
+}
+
+#[test]
+fn framing_errors_test() {
+    let schema = schema_container_of::<Snapshot>();
+    let mut data = framed(&[to_vec(&snapshot(1)).unwrap()]);
+    let first_length = data.len() as u64;
+    data.extend([50, 0, 0, 0, 1]);
+    let error = decode_framed_batch(&data, &schema).unwrap_err();
+    assert!(matches!(error, RecordStreamError::Truncated(1, offset) if offset == first_length), "{error}");
+
+    let error = split_frames(&[9, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9], 8).unwrap_err();
+    assert_eq!(error.to_string(), "Record 0 at byte 0 is longer than the maximum of 8 bytes");
+    assert_eq!(split_frames(&[1, 0, 0, 0, 7, 0, 0, 0, 0], 8).unwrap(), [&[7][..], &[][..]]);
+    assert!(decode_framed_batch(&[], &schema).unwrap().is_empty());
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+use borsh_serde_adapter::borsh_view::BorshView;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::errors::ViewError;
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Item {
+    name: String,
+    price: u64,
+    quantity: u32,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum Tier {
+    Basic,
+    Premium { level: u8 },
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+/// An account with a long sequence of structs, most of which a view never reads
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    items: Vec<Item>,
+    flags: [u8; 4],
+    path: Vec<Point>,
+    tier: Tier,
+    note: Option<String>,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "alice".to_string(),
+        items: (0..5)
+            .map(|i| Item {
+                name: format!("item-{i}"),
+                price: 100 * i,
+                quantity: i as u32,
+            })
+            .collect(),
+        flags: [1, 2, 3, 4],
+        path: (0..1000).map(|i| Point { x: i, y: -i }).collect(),
+        tier: Tier::Premium { level: 7 },
+        note: Some("hello".to_string()),
+    }
+}
+
+#[test]
+fn nested_fields_are_read_lazily() -> Result<(), ViewError> {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let view = BorshView::new(&account_ser, &container);
+
+    assert_eq!(view.get("items")?.index(3)?.get("price")?.as_u64()?, 300);
+    assert_eq!(view.get("items")?.len()?, 5);
+    assert_eq!(view.get("flags")?.index(2)?.as_u64()?, 3);
+    assert_eq!(view.get("path")?.index(999)?.get("y")?.as_i64()?, -999);
+    assert_eq!(view.get("tier")?.variant()?, "Premium");
+    assert_eq!(view.get("tier")?.get("Premium")?.get("level")?.as_u64()?, 7);
+    assert_eq!(view.get("note")?.get("Some")?.as_str()?, "hello");
+    Ok(())
+}
+
+#[test]
+fn strings_and_bytes_are_borrowed() -> Result<(), ViewError> {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let view = BorshView::new(&account_ser, &container);
+
+    let owner = view.get("owner")?.as_str()?;
+    assert_eq!(owner, "alice");
+    assert!(account_ser.as_ptr_range().contains(&owner.as_ptr()));
+
+    let item = view.get("items")?.index(1)?;
+    assert_eq!(item.as_bytes()?, to_vec(&account().items[1]).expect("Error trying to serialize Item"));
+    assert_eq!(view.as_bytes()?, account_ser.as_slice());
+    Ok(())
+}
+
+#[test]
+fn views_decode_to_the_same_json() -> Result<(), ViewError> {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let view = BorshView::new(&account_ser, &container);
+
+    let expected = deserialize_from_schema(&mut account_ser.as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(view.to_json().expect("Deserialization failed"), expected);
+    assert_eq!(view.get("items")?.index(2)?.to_json().expect("Deserialization failed"), json!({"name": "item-2", "price": 200, "quantity": 2}));
+    assert_eq!(view.get("tier")?.to_json().expect("Deserialization failed"), expected["tier"]);
+    Ok(())
+}
+
+#[test]
+fn errors_are_reported() {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let view = BorshView::new(&account_ser, &container);
+
+    assert_eq!(view.get("missing").unwrap_err(), ViewError::NoField("Account".to_string(), "missing".to_string()));
+    assert_eq!(view.get("items").and_then(|items| items.index(5)).unwrap_err().to_string(), "Index 5 is out of bounds for Vec<Item> of length 5");
+    assert_eq!(view.get("owner").and_then(|owner| owner.as_u64()).unwrap_err().to_string(), "Expected unsigned integer, found String");
+    assert_eq!(
+        view.get("tier").and_then(|tier| tier.get("Basic")).unwrap_err(),
+        ViewError::InactiveVariant("Basic".to_string(), "Premium".to_string())
+    );
+
+    // Fields after the end of the input can't be reached
+    let truncated = &account_ser[..account_ser.len() - 3];
+    let view = BorshView::new(truncated, &container);
+    assert_eq!(view.get("owner").and_then(|owner| owner.as_str()).ok(), Some("alice"));
+    assert!(view.get("note").and_then(|note| note.get("Some")).is_ok());
+    assert_eq!(
+        view.get("note").and_then(|note| note.get("Some")).and_then(|note| note.as_str()).unwrap_err(),
+        ViewError::UnexpectedEnd("String".to_string())
+    );
+    let truncated = &account_ser[..20];
+    let view = BorshView::new(truncated, &container);
+    assert!(matches!(view.get("tier").unwrap_err(), ViewError::UnexpectedEnd(_)));
+
+    // Tags that name no variant, or are of a width borsh doesn't use, are told apart
+    let tier = to_vec(&Tier::Basic).expect("Error trying to serialize Tier");
+    let container: BorshSchemaContainer = schema_container_of::<Tier>();
+    let unknown = [5];
+    assert_eq!(BorshView::new(&unknown, &container).variant().unwrap_err(), ViewError::UnknownDiscriminant(5, "Tier".to_string()));
+    assert_eq!(BorshView::new(&tier, &container).variant(), Ok("Basic"));
+    let variants = vec![(0, "A".to_string(), "u8".to_string())];
+    let definitions = BTreeMap::from([("Wide".to_string(), Definition::Enum { tag_width: 3, variants })]);
+    let container = BorshSchemaContainer::new("Wide".to_string(), definitions);
+    assert_eq!(BorshView::new(&[0; 4], &container).variant().unwrap_err(), ViewError::Unsupported("Wide".to_string()));
+}
+
+#[test]
+fn shared_types_are_sized_once() -> Result<(), ViewError> {
+    // Level i is an enum whose two variants both hold level i + 1, so there are 2^40 paths down to the u8 at the bottom
+    let mut definitions = BTreeMap::new();
+    let fields = vec![("levels".to_string(), "Level0".to_string()), ("last".to_string(), "u8".to_string())];
+    definitions.insert("Root".to_string(), Definition::Struct { fields: Fields::NamedFields(fields) });
+    for level in 0..40 {
+        let next = if level == 39 { "u8".to_string() } else { format!("Level{}", level + 1) };
+        let variants = vec![(0, "A".to_string(), next.clone()), (1, "B".to_string(), next)];
+        definitions.insert(format!("Level{level}"), Definition::Enum { tag_width: 1, variants });
+    }
+    let container = BorshSchemaContainer::new("Root".to_string(), definitions);
+
+    let mut bytes = vec![0; 41];
+    bytes.push(7);
+    let view = BorshView::new(&bytes, &container);
+    assert_eq!(view.get("last")?.as_u64()?, 7);
+    assert_eq!(view.get("levels")?.as_bytes()?.len(), 41);
+    Ok(())
+}
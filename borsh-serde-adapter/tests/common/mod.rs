@@ -0,0 +1,8 @@
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+/// The status of the account fixtures, with a variant that has fields and one that doesn't
+#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum Status {
+    Active { since: i64 },
+    Frozen,
+}
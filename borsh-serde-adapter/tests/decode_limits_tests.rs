@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::{BorshSchemaContainer, Definition};
+use borsh_serde_adapter::deserialize_adapter::{deserialize_from_schema, deserialize_from_schema_with_limits, DecodeLimits};
+use serde_json::json;
+
+/// An account with one sequence of each kind, so that every limit can be reached
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balances: Vec<u64>,
+    tags: Vec<String>,
+}
+
+#[derive(BorshSchema)]
+pub struct Marker;
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub struct Markers {
+    markers: Vec<Marker>,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "alice".to_string(),
+        balances: vec![1, 2, 3],
+        tags: vec!["a".to_string(), "b".to_string()],
+    }
+}
+
+#[test]
+fn account_decodes_within_limits() {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let limits = DecodeLimits {
+        max_sequence_length: 3,
+        max_total_bytes: account_ser.len(),
+        max_string_length: 5,
+        max_depth: 2,
+        max_output_nodes: 9,
+    };
+
+    let result = deserialize_from_schema_with_limits(&mut account_ser.as_slice(), &container, &limits).expect("Deserialization failed");
+    assert_eq!(result, json!({"owner": "alice", "balances": [1, 2, 3], "tags": ["a", "b"]}));
+}
+
+#[test]
+fn each_limit_is_enforced() {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let account_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let decode = |limits: DecodeLimits| {
+        deserialize_from_schema_with_limits(&mut account_ser.as_slice(), &container, &limits)
+            .expect_err("A limit should be exceeded")
+            .to_string()
+    };
+
+    assert_eq!(decode(DecodeLimits { max_sequence_length: 2, ..DecodeLimits::default() }), "Sequence length 3 exceeds the maximum of 2");
+    assert_eq!(decode(DecodeLimits { max_total_bytes: account_ser.len() - 1, ..DecodeLimits::default() }), format!("Maximum of {} bytes exceeded", account_ser.len() - 1));
+    assert_eq!(decode(DecodeLimits { max_string_length: 4, ..DecodeLimits::default() }), "String length 5 exceeds the maximum of 4");
+    assert_eq!(decode(DecodeLimits { max_depth: 1, ..DecodeLimits::default() }), "Maximum nesting depth of 1 exceeded");
+    assert_eq!(decode(DecodeLimits { max_output_nodes: 8, ..DecodeLimits::default() }), "Maximum of 8 output nodes exceeded");
+}
+
+#[test]
+fn huge_sequence_length_fails_fast() {
+    let container: BorshSchemaContainer = schema_container_of::<Vec<u64>>();
+    let hostile = [0xFF, 0xFF, 0xFF, 0xFF, 1, 2, 3];
+
+    let error = deserialize_from_schema(&mut hostile.as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "Sequence of 4294967295 u64 needs more than the 3 bytes remaining");
+
+    // Elements without a fixed size are bounded by their smallest encoding, a String needs at least its length prefix
+    let container: BorshSchemaContainer = schema_container_of::<Vec<String>>();
+    let hostile = [0x00, 0x00, 0x00, 0x10, 0, 0, 0, 0];
+    let error = deserialize_from_schema(&mut hostile.as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), "Sequence of 268435456 String needs more than the 4 bytes remaining");
+}
+
+#[test]
+fn huge_string_length_fails_fast() {
+    let container: BorshSchemaContainer = schema_container_of::<String>();
+    let hostile = [0xFF, 0xFF, 0xFF, 0xFF, b'a'];
+
+    let error = deserialize_from_schema(&mut hostile.as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), "String of 4294967295 bytes needs more than the 1 bytes remaining");
+}
+
+#[test]
+fn zero_sized_sequences_are_rejected() {
+    let container: BorshSchemaContainer = schema_container_of::<Markers>();
+    let hostile = [0xFF, 0xFF, 0xFF, 0xFF];
+
+    let error = deserialize_from_schema(&mut hostile.as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), "Sequence of zero sized type Marker is not allowed");
+
+    let empty = [0x00, 0x00, 0x00, 0x00];
+    let result = deserialize_from_schema(&mut empty.as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(result, json!({"markers": []}));
+}
+
+/// Level i is a pair of level i + 1, so there are 2^40 paths from the root to the bytes at the bottom
+fn shared_levels_schema() -> BorshSchemaContainer {
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Levels".to_string(), Definition::Sequence { length_width: 4, length_range: 0..=u32::MAX as u64, elements: "Level0".to_string() });
+    for level in 0..40 {
+        let next = format!("Level{}", level + 1);
+        definitions.insert(format!("Level{level}"), Definition::Tuple { elements: vec![next.clone(), next] });
+    }
+    definitions.insert("Level40".to_string(), Definition::Sequence { length_width: 4, length_range: 0..=u32::MAX as u64, elements: "u8".to_string() });
+    BorshSchemaContainer::new("Levels".to_string(), definitions)
+}
+
+#[test]
+fn shared_types_are_sized_once() {
+    let container = shared_levels_schema();
+    let result = deserialize_from_schema(&mut [0, 0, 0, 0].as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(result, json!([]));
+
+    let error = deserialize_from_schema(&mut [1, 0, 0, 0].as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), "Sequence of 1 Level0 needs more than the 0 bytes remaining");
+}
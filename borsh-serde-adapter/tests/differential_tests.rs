@@ -0,0 +1,228 @@
+#![cfg(feature = "generator")]
+#![recursion_limit = "256"]
+#![allow(clippy::type_complexity)]
+
+use borsh::schema::BorshSchemaContainer;
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::hexdump::Hexdump;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use borsh_serde_adapter::value_generator::{GeneratorLimits, ValueGenerator};
+
+#[derive(Default, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Person {
+    first_name: String,
+    last_name: String,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum TestEnum {
+    One(u8),
+    Two(u8),
+    Three(u8),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct AllTypes {
+    type_u8: u8,
+    type_u16: u16,
+    type_u32: u32,
+    type_u64: u64,
+    type_u128: u128,
+    type_i8: i8,
+    type_i16: i16,
+    type_i32: i32,
+    type_i64: i64,
+    type_i128: i128,
+    type_f32: f32,
+    type_f64: f64,
+    type_string: String,
+    type_bool: bool,
+    type_array: [u8; 3],
+    type_sequence: Vec<String>,
+    type_tuple: (u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64, String, bool),
+    type_enum: TestEnum,
+    type_struct: Person,
+}
+
+#[derive(Clone, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum Instruction {
+    Initialize,
+    Transfer { amount: u64, memo: Option<String> },
+    Batch(Vec<Instruction>),
+    Pair(u16, i32),
+    Close(Box<Instruction>),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Wrapper(u32);
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Collections {
+    bytes: Vec<u8>,
+    matrix: [[i16; 2]; 3],
+    nested: Vec<Vec<Option<bool>>>,
+    wrapped: Vec<Wrapper>,
+    floats: (f32, f64),
+    unit: (),
+}
+
+fn all_types() -> AllTypes {
+    AllTypes {
+        type_u8: u8::MAX,
+        type_u16: u16::MAX,
+        type_u32: u32::MAX,
+        type_u64: u64::MAX,
+        type_u128: u128::MAX,
+        type_i8: i8::MIN,
+        type_i16: i16::MIN,
+        type_i32: i32::MIN,
+        type_i64: i64::MIN,
+        type_i128: i128::MIN,
+        type_f32: f32::MIN,
+        type_f64: f64::MAX,
+        type_string: "abc".to_string(),
+        type_bool: false,
+        type_array: [b'a', b'b', b'c'],
+        type_sequence: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        type_tuple: (1, 2, 3, 4, 5, -1, -2, -3, -4, -5, f32::MIN_POSITIVE, 0.5, "xyz".to_string(), true),
+        type_enum: TestEnum::Three(3),
+        type_struct: Person::default(),
+    }
+}
+
+/// Small xorshift generator so the mutated inputs are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Checks that both adapters and the plan turn the bytes into JSON and back into the same bytes
+fn assert_round_trip(bytes: &[u8], container: &BorshSchemaContainer, plan: &SchemaPlan) {
+    let mut buffer = bytes;
+    let value = deserialize_from_schema(&mut buffer, container).expect("Deserialization failed");
+    assert!(buffer.is_empty(), "{} bytes left over decoding {value}", buffer.len());
+    assert_eq!(plan.deserialize(&mut &bytes[..]).expect("Plan deserialization failed"), value);
+
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &value, container).unwrap_or_else(|error| panic!("Serializing {value} failed: {error}"));
+    assert_eq!(writer, bytes, "{value}");
+    let mut writer = Vec::new();
+    plan.serialize(&mut writer, &value).expect("Plan serialization failed");
+    assert_eq!(writer, bytes, "{value}");
+}
+
+/// Checks values of a derived type against the adapters: given values and generated values must round trip, and
+/// mutations of their bytes must be accepted or rejected exactly as the derived decoder does
+fn check_type<T: BorshSerialize + BorshDeserialize + BorshSchema>(values: &[T], seed: u64) {
+    let container = schema_container_of::<T>();
+    let plan = SchemaPlan::compile(&container);
+    let mut corpus: Vec<Vec<u8>> = values.iter().map(|value| to_vec(value).expect("Serialization failed")).collect();
+
+    let mut generator = ValueGenerator::new(&container, seed, GeneratorLimits::default());
+    for _ in 0..100 {
+        let (_, bytes) = generator.generate().expect("Generation failed");
+        let derived = T::try_from_slice(&bytes).expect("Generated bytes should decode with the derived decoder");
+        assert_eq!(to_vec(&derived).expect("Serialization failed"), bytes);
+        corpus.push(bytes);
+    }
+
+    let mut rng = XorShift(seed | 1);
+    for bytes in &corpus {
+        assert_round_trip(bytes, &container, &plan);
+
+        for _ in 0..50 {
+            let mut input = bytes.clone();
+            match rng.below(3) {
+                0 if !input.is_empty() => {
+                    let index = rng.below(input.len() as u64) as usize;
+                    input[index] = rng.next() as u8;
+                }
+                1 => input.truncate(rng.below(input.len() as u64 + 1) as usize),
+                _ => input.extend((0..rng.below(4)).map(|_| rng.next() as u8)),
+            }
+            check_against_derived::<T>(&input, &container, &plan);
+        }
+    }
+}
+
+/// Whether the bytes hold an infinite f32 or f64, which decodes to null as JSON has no number for it
+fn has_infinite_float(bytes: &[u8], container: &BorshSchemaContainer) -> bool {
+    Hexdump::annotate(bytes, container).spans.iter().any(|span| {
+        let float = &bytes[span.start..span.end];
+        match span.declaration.as_str() {
+            "f32" => f32::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            "f64" => f64::from_le_bytes(float.try_into().unwrap()).is_infinite(),
+            _ => false,
+        }
+    })
+}
+
+fn check_against_derived<T: BorshSerialize + BorshDeserialize>(input: &[u8], container: &BorshSchemaContainer, plan: &SchemaPlan) {
+    let mut derived_buffer = input;
+    let derived = T::deserialize(&mut derived_buffer);
+    let mut buffer = input;
+    let result = deserialize_from_schema(&mut buffer, container);
+    assert_eq!(result.is_ok(), derived.is_ok(), "{input:?} gave {result:?}");
+
+    if let (Ok(derived), Ok(value)) = (derived, result) {
+        assert_eq!(buffer.len(), derived_buffer.len(), "{input:?}");
+        let consumed = &input[..input.len() - buffer.len()];
+        assert_eq!(to_vec(&derived).expect("Serialization failed"), consumed);
+        if has_infinite_float(consumed, container) {
+            let mut writer = Vec::new();
+            assert!(serialize_serde_json_to_borsh(&mut writer, &value, container).is_err(), "{value}");
+        } else {
+            assert_round_trip(consumed, container, plan);
+        }
+    }
+}
+
+#[test]
+fn all_types_match_the_derived_impls() {
+    check_type(&[all_types()], 1);
+}
+
+#[test]
+fn enums_match_the_derived_impls() {
+    check_type(&[
+        Instruction::Initialize,
+        Instruction::Transfer { amount: 5, memo: None },
+        Instruction::Transfer { amount: u64::MAX, memo: Some("rent".to_string()) },
+        Instruction::Batch(vec![Instruction::Pair(1, -1), Instruction::Close(Box::new(Instruction::Initialize))]),
+    ], 2);
+}
+
+#[test]
+fn collections_match_the_derived_impls() {
+    check_type(&[Collections {
+        bytes: vec![0, 1, 255],
+        matrix: [[1, -1], [2, -2], [3, -3]],
+        nested: vec![vec![], vec![None, Some(true), Some(false)]],
+        wrapped: vec![Wrapper(7), Wrapper(u32::MAX)],
+        floats: (f32::MAX, -0.0),
+        unit: (),
+    }], 3);
+    check_type(&[Wrapper(9)], 4);
+    check_type(&[(), ()], 5);
+}
+
+#[test]
+fn infinite_floats_are_refused_when_serializing() {
+    let container = schema_container_of::<(f32, f64)>();
+    let bytes = to_vec(&(f32::INFINITY, 0.5f64)).unwrap();
+    assert!(has_infinite_float(&bytes, &container));
+    assert!(!has_infinite_float(&to_vec(&(f32::MAX, f64::MIN)).unwrap(), &container));
+    check_against_derived::<(f32, f64)>(&bytes, &container, &SchemaPlan::compile(&container));
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::{BorshSchemaContainer, Definition};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use serde_json::{json, Value};
+
+#[derive(BorshSerialize, BorshSchema)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum Opcode {
+    Nop = 0,
+    Push = 5,
+    Halt = 255,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Program {
+    ops: Vec<Opcode>,
+}
+
+fn encode(value: &Value, schema: &BorshSchemaContainer) -> Vec<u8> {
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, value, schema).expect("Serialization failed");
+    writer
+}
+
+/// A sequence of u16 with a u8 length and an enum with a u16 tag, neither of which the derive produces
+fn custom_width_schema() -> BorshSchemaContainer {
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Message".to_string(), Definition::Tuple { elements: vec!["Short".to_string(), "Kind".to_string()] });
+    definitions.insert("Short".to_string(), Definition::Sequence { length_width: 1, length_range: 0..=255, elements: "u16".to_string() });
+    definitions.insert("Kind".to_string(), Definition::Enum {
+        tag_width: 2,
+        variants: vec![(0, "Small".to_string(), "u8".to_string()), (1000, "Large".to_string(), "u64".to_string())],
+    });
+    BorshSchemaContainer::new("Message".to_string(), definitions)
+}
+
+#[test]
+fn explicit_discriminants_are_matched_by_value() {
+    let schema = schema_container_of::<Program>();
+    let data = to_vec(&Program { ops: vec![Opcode::Push, Opcode::Nop, Opcode::Halt] }).unwrap();
+    assert_eq!(data[4..], [5, 0, 255]);
+
+    let value = deserialize_from_schema(&mut data.as_slice(), &schema).unwrap();
+    assert_eq!(value, json!({"ops": [{"Push": []}, {"Nop": []}, {"Halt": []}]}));
+    assert_eq!(encode(&value, &schema), data);
+}
+
+#[test]
+fn unknown_discriminants_are_errors() {
+    let schema = schema_container_of::<Program>();
+    // 1 is the index of Push, but not its discriminant
+    let error = deserialize_from_schema(&mut [1, 0, 0, 0, 1].as_slice(), &schema).unwrap_err();
+    assert_eq!(error.to_string(), "Unknown discriminant 1 for Opcode");
+}
+
+#[test]
+fn length_and_tag_widths_are_honoured() {
+    let schema = custom_width_schema();
+    let data = [2, 1, 0, 2, 0, 0xe8, 0x03, 9, 0, 0, 0, 0, 0, 0, 0];
+
+    let value = deserialize_from_schema(&mut data.as_slice(), &schema).unwrap();
+    assert_eq!(value, json!([[1, 2], {"Large": 9}]));
+    assert_eq!(encode(&value, &schema), data);
+    assert_eq!(encode(&json!([[], {"Small": 7}]), &schema), [0, 0, 0, 7]);
+}
@@ -0,0 +1,94 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::anchor_idl::account_discriminator;
+use borsh_serde_adapter::discriminator_dispatch::DiscriminatorDispatcher;
+use borsh_serde_adapter::errors::DiscriminatorError;
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Vault {
+    authority: [u8; 4],
+    balance: u64,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Config {
+    admin: String,
+    paused: bool,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Transfer {
+    amount: u64,
+}
+
+fn prefixed(discriminator: &[u8], value: &impl BorshSerialize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    data.extend(to_vec(value).unwrap());
+    data
+}
+
+fn dispatcher() -> DiscriminatorDispatcher {
+    let mut dispatcher = DiscriminatorDispatcher::new();
+    dispatcher.register_anchor_account("Vault", schema_container_of::<Vault>()).unwrap();
+    dispatcher.register("Config", [1, 2, 3, 4, 5, 6, 7, 8], schema_container_of::<Config>()).unwrap();
+    dispatcher.register("Transfer", [3], schema_container_of::<Transfer>()).unwrap();
+    dispatcher
+}
+
+#[test]
+fn decode_test() {
+    let dispatcher = dispatcher();
+
+    let mut vault = prefixed(&account_discriminator("Vault"), &Vault { authority: [1, 2, 3, 4], balance: 50 });
+    // Space allocated beyond the value is ignored
+    vault.extend([0; 10]);
+    assert_eq!(dispatcher.decode(&vault).unwrap(), json!({"type": "Vault", "value": {"authority": [1, 2, 3, 4], "balance": 50}}));
+
+    let config = prefixed(&[1, 2, 3, 4, 5, 6, 7, 8], &Config { admin: "root".to_string(), paused: true });
+    assert_eq!(dispatcher.decode(&config).unwrap(), json!({"type": "Config", "value": {"admin": "root", "paused": true}}));
+
+    let transfer = prefixed(&[3], &Transfer { amount: 9 });
+    let (name, value) = dispatcher.decode_with_name(&transfer).unwrap();
+    assert_eq!((name.as_str(), value), ("Transfer", json!({"amount": 9})));
+    assert_eq!(dispatcher.resolve(&transfer).unwrap().0, "Transfer");
+}
+
+#[test]
+fn encode_test() {
+    let dispatcher = dispatcher();
+    let data = dispatcher.encode("Transfer", &json!({"amount": 9})).unwrap();
+    assert_eq!(data, prefixed(&[3], &Transfer { amount: 9 }));
+
+    let value = json!({"authority": [1, 2, 3, 4], "balance": 50});
+    let data = dispatcher.encode("Vault", &value).unwrap();
+    assert_eq!(&data[..8], account_discriminator("Vault"));
+    assert_eq!(dispatcher.decode(&data).unwrap()["value"], value);
+
+    assert!(matches!(dispatcher.encode("Transfer", &json!({})).unwrap_err(), DiscriminatorError::Encode(_)));
+    assert_eq!(dispatcher.encode("Mint", &json!({})).unwrap_err().to_string(), "Mint is not registered");
+}
+
+#[test]
+fn registration_test() {
+    let mut dispatcher = dispatcher();
+    let entries: Vec<(&str, usize)> = dispatcher.entries().map(|(name, discriminator)| (name, discriminator.len())).collect();
+    assert_eq!(entries, [("Vault", 8), ("Config", 8), ("Transfer", 1)]);
+    assert!(dispatcher.schema("Config").is_some());
+
+    let schema = schema_container_of::<Transfer>;
+    assert_eq!(dispatcher.register("Mint", Vec::new(), schema()).unwrap_err().to_string(), "Discriminator of Mint is empty");
+    assert_eq!(dispatcher.register("Transfer", [4], schema()).unwrap_err().to_string(), "Transfer is already registered");
+    assert_eq!(
+        dispatcher.register("Mint", [1, 2], schema()).unwrap_err().to_string(),
+        "Discriminator 0102 of Mint conflicts with the discriminator of Config"
+    );
+    assert!(matches!(dispatcher.register("Burn", [3, 0], schema()).unwrap_err(), DiscriminatorError::Conflict(..)));
+    dispatcher.register("Mint", [4], schema()).unwrap();
+}
+
+#[test]
+fn errors_test() {
+    let dispatcher = dispatcher();
+    assert_eq!(dispatcher.decode(&[9; 12]).unwrap_err().to_string(), "Unknown discriminator 0909090909090909");
+    assert!(matches!(dispatcher.decode(&[3, 1]).unwrap_err(), DiscriminatorError::Io(_)));
+}
@@ -0,0 +1,161 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::flat_export::{FlatOptions, FlatSchema, SequenceHandling, VariantHandling};
+
+mod common;
+use common::Status;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Balance {
+    amount: u64,
+    decimals: u8,
+}
+
+/// An account with a nested struct, an Option of one and a tuple, to be flattened into columns
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    key: [u8; 4],
+    balance: Balance,
+    tags: Vec<String>,
+    delegate: Option<Balance>,
+    status: Status,
+    pair: (u16, bool),
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Order {
+    id: u32,
+    lines: Vec<Balance>,
+    notes: Vec<String>,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "alice".to_string(),
+        key: [0, 1, 0xab, 0xff],
+        balance: Balance { amount: 5, decimals: 2 },
+        tags: vec!["a".to_string(), "b, c".to_string()],
+        delegate: None,
+        status: Status::Active { since: -1 },
+        pair: (7, true),
+    }
+}
+
+fn rows(table: &FlatSchema, bytes: &[u8]) -> Vec<Vec<String>> {
+    table.decode_rows(&mut &bytes[..]).expect("Decoding failed")
+}
+
+#[test]
+fn columns_test() {
+    let container = schema_container_of::<Account>();
+    let table = FlatSchema::compile(&container, &FlatOptions::default());
+    assert_eq!(table.columns(), [
+        "owner",
+        "key",
+        "balance.amount",
+        "balance.decimals",
+        "tags",
+        "delegate.amount",
+        "delegate.decimals",
+        "status",
+        "status.Active.since",
+        "pair[0]",
+        "pair[1]",
+    ]);
+    assert_eq!(rows(&table, &to_vec(&account()).unwrap()), [[
+        "alice", "0001abff", "5", "2", "a;b, c", "", "", "Active", "-1", "7", "true",
+    ]]);
+}
+
+#[test]
+fn truncate_and_json_variants_test() {
+    let container = schema_container_of::<Account>();
+    let options = FlatOptions {
+        sequences: SequenceHandling::Truncate(3),
+        variants: VariantHandling::Json,
+    };
+    let table = FlatSchema::compile(&container, &options);
+    assert_eq!(&table.columns()[4..7], ["tags[0]", "tags[1]", "tags[2]"]);
+    assert_eq!(table.columns()[9], "status");
+
+    let account = Account {
+        tags: vec!["x".to_string(); 5],
+        delegate: Some(Balance { amount: 9, decimals: 0 }),
+        status: Status::Frozen,
+        ..account()
+    };
+    let row = &rows(&table, &to_vec(&account).unwrap())[0];
+    assert_eq!(&row[4..10], ["x", "x", "x", "9", "0", "{\"Frozen\":[]}"]);
+}
+
+#[test]
+fn explode_test() {
+    let container = schema_container_of::<Order>();
+    let options = FlatOptions {
+        sequences: SequenceHandling::Explode,
+        ..FlatOptions::default()
+    };
+    let table = FlatSchema::compile(&container, &options);
+    assert_eq!(table.columns(), ["id", "lines[*].amount", "lines[*].decimals", "notes[*]"]);
+
+    let order = Order {
+        id: 1,
+        lines: vec![Balance { amount: 10, decimals: 1 }, Balance { amount: 20, decimals: 2 }],
+        notes: vec!["x".to_string(), "y".to_string()],
+    };
+    assert_eq!(rows(&table, &to_vec(&order).unwrap()), [
+        ["1", "10", "1", "x"],
+        ["1", "10", "1", "y"],
+        ["1", "20", "2", "x"],
+        ["1", "20", "2", "y"],
+    ]);
+
+    let empty = Order { id: 2, lines: Vec::new(), notes: vec!["z".to_string()] };
+    assert_eq!(rows(&table, &to_vec(&empty).unwrap()), [["2", "", "", "z"]]);
+}
+
+#[test]
+fn recursive_values_are_json_test() {
+    let container = schema_container_of::<Node>();
+    let options = FlatOptions {
+        sequences: SequenceHandling::Explode,
+        ..FlatOptions::default()
+    };
+    let table = FlatSchema::compile(&container, &options);
+    assert_eq!(table.columns(), ["value", "children[*]"]);
+
+    let node = Node { value: 1, children: vec![Node { value: 2, children: Vec::new() }] };
+    assert_eq!(rows(&table, &to_vec(&node).unwrap()), [["1", "{\"children\":[],\"value\":2}"]]);
+}
+
+#[test]
+fn invalid_record_test() {
+    let container = schema_container_of::<Order>();
+    let table = FlatSchema::compile(&container, &FlatOptions::default());
+    assert!(table.decode_rows(&mut [1u8, 0].as_slice()).is_err());
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn write_csv_test() {
+    use borsh_serde_adapter::flat_export::write_csv;
+
+    let container = schema_container_of::<Account>();
+    let records = vec![to_vec(&account()).unwrap(), to_vec(&Account { status: Status::Frozen, ..account() }).unwrap()];
+    let mut csv = Vec::new();
+    assert_eq!(write_csv(&mut csv, &container, &records, &FlatOptions::default()).unwrap(), 2);
+    assert_eq!(String::from_utf8(csv).unwrap(), "\
+owner,key,balance.amount,balance.decimals,tags,delegate.amount,delegate.decimals,status,status.Active.since,pair[0],pair[1]
+alice,0001abff,5,2,\"a;b, c\",,,Active,-1,7,true
+alice,0001abff,5,2,\"a;b, c\",,,Frozen,,7,true
+");
+
+    let error = write_csv(Vec::new(), &container, [vec![0u8]], &FlatOptions::default()).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid record 0");
+}
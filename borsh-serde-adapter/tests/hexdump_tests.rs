@@ -0,0 +1,142 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::hexdump::{Hexdump, SpanKind};
+use serde_json::json;
+
+mod common;
+use common::Status;
+
+/// An account with a fixed array and a None, so that every kind of span is annotated
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balance: u64,
+    key: [u8; 4],
+    lots: Vec<u16>,
+    status: Status,
+    delegate: Option<u32>,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "bob".to_string(),
+        balance: 10,
+        key: [0xde, 0xad, 0xbe, 0xef],
+        lots: vec![1, 2],
+        status: Status::Active { since: -1 },
+        delegate: None,
+    }
+}
+
+#[test]
+fn spans_test() {
+    let bytes = to_vec(&account()).unwrap();
+    let dump = Hexdump::annotate(&bytes, &schema_container_of::<Account>());
+    assert_eq!(dump.failure, None);
+    assert_eq!(dump.trailing, None);
+
+    let spans: Vec<(usize, usize, &str, SpanKind)> = dump.spans.iter().map(|span| (span.start, span.end, span.path.as_str(), span.kind)).collect();
+    assert_eq!(spans, [
+        (0, 7, "owner", SpanKind::Value),
+        (7, 15, "balance", SpanKind::Value),
+        (15, 19, "key", SpanKind::Value),
+        (19, 23, "lots", SpanKind::Length),
+        (23, 25, "lots[0]", SpanKind::Value),
+        (25, 27, "lots[1]", SpanKind::Value),
+        (27, 28, "status", SpanKind::Tag),
+        (28, 36, "status.Active.since", SpanKind::Value),
+        (36, 37, "delegate", SpanKind::Tag),
+    ]);
+    assert_eq!(dump.spans[0].value, json!("bob"));
+    assert_eq!(dump.spans[2].value, json!("deadbeef"));
+    assert_eq!(dump.spans[6].value, json!("Active"));
+    assert_eq!(dump.spans[7].value, json!(-1));
+}
+
+#[test]
+fn text_test() {
+    let bytes = to_vec(&account()).unwrap();
+    let text = Hexdump::annotate(&bytes, &schema_container_of::<Account>()).to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert_eq!(lines[0], "  00000000  03 00 00 00 62 6f 62                             owner: String = \"bob\"");
+    assert_eq!(lines[3], "  00000013  02 00 00 00                                      lots: length of Vec<u16> = 2");
+    assert_eq!(lines[6], "  0000001b  00                                               status: variant of Status = Active");
+    assert_eq!(lines[8], "  00000024  00                                               delegate: variant of Option<u32> = None");
+}
+
+#[test]
+fn failure_test() {
+    let mut bytes = to_vec(&account()).unwrap();
+    // An unknown variant of Status
+    bytes[27] = 7;
+    let dump = Hexdump::annotate(&bytes, &schema_container_of::<Account>());
+    assert_eq!(dump.spans.len(), 6);
+    let failure = dump.failure.as_ref().unwrap();
+    assert_eq!((failure.offset, failure.path.as_str(), failure.expected.as_str()), (27, "status", "variant of Status"));
+    assert_eq!(failure.reason, "unknown discriminant 7");
+    assert_eq!(
+        dump.to_text().lines().last().unwrap(),
+        "!!0000001b  07 ff ff ff ff ff ff ff ff 00                    status: expected variant of Status: unknown discriminant 7"
+    );
+
+    let bytes = to_vec(&account()).unwrap();
+    let dump = Hexdump::annotate(&bytes[..12], &schema_container_of::<Account>());
+    let failure = dump.failure.unwrap();
+    assert_eq!((failure.offset, failure.path.as_str(), failure.expected.as_str()), (7, "balance", "u64"));
+    assert_eq!(failure.reason, "needs 8 bytes but 5 remain");
+
+    let mut bytes = to_vec(&account()).unwrap();
+    bytes[19] = 200;
+    let failure = Hexdump::annotate(&bytes, &schema_container_of::<Account>()).failure.unwrap();
+    assert_eq!((failure.offset, failure.expected.as_str()), (19, "Vec<u16>"));
+    assert_eq!(failure.reason, "200 u16 need more than the 14 bytes remaining");
+
+    let failure = Hexdump::annotate(&[2, 0, 0, 0, 0xff, 0xfe], &schema_container_of::<String>()).failure.unwrap();
+    assert_eq!((failure.offset, failure.path.as_str(), failure.reason.as_str()), (0, "", "02000000fffe is not a valid String"));
+    assert!(Hexdump::annotate(&[2], &schema_container_of::<bool>()).to_text().starts_with("!!00000000  02"));
+}
+
+#[test]
+fn trailing_and_json_test() {
+    let mut bytes = to_vec(&(7u8, vec![1u8, 2])).unwrap();
+    bytes.extend([9, 9]);
+    let dump = Hexdump::annotate(&bytes, &schema_container_of::<(u8, Vec<u8>)>());
+    assert_eq!(dump.trailing, Some(7));
+    assert!(dump.to_text().ends_with("  00000007  09 09                                            2 bytes left over\n"));
+
+    assert_eq!(dump.to_json(), json!({
+        "spans": [
+            {"start": 0, "end": 1, "path": "[0]", "declaration": "u8", "kind": "value", "value": 7},
+            {"start": 1, "end": 5, "path": "[1]", "declaration": "Vec<u8>", "kind": "length", "value": 2},
+            {"start": 5, "end": 7, "path": "[1]", "declaration": "Vec<u8>", "kind": "value", "value": "0102"}
+        ],
+        "failure": null,
+        "trailing": 7,
+        "bytes": "070200000001020909"
+    }));
+}
+
+#[cfg(feature = "generator")]
+#[test]
+fn agrees_with_deserializer_test() {
+    use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+    use borsh_serde_adapter::value_generator::{random_schema, GeneratorLimits, ValueGenerator};
+
+    for seed in 0..100 {
+        let container = random_schema(seed, 4);
+        let mut generator = ValueGenerator::new(&container, seed, GeneratorLimits::default());
+        let (_, bytes) = generator.generate().unwrap();
+        for cut in (0..=bytes.len()).step_by(bytes.len() / 32 + 1) {
+            let mut mutated = bytes[..cut].to_vec();
+            if cut < bytes.len() {
+                mutated.extend(bytes[cut..].iter().map(|byte| byte ^ 0x5a));
+            }
+            let dump = Hexdump::annotate(&mutated, &container);
+            let decoded = deserialize_from_schema(&mut mutated.as_slice(), &container);
+            assert_eq!(dump.failure.is_none(), decoded.is_ok(), "Seed {seed}, cut {cut}: {:?}", dump.failure);
+            let covered = dump.spans.last().map_or(0, |span| span.end);
+            assert!(dump.spans.windows(2).all(|pair| pair[0].end <= pair[1].start), "Seed {seed}");
+            assert!(covered <= mutated.len());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 #![recursion_limit = "256"]
+#![allow(clippy::len_zero, clippy::let_unit_value, clippy::to_string_in_format_args, clippy::bool_assert_comparison, clippy::type_complexity)]
 
 use std::fs::File;
 use std::io::{BufReader, Write};
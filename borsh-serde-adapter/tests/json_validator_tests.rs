@@ -0,0 +1,81 @@
+use borsh::{BorshSchema, schema_container_of};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::errors::{ExpectationError, ViolationReason};
+use borsh_serde_adapter::json_validator::{validate, Violation};
+use serde_json::json;
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub enum Side {
+    Buy(u64),
+    Sell(u64),
+}
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub struct Order {
+    owner: String,
+    price: u32,
+    offset: i8,
+    amount: u128,
+    key: [u8; 4],
+    tags: Vec<String>,
+    side: Side,
+}
+
+fn violation(path: &str, reason: ViolationReason) -> Violation {
+    Violation {
+        path: path.to_string(),
+        reason,
+    }
+}
+
+#[test]
+fn valid_value_has_no_violations() {
+    let container: BorshSchemaContainer = schema_container_of::<Order>();
+    let order = json!({
+        "owner": "John",
+        "price": 10,
+        "offset": -1,
+        "amount": "340282366920938463463374607431768211455",
+        "key": [1, 2, 3, 4],
+        "tags": ["a", "b"],
+        "side": {"Buy": 5}
+    });
+
+    assert!(validate(&order, &container).is_empty());
+}
+
+#[test]
+fn all_violations_are_collected() {
+    let container: BorshSchemaContainer = schema_container_of::<Order>();
+    let order = json!({
+        "price": 4294967296u64,
+        "offset": -129,
+        "amount": "-1",
+        "key": [1, 2, 300],
+        "tags": ["a", 1],
+        "side": {"Hold": 5}
+    });
+
+    let violations = validate(&order, &container);
+    assert_eq!(violations, vec![
+        violation("/owner", ViolationReason::MissingProperty("owner".to_string())),
+        violation("/price", ViolationReason::OutOfRange("4294967296".to_string(), "u32".to_string())),
+        violation("/offset", ViolationReason::OutOfRange("-129".to_string(), "i8".to_string())),
+        violation("/amount", ViolationReason::OutOfRange("-1".to_string(), "u128".to_string())),
+        violation("/key", ViolationReason::WrongLength(4, 3)),
+        violation("/key/2", ViolationReason::OutOfRange("300".to_string(), "u8".to_string())),
+        violation("/tags/1", ViolationReason::Expected(ExpectationError::String)),
+        violation("/side", ViolationReason::UnknownVariant("Hold".to_string())),
+    ]);
+    assert_eq!(violations[4].to_string(), "/key: Expected array of length 4, found length 3");
+}
+
+#[test]
+fn wrong_root_type_is_reported() {
+    let container: BorshSchemaContainer = schema_container_of::<Order>();
+
+    let violations = validate(&json!([1, 2, 3]), &container);
+    assert_eq!(violations, vec![violation("", ViolationReason::Expected(ExpectationError::Object))]);
+}
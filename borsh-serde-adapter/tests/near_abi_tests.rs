@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::errors::NearAbiError;
+use borsh_serde_adapter::near_abi::{schema_from_abi_json, NearAbi};
+use serde_json::{json, Value};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum Level {
+    Low,
+    High { priority: u8 },
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct StatusMessage {
+    message: String,
+    level: Level,
+    updated: u64,
+    tags: Vec<String>,
+    checksum: [u8; 4],
+}
+
+#[derive(BorshSerialize)]
+pub struct SetStatusArgs {
+    message: String,
+    level: Level,
+}
+
+#[derive(BorshSerialize)]
+pub struct Entry {
+    owner: String,
+    delta: i32,
+    note: Option<String>,
+    hash: [u8; 4],
+}
+
+fn load_abi(file_name: &str) -> NearAbi {
+    let file = File::open(format!("./tests/abi/{file_name}")).unwrap();
+    let abi: Value = serde_json::from_reader(BufReader::new(file)).expect("Reading the ABI failed");
+    NearAbi::from_json(&abi).expect("Importing the ABI failed")
+}
+
+#[test]
+fn function_names_test() {
+    let abi = load_abi("status_message_abi.json");
+    assert_eq!(abi.function_names().collect::<Vec<_>>(), ["add", "clear", "get_status", "set_status"]);
+    assert!(abi.has_borsh_args("set_status"));
+    assert!(abi.has_borsh_args("clear"));
+    assert!(!abi.has_borsh_args("add"));
+}
+
+#[test]
+fn decode_and_encode_args_test() {
+    let abi = load_abi("status_message_abi.json");
+    let args = SetStatusArgs { message: "hello".to_string(), level: Level::High { priority: 3 } };
+    let data = to_vec(&args).unwrap();
+
+    let value = abi.decode_args("set_status", &data).unwrap();
+    assert_eq!(value, json!({"message": "hello", "level": {"High": {"priority": 3}}}));
+    assert_eq!(abi.encode_args("set_status", &value).unwrap(), data);
+
+    assert_eq!(abi.decode_args("get_status", &to_vec(&"alice.near".to_string()).unwrap()).unwrap(), json!({"account_id": ["alice.near"]}));
+    assert_eq!(abi.decode_args("clear", &[]).unwrap(), json!([]));
+    assert_eq!(abi.encode_args("clear", &json!([])).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn decode_result_test() {
+    let abi = load_abi("status_message_abi.json");
+    assert_eq!(abi.result_schema("get_status").unwrap(), schema_container_of::<Option<StatusMessage>>());
+
+    let status = StatusMessage {
+        message: "away".to_string(),
+        level: Level::Low,
+        updated: 42,
+        tags: vec!["a".to_string()],
+        checksum: [1, 2, 3, 4],
+    };
+    let value = abi.decode_result("get_status", &to_vec(&Some(status)).unwrap()).unwrap();
+    assert_eq!(value, json!({"Some": {"message": "away", "level": {"Low": []}, "updated": 42, "tags": ["a"], "checksum": [1, 2, 3, 4]}}));
+    assert_eq!(abi.decode_result("get_status", &[0]).unwrap(), json!({"None": null}));
+}
+
+#[test]
+fn legacy_abi_test() {
+    let abi = load_abi("counter_legacy_abi.json");
+    assert_eq!(abi.decode_args("increment", &[5]).unwrap(), json!({"by": 5}));
+    assert_eq!(abi.decode_args("get_history", &to_vec(&"bob.near".to_string()).unwrap()).unwrap(), json!({"owner": "bob.near"}));
+
+    let history = vec![
+        Entry { owner: "bob.near".to_string(), delta: -2, note: Some("refund".to_string()), hash: [9, 8, 7, 6] },
+        Entry { owner: "eve.near".to_string(), delta: 1, note: None, hash: [0; 4] },
+    ];
+    let value = abi.decode_result("get_history", &to_vec(&history).unwrap()).unwrap();
+    assert_eq!(value[0], json!({"owner": "bob.near", "delta": -2, "note": {"Some": "refund"}, "hash": [9, 8, 7, 6]}));
+    assert_eq!(value[1]["note"], json!({"None": null}));
+}
+
+#[test]
+fn errors_test() {
+    let abi = load_abi("status_message_abi.json");
+    assert_eq!(abi.args_schema("add").unwrap_err().to_string(), "Function add takes JSON arguments");
+    assert_eq!(abi.result_schema("add").unwrap_err().to_string(), "Function add returns JSON");
+    assert_eq!(abi.result_schema("set_status").unwrap_err().to_string(), "Function set_status returns nothing");
+    assert_eq!(abi.decode_args("transfer", &[]).unwrap_err().to_string(), "Function transfer not found");
+    assert_eq!(abi.decode_args("clear", &[0]).unwrap_err().to_string(), "1 bytes left over after decoding");
+    assert!(matches!(abi.decode_args("set_status", &[1]).unwrap_err(), NearAbiError::Io(_)));
+    assert!(matches!(abi.encode_args("set_status", &json!({"message": "hi"})).unwrap_err(), NearAbiError::Encode(_)));
+
+    let error = NearAbi::from_json(&json!({"schema_version": "0.4.0"})).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid ABI: body.functions is not a list");
+    let error = schema_from_abi_json(&json!({"declaration": "A", "definitions": {"A": {"Struct": {}}}})).unwrap_err();
+    assert!(matches!(error, NearAbiError::Invalid(_)), "{error}");
+}
+
+#[test]
+fn conflicting_definitions_test() {
+    let arg = |definition: Value| json!({"name": "x", "type_schema": {"declaration": "Point", "definitions": {"Point": definition}}});
+    let abi = NearAbi::from_json(&json!({"body": {"functions": [{
+        "name": "move_to",
+        "kind": "call",
+        "params": {"serialization_type": "borsh", "args": [
+            arg(json!({"Struct": {"fields": {"UnnamedFields": ["u8"]}}})),
+            arg(json!({"Struct": {"fields": {"UnnamedFields": ["u16"]}}}))
+        ]}
+    }]}}))
+    .unwrap();
+    assert_eq!(abi.args_schema("move_to").unwrap_err().to_string(), "Point has conflicting definitions in the arguments of move_to");
+}
@@ -0,0 +1,178 @@
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::{deserialize_from_schema, deserialize_from_schema_with_max_depth};
+use borsh_serde_adapter::errors::ViolationReason;
+use borsh_serde_adapter::json_validator::{validate, validate_with_max_depth};
+use borsh_serde_adapter::serialize_adapter::{serialize_serde_json_to_borsh, serialize_serde_json_to_borsh_with_max_depth};
+use serde_json::json;
+
+#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum Expr {
+    Literal { value: i64 },
+    Neg { operand: Box<Expr> },
+    Add { left: Box<Expr>, right: Box<Expr> },
+}
+
+fn chain(depth: usize) -> Node {
+    let mut node = Node { value: 0, children: vec![] };
+    for value in 1..depth {
+        node = Node { value: (value % 256) as u8, children: vec![node] };
+    }
+    node
+}
+
+fn negations(depth: usize) -> Expr {
+    let mut expr = Expr::Literal { value: 7 };
+    for _ in 0..depth {
+        expr = Expr::Neg { operand: Box::new(expr) };
+    }
+    expr
+}
+
+/// Small xorshift generator so the adversarial inputs are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+#[test]
+fn recursive_tree_round_trips() {
+    let tree = Node {
+        value: 1,
+        children: vec![
+            Node { value: 2, children: vec![] },
+            Node { value: 3, children: vec![Node { value: 4, children: vec![] }] },
+        ],
+    };
+    let container: BorshSchemaContainer = schema_container_of::<Node>();
+    let tree_ser = to_vec(&tree).expect("Error trying to serialize Node");
+
+    let result = deserialize_from_schema(&mut tree_ser.as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(result, json!({
+        "value": 1,
+        "children": [
+            {"value": 2, "children": []},
+            {"value": 3, "children": [{"value": 4, "children": []}]}
+        ]
+    }));
+
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &result, &container).expect("Serialization failed");
+    assert_eq!(writer, tree_ser);
+}
+
+#[test]
+fn deep_but_legitimate_tree_decodes() {
+    // Each level of the chain is a struct and a sequence, so 500 levels are 1000 nested values
+    let tree = chain(500);
+    let container: BorshSchemaContainer = schema_container_of::<Node>();
+    let tree_ser = to_vec(&tree).expect("Error trying to serialize Node");
+
+    let result = deserialize_from_schema(&mut tree_ser.as_slice(), &container).expect("Deserialization failed");
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &result, &container).expect("Serialization failed");
+    assert_eq!(writer, tree_ser);
+}
+
+#[test]
+fn max_depth_is_enforced() {
+    let container: BorshSchemaContainer = schema_container_of::<Node>();
+    let tree_ser = to_vec(&chain(10)).expect("Error trying to serialize Node");
+
+    assert!(deserialize_from_schema_with_max_depth(&mut tree_ser.as_slice(), &container, 20).is_ok());
+    let error = deserialize_from_schema_with_max_depth(&mut tree_ser.as_slice(), &container, 19).expect_err("Depth should be exceeded");
+    assert_eq!(error.to_string(), "Maximum nesting depth of 19 exceeded");
+
+    let value = deserialize_from_schema(&mut tree_ser.as_slice(), &container).expect("Deserialization failed");
+    let mut writer = Vec::new();
+    assert!(serialize_serde_json_to_borsh_with_max_depth(&mut writer, &value, &container, 20).is_ok());
+    let error = serialize_serde_json_to_borsh_with_max_depth(&mut Vec::new(), &value, &container, 19).expect_err("Depth should be exceeded");
+    assert_eq!(error.to_string(), "Maximum nesting depth of 19 exceeded");
+
+    assert_eq!(validate_with_max_depth(&value, &container, 20), []);
+    let violations = validate_with_max_depth(&value, &container, 19);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].reason, ViolationReason::TooDeep(19));
+}
+
+#[test]
+fn hostile_nesting_fails_without_overflowing_the_stack() {
+    let container: BorshSchemaContainer = schema_container_of::<Expr>();
+
+    // A million Neg tags, each one byte, would need a million native stack frames with a recursive decoder
+    let mut hostile = vec![1u8; 1_000_000];
+    hostile.push(0);
+    hostile.extend_from_slice(&7i64.to_le_bytes());
+    let error = deserialize_from_schema(&mut hostile.as_slice(), &container).expect_err("Depth should be exceeded");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // The same shape within the limit decodes
+    let expr_ser = to_vec(&negations(300)).expect("Error trying to serialize Expr");
+    let result = deserialize_from_schema(&mut expr_ser.as_slice(), &container).expect("Deserialization failed");
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &result, &container).expect("Serialization failed");
+    assert_eq!(writer, expr_ser);
+
+    // Values nested past the limit are reported by the validator, which doesn't recurse either
+    let expr_ser = to_vec(&negations(600)).expect("Error trying to serialize Expr");
+    let value = deserialize_from_schema_with_max_depth(&mut expr_ser.as_slice(), &container, 2_000).expect("Deserialization failed");
+    let violations = validate(&value, &container);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].reason, ViolationReason::TooDeep(1024));
+    assert_eq!(validate_with_max_depth(&value, &container, 2_000), []);
+}
+
+#[test]
+fn adversarial_inputs_never_panic() {
+    let mut rng = XorShift(0x5eed_1234_abcd_0001);
+    let container: BorshSchemaContainer = schema_container_of::<Expr>();
+
+    for _ in 0..2000 {
+        let mut input = Vec::new();
+        let length = rng.below(96) as usize;
+        for _ in 0..length {
+            // Keep most bytes valid tags so that decoding goes deep before running out of input
+            let byte = if rng.below(4) == 0 { rng.next() as u8 } else { rng.below(3) as u8 };
+            input.push(byte);
+        }
+
+        let expected = Expr::try_from_slice(&input).is_ok();
+        let mut buffer = input.as_slice();
+        let result = deserialize_from_schema(&mut buffer, &container);
+        if expected {
+            let value = result.expect("Input accepted by the derived decoder should be accepted");
+            assert!(buffer.is_empty());
+            let mut writer = Vec::new();
+            serialize_serde_json_to_borsh(&mut writer, &value, &container).expect("Serialization failed");
+            assert_eq!(writer, input);
+        }
+    }
+}
+
+#[test]
+fn failed_decoding_leaves_the_buffer_where_reading_stopped() {
+    let schema = schema_container_of::<Node>();
+    let data = to_vec(&Node { value: 1, children: vec![chain(2)] }).unwrap();
+    let truncated = &data[..data.len() - 2];
+
+    let mut buffer = truncated;
+    deserialize_from_schema(&mut buffer, &schema).expect_err("Deserialization should fail");
+    // The inner node's children length was read before the 3 bytes left proved too few for a child
+    assert_eq!(buffer.len(), 3);
+}
@@ -0,0 +1,95 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::deserialize_adapter::{
+    deserialize_from_schema, deserialize_from_schema_partial, deserialize_from_schema_partial_with_limits, DecodeLimits, PARTIAL_ERROR_KEY,
+};
+use serde_json::{json, Value};
+
+mod common;
+use common::Status;
+
+/// An account ending in a string, so that truncation can cut a field after the enum
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balance: u64,
+    lots: Vec<u16>,
+    status: Status,
+    memo: String,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "bob".to_string(),
+        balance: 10,
+        lots: vec![1, 2, 3],
+        status: Status::Active { since: 5 },
+        memo: "hi".to_string(),
+    }
+}
+
+fn marker(value: &Value) -> &Value {
+    &value[PARTIAL_ERROR_KEY]
+}
+
+#[test]
+fn complete_value_test() {
+    let bytes = to_vec(&account()).unwrap();
+    let partial = deserialize_from_schema_partial(&mut bytes.as_slice(), &schema_container_of::<Account>());
+    assert_eq!(partial.failed_at, None);
+    assert_eq!(partial.value, deserialize_from_schema(&mut bytes.as_slice(), &schema_container_of::<Account>()).unwrap());
+}
+
+#[test]
+fn truncated_test() {
+    let bytes = to_vec(&account()).unwrap();
+    let mut buffer = &bytes[..bytes.len() - 1];
+    let partial = deserialize_from_schema_partial(&mut buffer, &schema_container_of::<Account>());
+    assert_eq!(partial.failed_at, Some(34));
+    assert_eq!(buffer, &[2, 0, 0, 0, b'h']);
+    assert_eq!(partial.value, json!({
+        "owner": "bob",
+        "balance": 10,
+        "lots": [1, 2, 3],
+        "status": {"Active": {"since": 5}},
+        "memo": {PARTIAL_ERROR_KEY: {"error": "String of 2 bytes needs more than the 1 bytes remaining", "expected": "String", "offset": 34, "remaining": "0200000068"}},
+    }));
+
+    // The length of a sequence is checked against the bytes left before any element is read
+    let partial = deserialize_from_schema_partial(&mut &bytes[..24], &schema_container_of::<Account>());
+    assert_eq!(partial.failed_at, Some(15));
+    assert_eq!(marker(&partial.value["lots"])["error"], "Sequence of 3 u16 needs more than the 5 bytes remaining");
+}
+
+#[test]
+fn corrupted_enum_test() {
+    let mut bytes = to_vec(&account()).unwrap();
+    bytes[25] = 9;
+    let partial = deserialize_from_schema_partial(&mut bytes.as_slice(), &schema_container_of::<Account>());
+    assert_eq!(partial.failed_at, Some(25));
+    assert_eq!(partial.value["lots"], json!([1, 2, 3]));
+    assert_eq!(marker(&partial.value["status"])["error"], "Unknown discriminant 9 for Status");
+    assert_eq!(marker(&partial.value["status"])["expected"], "Status");
+    assert!(partial.value.get("memo").is_none());
+
+    // A failure inside the variant keeps the variant
+    let bytes = to_vec(&account()).unwrap();
+    let partial = deserialize_from_schema_partial(&mut &bytes[..30], &schema_container_of::<Account>());
+    assert_eq!(marker(&partial.value["status"]["Active"]["since"])["expected"], "i64");
+}
+
+#[test]
+fn root_failure_test() {
+    let partial = deserialize_from_schema_partial(&mut [1u8, 2].as_slice(), &schema_container_of::<u64>());
+    assert_eq!(partial.failed_at, Some(0));
+    assert_eq!(marker(&partial.value)["remaining"], "0102");
+}
+
+#[test]
+fn limits_test() {
+    let bytes = to_vec(&account()).unwrap();
+    let limits = DecodeLimits { max_sequence_length: 2, ..DecodeLimits::default() };
+    let partial = deserialize_from_schema_partial_with_limits(&mut bytes.as_slice(), &schema_container_of::<Account>(), &limits);
+    assert_eq!(partial.failed_at, Some(15));
+    assert_eq!(marker(&partial.value["lots"])["error"], "Sequence length 3 exceeds the maximum of 2");
+    assert_eq!(partial.value["balance"], 10);
+}
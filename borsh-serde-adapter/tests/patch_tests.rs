@@ -0,0 +1,154 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::patch::patch_from_schema;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum AssetKind {
+    Native,
+    Token { decimals: u8 },
+    Nft { id: String },
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Balance {
+    mint: String,
+    amount: u64,
+    kind: AssetKind,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Portfolio {
+    owner: String,
+    balances: Vec<Balance>,
+    history: Vec<u64>,
+    settings: (bool, String),
+    nonce: u32,
+}
+
+fn portfolio() -> Portfolio {
+    Portfolio {
+        owner: "alice".to_string(),
+        balances: vec![
+            Balance { mint: "sol".to_string(), amount: 5, kind: AssetKind::Native },
+            Balance { mint: "usdc".to_string(), amount: 100, kind: AssetKind::Token { decimals: 6 } },
+            Balance { mint: "ape".to_string(), amount: 1, kind: AssetKind::Nft { id: "ape-42".to_string() } },
+        ],
+        history: vec![10, 20, 30],
+        settings: (true, "dark".to_string()),
+        nonce: 7,
+    }
+}
+
+/// Applies the edits to the decoded JSON and serializes it again, which is what a patch must be equivalent to
+fn round_trip(bytes: &[u8], container: &BorshSchemaContainer, edits: &[(&str, serde_json::Value)]) -> Vec<u8> {
+    let mut value = deserialize_from_schema(&mut &bytes[..], container).expect("Deserialization failed");
+    for (path, new_value) in edits {
+        let pointer = format!("/{}", path.replace(['.', '['], "/").replace(']', "")).replace("//", "/");
+        let pointer = pointer.trim_end_matches('/').to_string();
+        *value.pointer_mut(&pointer).expect("Path should exist in the value") = new_value.clone();
+    }
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &value, container).expect("Serialization failed");
+    writer
+}
+
+#[test]
+fn fixed_size_fields_are_rewritten_in_place() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let original = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let edits = [("nonce", json!(8)), ("balances[1].amount", json!(250)), ("settings[0]", json!(false))];
+
+    let mut bytes = original.clone();
+    patch_from_schema(&mut bytes, &container, &edits).expect("Patch failed");
+    assert_eq!(bytes.len(), original.len());
+    assert_eq!(bytes, round_trip(&original, &container, &edits));
+}
+
+#[test]
+fn variable_length_edits_shift_later_bytes() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let original = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let edits = [
+        ("owner", json!("bartholomew")),
+        ("balances[0].mint", json!("")),
+        ("balances[2].kind", json!({"Token": {"decimals": 0}})),
+        ("history", json!([1, 2, 3, 4, 5])),
+        ("settings[1]", json!("a much longer theme name")),
+    ];
+
+    let mut bytes = original.clone();
+    patch_from_schema(&mut bytes, &container, &edits).expect("Patch failed");
+    assert_eq!(bytes, round_trip(&original, &container, &edits));
+
+    let value = deserialize_from_schema(&mut bytes.as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(value["owner"], json!("bartholomew"));
+    assert_eq!(value["balances"][2]["kind"], json!({"Token": {"decimals": 0}}));
+    assert_eq!(value["nonce"], json!(7));
+}
+
+#[test]
+fn later_edits_see_earlier_ones() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let original = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+
+    // The second edit goes through the variant written by the first, and the last into an element added by replacing
+    // the whole value
+    let edits = [
+        ("balances[0].kind", json!({"Nft": {"id": "x"}})),
+        ("balances[0].kind.Nft.id", json!("punk-1")),
+        ("history", json!([])),
+        ("", json!({
+            "owner": "carol",
+            "balances": [],
+            "history": [1],
+            "settings": [false, ""],
+            "nonce": 0
+        })),
+        ("history[0]", json!(99)),
+    ];
+    let mut bytes = original.clone();
+    patch_from_schema(&mut bytes, &container, &edits).expect("Patch failed");
+
+    let value = deserialize_from_schema(&mut bytes.as_slice(), &container).expect("Deserialization failed");
+    assert_eq!(value, json!({
+        "owner": "carol",
+        "balances": [],
+        "history": [99],
+        "settings": [false, ""],
+        "nonce": 0
+    }));
+}
+
+#[test]
+fn failed_patches_leave_bytes_unchanged() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let original = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let mut bytes = original.clone();
+
+    // An inactive variant is only found once earlier edits are applied, so those are undone
+    let error = patch_from_schema(&mut bytes, &container, &[
+        ("owner", json!("bob")),
+        ("balances[0].kind.Token.decimals", json!(2)),
+    ]).expect_err("Patch should fail");
+    assert_eq!(error.to_string(), "Can't patch balances[0].kind.Token.decimals: Variant Token is not the active variant Native");
+    assert_eq!(bytes, original);
+
+    let error = patch_from_schema(&mut bytes, &container, &[("owner", json!("bob")), ("balances[7].amount", json!(1))])
+        .expect_err("Patch should fail");
+    assert!(error.to_string().starts_with("Can't patch balances[7].amount"));
+    assert_eq!(bytes, original);
+
+    let error = patch_from_schema(&mut bytes, &container, &[("nonce", json!("eight"))]).expect_err("Patch should fail");
+    assert_eq!(error.to_string(), "Invalid value for nonce: Expected number");
+    assert_eq!(bytes, original);
+
+    let error = patch_from_schema(&mut bytes, &container, &[("balances[*].amount", json!(0))]).expect_err("Patch should fail");
+    assert_eq!(error.to_string(), "Invalid path balances[*].amount: [*] can't be used in a patch");
+
+    let error = patch_from_schema(&mut bytes, &container, &[("balance", json!(0))]).expect_err("Patch should fail");
+    assert_eq!(error.to_string(), "Path balance does not exist: Portfolio has no field or variant balance");
+    assert_eq!(bytes, original);
+}
@@ -0,0 +1,177 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::{deserialize_from_schema, DecodeLimits};
+use borsh_serde_adapter::errors::PathError;
+use borsh_serde_adapter::projection::{project_from_schema, project_from_schema_with_limits, Projection};
+use borsh_serde_adapter::schema_path::{format_path, parse_path, PathSegment};
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum AssetKind {
+    Native,
+    Token { decimals: u8 },
+    Nft(String),
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Balance {
+    mint: String,
+    amount: u64,
+    kind: AssetKind,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Portfolio {
+    owner: String,
+    balances: Vec<Balance>,
+    history: Vec<u64>,
+    settings: (bool, String),
+}
+
+fn portfolio() -> Portfolio {
+    Portfolio {
+        owner: "alice".to_string(),
+        balances: vec![
+            Balance { mint: "sol".to_string(), amount: 5, kind: AssetKind::Native },
+            Balance { mint: "usdc".to_string(), amount: 100, kind: AssetKind::Token { decimals: 6 } },
+            Balance { mint: "ape".to_string(), amount: 1, kind: AssetKind::Nft("ape-42".to_string()) },
+            Balance { mint: "bonk".to_string(), amount: 9, kind: AssetKind::Token { decimals: 5 } },
+        ],
+        history: vec![10, 20, 30],
+        settings: (true, "dark".to_string()),
+    }
+}
+
+#[test]
+fn selected_paths_are_projected() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+
+    let mut buffer = portfolio_ser.as_slice();
+    let result = project_from_schema(&mut buffer, &container, &["owner", "balances[*].mint"]).expect("Projection failed");
+    assert_eq!(result, json!({
+        "owner": "alice",
+        "balances": [{"mint": "sol"}, {"mint": "usdc"}, {"mint": "ape"}, {"mint": "bonk"}]
+    }));
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn indices_and_wildcards_are_merged() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let projection = Projection::new(&container, &["balances[1].amount", "balances[*].mint", "history[2]", "settings[1]"]).expect("Paths should exist");
+
+    let result = projection.project(&mut portfolio_ser.as_slice()).expect("Projection failed");
+    assert_eq!(result, json!({
+        "balances": [{"mint": "sol"}, {"mint": "usdc", "amount": 100}, {"mint": "ape"}, {"mint": "bonk"}],
+        "history": [30],
+        "settings": ["dark"]
+    }));
+}
+
+#[test]
+fn variant_segments_filter_values() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+
+    let result = project_from_schema(&mut portfolio_ser.as_slice(), &container, &["balances[*].kind.Token.decimals"]).expect("Projection failed");
+    assert_eq!(result, json!({
+        "balances": [{"kind": {"Token": {"decimals": 6}}}, {"kind": {"Token": {"decimals": 5}}}]
+    }));
+
+    // Other selected fields keep elements whose variant doesn't match
+    let result = project_from_schema(&mut portfolio_ser.as_slice(), &container, &["balances[*].kind.Nft", "balances[*].amount"]).expect("Projection failed");
+    assert_eq!(result, json!({
+        "balances": [{"amount": 5}, {"amount": 100}, {"amount": 1, "kind": {"Nft": ["ape-42"]}}, {"amount": 9}]
+    }));
+}
+
+#[test]
+fn whole_subtrees_match_full_decode() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let expected = deserialize_from_schema(&mut portfolio_ser.as_slice(), &container).expect("Deserialization failed");
+
+    let result = project_from_schema(&mut portfolio_ser.as_slice(), &container, &["balances", "settings"]).expect("Projection failed");
+    assert_eq!(result, json!({"balances": expected["balances"], "settings": expected["settings"]}));
+
+    let result = project_from_schema(&mut portfolio_ser.as_slice(), &container, &[""]).expect("Projection failed");
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn indices_past_the_end_are_left_out() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+
+    let mut buffer = portfolio_ser.as_slice();
+    let result = project_from_schema(&mut buffer, &container, &["history[5]", "balances[7].mint", "balances[0].mint"]).expect("Projection failed");
+    assert_eq!(result, json!({"balances": [{"mint": "sol"}], "history": []}));
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn limits_are_enforced() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let error = |paths: &[&str], limits: DecodeLimits| {
+        project_from_schema_with_limits(&mut portfolio_ser.as_slice(), &container, paths, &limits)
+            .expect_err("Projection should fail")
+            .to_string()
+    };
+
+    // Skipped bytes are read too
+    let limits = DecodeLimits { max_total_bytes: portfolio_ser.len() - 1, ..DecodeLimits::default() };
+    assert_eq!(error(&["owner"], limits), format!("Maximum of {} bytes exceeded", portfolio_ser.len() - 1));
+    let limits = DecodeLimits { max_sequence_length: 3, ..DecodeLimits::default() };
+    assert_eq!(error(&["balances[0].mint"], limits), "Sequence length 4 exceeds the maximum of 3");
+    let limits = DecodeLimits { max_string_length: 3, ..DecodeLimits::default() };
+    assert_eq!(error(&["owner"], limits), "String length 5 exceeds the maximum of 3");
+    let limits = DecodeLimits { max_depth: 2, ..DecodeLimits::default() };
+    assert_eq!(error(&["balances[*].kind"], limits), "Maximum nesting depth of 2 exceeded");
+    let limits = DecodeLimits { max_output_nodes: 4, ..DecodeLimits::default() };
+    assert_eq!(error(&["balances[*].mint"], limits), "Maximum of 4 output nodes exceeded");
+
+    let limits = DecodeLimits { max_depth: 5, max_output_nodes: 7, ..DecodeLimits::default() };
+    let result = project_from_schema_with_limits(&mut portfolio_ser.as_slice(), &container, &["owner", "balances[1].kind"], &limits)
+        .expect("Projection failed");
+    assert_eq!(result, json!({"owner": "alice", "balances": [{"kind": {"Token": {"decimals": 6}}}]}));
+}
+
+#[test]
+fn paths_missing_from_the_schema_fail() {
+    let container: BorshSchemaContainer = schema_container_of::<Portfolio>();
+    let error = |path: &str| Projection::new(&container, &[path]).expect_err("Path should not exist");
+
+    assert_eq!(error("balances[*].missing"), PathError::NoSuchField("balances[*].missing".to_string(), "Balance".to_string(), "missing".to_string()));
+    assert_eq!(error("owner[0]").to_string(), "Path owner[0] does not exist: String can't be indexed");
+    assert_eq!(error("settings[*]").to_string(), "Path settings[*] does not exist: (bool, String) is not a sequence, so it can't be indexed with [*]");
+    assert_eq!(error("settings[2]").to_string(), "Path settings[2] does not exist: index 2 is out of bounds for (bool, String)");
+    assert_eq!(error("balances[").to_string(), "Invalid path balances[: missing ]");
+
+    let portfolio_ser = to_vec(&portfolio()).expect("Error trying to serialize Portfolio");
+    let error = project_from_schema(&mut portfolio_ser.as_slice(), &container, &["owner.name"]).expect_err("Path should not exist");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn paths_are_parsed() {
+    assert_eq!(parse_path("balances[*].kind.Token").expect("Path should parse"), vec![
+        PathSegment::Field("balances".to_string()),
+        PathSegment::Wildcard,
+        PathSegment::Field("kind".to_string()),
+        PathSegment::Field("Token".to_string()),
+    ]);
+    assert_eq!(parse_path("[0][1].x").expect("Path should parse"), vec![
+        PathSegment::Index(0),
+        PathSegment::Index(1),
+        PathSegment::Field("x".to_string()),
+    ]);
+    assert_eq!(parse_path("").expect("Path should parse"), vec![]);
+    assert_eq!(format_path(&parse_path("matrix[2][*].value").expect("Path should parse")), "matrix[2][*].value");
+
+    for invalid in ["a..b", "a.", "a[x]", "a[1]b", "a]", ".a"] {
+        assert!(matches!(parse_path(invalid), Err(PathError::Invalid(..))), "{invalid} should be invalid");
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::Read;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::errors::RecordStreamError;
+use borsh_serde_adapter::record_stream::{Framing, RecordReader, RecordStreamOptions};
+use serde_json::{json, Value};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct LogEntry {
+    level: u8,
+    message: String,
+}
+
+fn entry(level: u8, message: &str) -> LogEntry {
+    LogEntry { level, message: message.to_string() }
+}
+
+fn prefixed(bytes: &[u8]) -> Vec<u8> {
+    let mut data = (bytes.len() as u32).to_le_bytes().to_vec();
+    data.extend(bytes);
+    data
+}
+
+fn self_delimiting() -> RecordStreamOptions {
+    RecordStreamOptions { framing: Framing::SelfDelimiting, ..RecordStreamOptions::default() }
+}
+
+/// Hands out at most one byte per read, to exercise records split across reads
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn length_prefixed_test() {
+    let schema = schema_container_of::<LogEntry>();
+    let mut data = prefixed(&to_vec(&entry(1, "started")).unwrap());
+    data.extend(prefixed(&to_vec(&entry(3, "stopped")).unwrap()));
+
+    let mut reader = RecordReader::new(data.as_slice(), &schema, RecordStreamOptions::default());
+    assert_eq!(reader.next().unwrap().unwrap(), json!({"level": 1, "message": "started"}));
+    assert_eq!((reader.index(), reader.offset()), (1, 16));
+    assert_eq!(reader.next().unwrap().unwrap(), json!({"level": 3, "message": "stopped"}));
+    assert!(reader.next().is_none());
+
+    let values: Vec<Value> = RecordReader::new(Trickle(&data), &schema, RecordStreamOptions::default()).map(Result::unwrap).collect();
+    assert_eq!(values.len(), 2);
+}
+
+#[test]
+fn self_delimiting_test() {
+    let schema = schema_container_of::<LogEntry>();
+    let long_message = "x".repeat(20_000);
+    let mut data = to_vec(&entry(1, "started")).unwrap();
+    data.extend(to_vec(&entry(2, &long_message)).unwrap());
+    data.extend(to_vec(&entry(3, "stopped")).unwrap());
+
+    let values: Vec<Value> = RecordReader::new(data.as_slice(), &schema, self_delimiting()).map(Result::unwrap).collect();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[1]["message"], long_message.as_str());
+    assert_eq!(values[2], json!({"level": 3, "message": "stopped"}));
+
+    let values: Vec<Value> = RecordReader::new(Trickle(&data), &schema, self_delimiting()).map(Result::unwrap).collect();
+    assert_eq!(values[2]["level"], 3);
+}
+
+#[test]
+fn skip_corrupt_test() {
+    let schema = schema_container_of::<LogEntry>();
+    let mut data = prefixed(&to_vec(&entry(1, "first")).unwrap());
+    data.extend(prefixed(&[1, 9, 0, 0, 0, b'b']));
+    let mut trailing = to_vec(&entry(2, "extra")).unwrap();
+    trailing.push(0);
+    data.extend(prefixed(&trailing));
+    data.extend(prefixed(&to_vec(&entry(4, "last")).unwrap()));
+
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, RecordStreamOptions::default()).collect();
+    assert_eq!(records.len(), 2);
+    let error = records[1].as_ref().unwrap_err();
+    assert!(matches!(error, RecordStreamError::Invalid(1, 14, _)), "{error}");
+    assert_eq!((error.index(), error.offset()), (1, 14));
+
+    let options = RecordStreamOptions { skip_corrupt: true, ..RecordStreamOptions::default() };
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, options).collect();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[2].as_ref().unwrap_err().to_string(), "Record 2 at byte 24 has 1 bytes left over after decoding");
+    assert_eq!(records[3].as_ref().unwrap(), &json!({"level": 4, "message": "last"}));
+}
+
+#[test]
+fn too_long_test() {
+    let schema = schema_container_of::<LogEntry>();
+    let mut data = prefixed(&to_vec(&entry(1, &"y".repeat(40))).unwrap());
+    data.extend(prefixed(&to_vec(&entry(2, "ok")).unwrap()));
+
+    let options = RecordStreamOptions { max_record_length: 16, skip_corrupt: true, ..RecordStreamOptions::default() };
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, options).collect();
+    assert_eq!(records[0].as_ref().unwrap_err().to_string(), "Record 0 at byte 0 is longer than the maximum of 16 bytes");
+    assert_eq!(records[1].as_ref().unwrap(), &json!({"level": 2, "message": "ok"}));
+
+    let data = to_vec(&entry(1, &"y".repeat(40))).unwrap();
+    let options = RecordStreamOptions { max_record_length: 16, ..self_delimiting() };
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, options).collect();
+    assert_eq!(records.len(), 1);
+    assert!(matches!(records[0], Err(RecordStreamError::Invalid(0, 0, _))));
+}
+
+#[test]
+fn truncated_and_corrupt_streams_end_iteration_test() {
+    let schema = schema_container_of::<LogEntry>();
+    let options = RecordStreamOptions { skip_corrupt: true, ..RecordStreamOptions::default() };
+
+    let mut data = prefixed(&to_vec(&entry(1, "first")).unwrap());
+    data.extend([20, 0, 0, 0, 1, 2]);
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, options.clone()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].as_ref().unwrap_err().to_string(), "Record 1 at byte 14 is truncated");
+
+    let records: Vec<_> = RecordReader::new(&[5u8, 0][..], &schema, options).collect();
+    assert!(matches!(records[..], [Err(RecordStreamError::Truncated(0, 0))]));
+
+    // Without a length prefix a corrupt record ends the iteration even when skipping is asked for
+    let mut data = to_vec(&entry(1, "first")).unwrap();
+    data.extend([1, 2, 0, 0, 0, 0xff, 0xfe]);
+    data.extend(to_vec(&entry(3, "third")).unwrap());
+    let options = RecordStreamOptions { skip_corrupt: true, ..self_delimiting() };
+    let records: Vec<_> = RecordReader::new(data.as_slice(), &schema, options).collect();
+    assert_eq!(records.len(), 2);
+    assert!(matches!(records[1], Err(RecordStreamError::Invalid(1, 10, _))));
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+use borsh_serde_adapter::schema_layout::{field_layout, fixed_size, memcmp_filter, size_range, FieldLayout, SizeBounds, SizeRange};
+use serde_json::json;
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Header {
+    version: u8,
+    authority: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub enum State {
+    Active { since: i64 },
+    Closed { at: i64 },
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Vault {
+    header: Header,
+    amounts: (u64, u64),
+    state: State,
+    flag: bool,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Profile {
+    id: u32,
+    active: bool,
+    name: String,
+    tags: Vec<u16>,
+    score: Option<u64>,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+fn field(path: &str, declaration: &str, offset: usize, size: Option<usize>) -> FieldLayout {
+    FieldLayout {
+        path: path.to_string(),
+        declaration: declaration.to_string(),
+        offset,
+        size,
+    }
+}
+
+#[test]
+fn fixed_size_types_have_an_exact_size() {
+    let container: BorshSchemaContainer = schema_container_of::<Vault>();
+    let vault = Vault {
+        header: Header { version: 1, authority: [7; 32] },
+        amounts: (10, 20),
+        state: State::Closed { at: 5 },
+        flag: true,
+    };
+    let expected = to_vec(&vault).expect("Error trying to serialize Vault").len();
+
+    assert_eq!(fixed_size(&container), Some(expected));
+    assert_eq!(size_range(&container, &SizeBounds::default()), SizeRange { min: 59, max: Some(59) });
+    assert_eq!(size_range(&container, &SizeBounds::default()).exact(), Some(59));
+}
+
+#[test]
+fn variable_size_types_have_a_range() {
+    let container: BorshSchemaContainer = schema_container_of::<Profile>();
+    assert_eq!(fixed_size(&container), None);
+
+    // 4 + 1 + a String and a Vec holding up to u32::MAX bytes and u16s + an Option of a u64
+    let range = size_range(&container, &SizeBounds::default());
+    assert_eq!(range.min, 4 + 1 + 4 + 4 + 1);
+    assert_eq!(range.max, Some(4 + 1 + (4 + u32::MAX as usize) + (4 + 2 * u32::MAX as usize) + 9));
+    assert_eq!(range.exact(), None);
+
+    let bounds = SizeBounds {
+        max_lengths: BTreeMap::from([("String".to_string(), 32)]),
+        default_max_length: Some(10),
+    };
+    let range = size_range(&container, &bounds);
+    assert_eq!(range.max, Some(4 + 1 + (4 + 32) + (4 + 2 * 10) + 9));
+
+    let largest = Profile {
+        id: 1,
+        active: true,
+        name: "x".repeat(32),
+        tags: vec![1; 10],
+        score: Some(3),
+    };
+    assert_eq!(to_vec(&largest).expect("Error trying to serialize Profile").len(), range.max.unwrap());
+}
+
+#[test]
+fn recursive_types_are_unbounded() {
+    let container: BorshSchemaContainer = schema_container_of::<Node>();
+    assert_eq!(size_range(&container, &SizeBounds::default()), SizeRange { min: 5, max: None });
+
+    // Bounding the length of the children doesn't bound the depth
+    let bounds = SizeBounds { max_lengths: BTreeMap::new(), default_max_length: Some(2) };
+    assert_eq!(size_range(&container, &bounds).max, None);
+}
+
+/// A schema of 40 levels, each a tuple of two of the next, ending in the given definition of Level40. Walking every
+/// path to the last level would take 2^40 steps.
+fn shared_levels_schema(last: Definition) -> BorshSchemaContainer {
+    let mut definitions = BTreeMap::new();
+    for level in 0..40 {
+        let next = format!("Level{}", level + 1);
+        definitions.insert(format!("Level{level}"), Definition::Tuple { elements: vec![next.clone(), next] });
+    }
+    definitions.insert("Level40".to_string(), last);
+    BorshSchemaContainer::new("Level0".to_string(), definitions)
+}
+
+#[test]
+fn shared_types_are_sized_once() {
+    let container = shared_levels_schema(Definition::Tuple { elements: vec!["u16".to_string()] });
+    assert_eq!(size_range(&container, &SizeBounds::default()), SizeRange { min: 2 << 40, max: Some(2 << 40) });
+
+    // A recursive enum at the bottom, whose End variant is the smallest way out
+    let container = shared_levels_schema(Definition::Enum {
+        tag_width: 1,
+        variants: vec![(0, "End".to_string(), "Level40__End".to_string()), (1, "More".to_string(), "Level0".to_string())],
+    });
+    let mut definitions: BTreeMap<_, _> = container.definitions().map(|(name, definition)| (name.clone(), definition.clone())).collect();
+    definitions.insert("Level40__End".to_string(), Definition::Struct { fields: Fields::Empty });
+    let container = BorshSchemaContainer::new("Level0".to_string(), definitions);
+    assert_eq!(size_range(&container, &SizeBounds::default()), SizeRange { min: 1 << 40, max: None });
+}
+
+#[test]
+fn fields_at_fixed_offsets_are_listed() {
+    let container: BorshSchemaContainer = schema_container_of::<Vault>();
+    assert_eq!(field_layout(&container), vec![
+        field("header", "Header", 0, Some(33)),
+        field("header.version", "u8", 0, Some(1)),
+        field("header.authority", "[u8; 32]", 1, Some(32)),
+        field("amounts", "(u64, u64)", 33, Some(16)),
+        field("amounts[0]", "u64", 33, Some(8)),
+        field("amounts[1]", "u64", 41, Some(8)),
+        field("state", "State", 49, Some(9)),
+        field("flag", "bool", 58, Some(1)),
+    ]);
+
+    // The layout ends with the first field whose size varies
+    let container: BorshSchemaContainer = schema_container_of::<Profile>();
+    assert_eq!(field_layout(&container), vec![
+        field("id", "u32", 0, Some(4)),
+        field("active", "bool", 4, Some(1)),
+        field("name", "String", 5, None),
+    ]);
+}
+
+#[test]
+fn memcmp_filters_match_serialized_bytes() {
+    let container: BorshSchemaContainer = schema_container_of::<Vault>();
+    let vault = Vault {
+        header: Header { version: 3, authority: [9; 32] },
+        amounts: (10, 20),
+        state: State::Active { since: 100 },
+        flag: false,
+    };
+    let vault_ser = to_vec(&vault).expect("Error trying to serialize Vault");
+
+    for (path, value) in [
+        ("header.authority", json!(vec![9; 32])),
+        ("amounts[1]", json!(20)),
+        ("state", json!({"Active": {"since": 100}})),
+        ("flag", json!(false)),
+    ] {
+        let (offset, bytes) = memcmp_filter(&container, path, &value).expect("Filter failed");
+        assert_eq!(&vault_ser[offset..offset + bytes.len()], bytes.as_slice(), "{path}");
+    }
+
+    let container: BorshSchemaContainer = schema_container_of::<Profile>();
+    let error = memcmp_filter(&container, "tags", &json!([])).expect_err("Filter should fail");
+    assert_eq!(error.to_string(), "tags is not a field at a fixed offset of Profile");
+}
@@ -0,0 +1,183 @@
+#![recursion_limit = "256"]
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::borsh_schema_util::PrimitiveType;
+use borsh_serde_adapter::deserialize_adapter::{deserialize_from_schema, deserialize_from_schema_with_limits, DecodeLimits};
+use borsh_serde_adapter::schema_plan::{PlanNode, SchemaPlan};
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use serde_derive::Serialize;
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub struct Person {
+    first_name: String,
+    last_name: String,
+}
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub enum TestEnum {
+    One(u8),
+    Two(u8),
+    Three(u8),
+}
+
+#[derive(Serialize, BorshSerialize, BorshSchema)]
+pub struct AllTypes {
+    type_u8: u8,
+    type_u16: u16,
+    type_u32: u32,
+    type_u64: u64,
+    type_i8: i8,
+    type_i16: i16,
+    type_i32: i32,
+    type_i64: i64,
+    type_f32: f32,
+    type_f64: f64,
+    type_string: String,
+    type_bool: bool,
+    type_array: [u8; 3],
+    type_sequence: Vec<String>,
+    type_tuple: (u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, String, bool),
+    type_enum: TestEnum,
+    type_struct: Person,
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub struct Header {
+    version: u16,
+    flags: [u8; 3],
+    origin: (i32, i32),
+    kind: TestEnum,
+    label: String,
+}
+
+fn all_types() -> AllTypes {
+    AllTypes {
+        type_u8: u8::MAX,
+        type_u16: u16::MAX,
+        type_u32: u32::MAX,
+        type_u64: u64::MAX,
+        type_i8: i8::MIN,
+        type_i16: i16::MIN,
+        type_i32: i32::MIN,
+        type_i64: i64::MIN,
+        type_f32: f32::MIN,
+        type_f64: f64::MIN,
+        type_string: "abc".to_string(),
+        type_bool: false,
+        type_array: [b'a', b'b', b'c'],
+        type_sequence: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        type_tuple: (u8::MAX, u16::MAX, u32::MAX, u64::MAX, i8::MIN, i16::MIN, i32::MIN, i64::MIN, f32::MAX, f64::MAX, "xyz".to_string(), true),
+        type_enum: TestEnum::One(1),
+        type_struct: Person {
+            first_name: "John".to_string(),
+            last_name: "Doe".to_string(),
+        },
+    }
+}
+
+#[test]
+fn plan_matches_schema_dispatch() {
+    let container: BorshSchemaContainer = schema_container_of::<AllTypes>();
+    let plan = SchemaPlan::compile(&container);
+    let all_types_ser = to_vec(&all_types()).expect("Error trying to serialize AllTypes");
+
+    let expected = deserialize_from_schema(&mut all_types_ser.as_slice(), &container).expect("Deserialization failed");
+    let mut buffer = all_types_ser.as_slice();
+    let result = plan.deserialize(&mut buffer).expect("Deserialization failed");
+    assert_eq!(result, expected);
+    assert!(buffer.is_empty());
+
+    let all_types_value = serde_json::to_value(all_types()).expect("Error serializing all_types");
+    let mut expected_writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut expected_writer, &all_types_value, &container).expect("Serialization failed");
+    let mut writer = Vec::new();
+    plan.serialize(&mut writer, &all_types_value).expect("Serialization failed");
+    assert_eq!(writer, expected_writer);
+    assert_eq!(writer, all_types_ser);
+}
+
+#[test]
+fn recursive_declarations_compile_to_one_node() {
+    let container: BorshSchemaContainer = schema_container_of::<Node>();
+    let plan = SchemaPlan::compile(&container);
+
+    assert_eq!(plan.nodes().len(), 3);
+    assert_eq!(plan.node(plan.root()).declaration, "Node");
+    let PlanNode::Struct { fields } = &plan.node(plan.root()).node else {
+        panic!("Node should compile to a struct");
+    };
+    assert_eq!(plan.node(fields[0].1).node, PlanNode::Primitive(PrimitiveType::U8));
+    let PlanNode::Sequence { elements, .. } = &plan.node(fields[1].1).node else {
+        panic!("Vec<Node> should compile to a sequence");
+    };
+    assert_eq!(*elements, plan.root());
+
+    let tree = Node {
+        value: 1,
+        children: vec![Node { value: 2, children: vec![] }],
+    };
+    let tree_ser = to_vec(&tree).expect("Error trying to serialize Node");
+    let result = plan.deserialize(&mut tree_ser.as_slice()).expect("Deserialization failed");
+    let mut writer = Vec::new();
+    plan.serialize(&mut writer, &result).expect("Serialization failed");
+    assert_eq!(writer, tree_ser);
+}
+
+#[test]
+fn sizes_are_precomputed() {
+    let container: BorshSchemaContainer = schema_container_of::<Header>();
+    let plan = SchemaPlan::compile(&container);
+    let size_of = |declaration: &str| {
+        let node = plan.nodes().iter().find(|node| node.declaration == declaration).expect("Declaration should be compiled");
+        (node.fixed_size, node.min_size)
+    };
+
+    assert_eq!(size_of("u16"), (Some(2), 2));
+    assert_eq!(size_of("[u8; 3]"), (Some(3), 3));
+    assert_eq!(size_of("(i32, i32)"), (Some(8), 8));
+    assert_eq!(size_of("TestEnum"), (Some(2), 1));
+    assert_eq!(size_of("String"), (None, 4));
+    assert_eq!(size_of("Header"), (None, 2 + 3 + 8 + 1 + 4));
+}
+
+#[test]
+fn errors_match_schema_dispatch() {
+    let container: BorshSchemaContainer = schema_container_of::<AllTypes>();
+    let plan = SchemaPlan::compile(&container);
+    let mut all_types_ser = to_vec(&all_types()).expect("Error trying to serialize AllTypes");
+
+    let limits = DecodeLimits { max_output_nodes: 20, ..DecodeLimits::default() };
+    let expected = deserialize_from_schema_with_limits(&mut all_types_ser.as_slice(), &container, &limits).expect_err("Limit should be exceeded");
+    let error = plan.deserialize_with_limits(&mut all_types_ser.as_slice(), &limits).expect_err("Limit should be exceeded");
+    assert_eq!(error.to_string(), expected.to_string());
+
+    // Corrupt the length of type_sequence
+    let offset = 1 + 2 + 4 + 8 + 1 + 2 + 4 + 8 + 4 + 8 + (4 + 3) + 1 + 3;
+    all_types_ser[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let expected = deserialize_from_schema(&mut all_types_ser.as_slice(), &container).expect_err("Deserialization should fail");
+    let error = plan.deserialize(&mut all_types_ser.as_slice()).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), expected.to_string());
+    assert!(error.to_string().starts_with("Sequence of 4294967295 String needs more than"));
+
+    // Sequences of fixed size primitives are read in one go by the plan, but fail the same way
+    let container: BorshSchemaContainer = schema_container_of::<Vec<u64>>();
+    let plan = SchemaPlan::compile(&container);
+    let amounts_ser = to_vec(&vec![1u64, 2, 3, 4]).expect("Error trying to serialize Vec<u64>");
+    for limits in [
+        DecodeLimits { max_output_nodes: 3, ..DecodeLimits::default() },
+        DecodeLimits { max_depth: 0, ..DecodeLimits::default() },
+        DecodeLimits { max_sequence_length: 3, ..DecodeLimits::default() },
+    ] {
+        let expected = deserialize_from_schema_with_limits(&mut amounts_ser.as_slice(), &container, &limits).expect_err("Limit should be exceeded");
+        let error = plan.deserialize_with_limits(&mut amounts_ser.as_slice(), &limits).expect_err("Limit should be exceeded");
+        assert_eq!(error.to_string(), expected.to_string());
+    }
+}
@@ -0,0 +1,181 @@
+#![cfg(feature = "http")]
+
+use std::sync::Arc;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::errors::RegistryError;
+use borsh_serde_adapter::schema_registry::{fingerprint, InMemorySchemaRegistry, SchemaRegistry};
+use borsh_serde_adapter::schema_registry_http::{SchemaRegistryClient, SchemaRegistryServer, SchemaRegistryServerHandle};
+use serde_json::{json, Value};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Person {
+    first_name: String,
+    last_name: String
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct PersonWithAge {
+    first_name: String,
+    last_name: String,
+    age: u8
+}
+
+fn start_server() -> (Arc<InMemorySchemaRegistry>, SchemaRegistryServerHandle) {
+    let registry = Arc::new(InMemorySchemaRegistry::new());
+    let server = SchemaRegistryServer::bind("127.0.0.1:0", registry.clone()).expect("Failed to bind server");
+    (registry, server.spawn())
+}
+
+#[test]
+fn client_fetches_and_caches_schema() {
+    let (registry, server) = start_server();
+    let container: BorshSchemaContainer = schema_container_of::<Person>();
+    registry.register("person", 1, &container, false).expect("Registration failed");
+
+    let client = SchemaRegistryClient::new(&server.url());
+    assert_eq!(client.names().expect("Listing names failed"), vec!["person".to_string()]);
+    assert_eq!(client.versions("person").expect("Listing versions failed"), vec![1]);
+    assert_eq!(client.get("person", 1).expect("Schema not found"), container);
+
+    let person = Person { first_name: "John".to_string(), last_name: "Doe".to_string() };
+    let person_ser = to_vec(&person).expect("Error trying to serialize Person");
+
+    // The schema is served from the cache once the server is gone
+    server.shutdown();
+    let result = client.deserialize("person", 1, &mut person_ser.as_slice()).expect("Deserialization failed");
+    assert_eq!(result, json!({"first_name": "John", "last_name": "Doe"}));
+}
+
+#[test]
+fn client_registers_and_looks_up_by_fingerprint() {
+    let (registry, server) = start_server();
+    let client = SchemaRegistryClient::new(&server.url());
+    let container: BorshSchemaContainer = schema_container_of::<Person>();
+
+    let entry = client.register("person", 1, &container, false).expect("Registration failed");
+    assert_eq!(entry.fingerprint, fingerprint(&container));
+    assert_eq!(registry.get("person", 1).expect("Schema not found"), container);
+
+    let lookup_client = SchemaRegistryClient::new(&server.url());
+    let found = lookup_client.find_by_fingerprint(&entry.fingerprint).expect("Schema not found");
+    assert_eq!(found, entry);
+    assert_eq!(lookup_client.get_by_fingerprint(&entry.fingerprint).expect("Schema not found"), container);
+}
+
+#[test]
+fn client_maps_server_errors() {
+    let (_registry, server) = start_server();
+    let client = SchemaRegistryClient::new(&server.url());
+
+    assert!(matches!(client.get("person", 1), Err(RegistryError::NotFound(_, 1))));
+    assert!(matches!(client.versions("person"), Err(RegistryError::NameNotFound(_))));
+    assert!(matches!(client.find_by_fingerprint("00"), Err(RegistryError::FingerprintNotFound(_))));
+
+    client.register("person", 1, &schema_container_of::<Person>(), false).expect("Registration failed");
+    assert!(matches!(
+        client.register("person", 1, &schema_container_of::<Person>(), false),
+        Err(RegistryError::AlreadyExists(_, 1))
+    ));
+    match client.register("person", 2, &schema_container_of::<PersonWithAge>(), false) {
+        Err(RegistryError::BreakingChange { previous, changes, .. }) => {
+            assert_eq!(previous, 1);
+            assert!(!changes.is_empty());
+        }
+        _ => panic!("Expected a breaking change"),
+    }
+    client.register("person", 2, &schema_container_of::<PersonWithAge>(), true).expect("Forced registration failed");
+    assert_eq!(client.get_latest("person").expect("Schema not found").0, 2);
+}
+
+#[test]
+fn client_encodes_names_in_paths() {
+    let (registry, server) = start_server();
+    let client = SchemaRegistryClient::new(&server.url());
+    let container: BorshSchemaContainer = schema_container_of::<Person>();
+
+    let name = "team person?v=1#a%2";
+    let entry = client.register(name, 1, &container, false).expect("Registration failed");
+    assert_eq!(registry.names().expect("Listing names failed"), vec![name.to_string()]);
+    assert_eq!(client.versions(name).expect("Listing versions failed"), vec![1]);
+    assert_eq!(client.get(name, 1).expect("Schema not found"), container);
+
+    client.clear_cache();
+    assert_eq!(client.find_by_fingerprint(&entry.fingerprint).expect("Fingerprint not found").name, name);
+    assert!(matches!(client.versions("team"), Err(RegistryError::NameNotFound(_))));
+    assert!(matches!(client.register("team/person", 1, &container, false), Err(RegistryError::InvalidName(_))));
+}
+
+#[test]
+fn bad_requests_are_not_invalid_names() {
+    let (_registry, server) = start_server();
+    let error = ureq::put(&format!("{}/schemas/person/1", server.url()))
+        .send_bytes(&[1, 2, 3])
+        .expect_err("Invalid schema accepted");
+    match error {
+        ureq::Error::Status(400, response) => {
+            let body: Value = response.into_json().expect("Invalid JSON");
+            assert!(body["error"].as_str().unwrap().starts_with("Invalid schema"));
+            assert_eq!(body.get("kind"), None);
+        }
+        error => panic!("Expected a 400 response, got {error}"),
+    }
+
+    let error = ureq::put(&format!("{}/schemas/.person/1", server.url()))
+        .send_bytes(&to_vec(&schema_container_of::<Person>()).unwrap())
+        .expect_err("Invalid name accepted");
+    match error {
+        ureq::Error::Status(400, response) => assert_eq!(response.into_json::<Value>().unwrap()["kind"], "invalid_name"),
+        error => panic!("Expected a 400 response, got {error}"),
+    }
+}
+
+#[test]
+fn server_returns_json_schema() {
+    let (registry, server) = start_server();
+    registry.register("person", 1, &schema_container_of::<Person>(), false).expect("Registration failed");
+
+    let response = ureq::get(&format!("{}/schemas/person/latest?format=json", server.url()))
+        .call()
+        .expect("Request failed");
+    assert_eq!(response.header("X-Schema-Version"), Some("1"));
+    let schema: Value = response.into_json().expect("Invalid JSON");
+    assert_eq!(schema["declaration"], json!("Person"));
+}
+
+#[test]
+fn long_bodies_are_refused() {
+    let registry = Arc::new(InMemorySchemaRegistry::new());
+    let schema = to_vec(&schema_container_of::<Person>()).unwrap();
+    let server = SchemaRegistryServer::bind_with_max_body_length("127.0.0.1:0", registry.clone(), schema.len())
+        .expect("Failed to bind server")
+        .spawn();
+
+    let error = ureq::put(&format!("{}/schemas/person/1", server.url()))
+        .send_bytes(&[schema.as_slice(), &[0]].concat())
+        .expect_err("Long body accepted");
+    assert!(matches!(error, ureq::Error::Status(413, _)), "Expected a 413 response, got {error}");
+    assert!(matches!(registry.versions("person"), Err(RegistryError::NameNotFound(_))));
+
+    ureq::put(&format!("{}/schemas/person/1", server.url())).send_bytes(&schema).expect("Registration failed");
+    assert_eq!(registry.versions("person").expect("Listing versions failed"), vec![1]);
+}
+
+#[test]
+fn query_values_are_percent_decoded() {
+    let (registry, server) = start_server();
+    registry.register("person", 1, &schema_container_of::<Person>(), false).expect("Registration failed");
+
+    let response = ureq::get(&format!("{}/schemas/person/1?format=%6Aso%6E", server.url()))
+        .call()
+        .expect("Request failed");
+    let schema: Value = response.into_json().expect("Invalid JSON");
+    assert_eq!(schema["declaration"], json!("Person"));
+
+    let breaking = to_vec(&schema_container_of::<PersonWithAge>()).unwrap();
+    ureq::put(&format!("{}/schemas/person/2?%66orce=%74rue", server.url()))
+        .send_bytes(&breaking)
+        .expect("Forced registration failed");
+    assert_eq!(registry.versions("person").expect("Listing versions failed"), vec![1, 2]);
+}
@@ -0,0 +1,140 @@
+use borsh::{BorshSchema, schema_container_of};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::errors::RegistryError;
+use borsh_serde_adapter::schema_compatibility::breaking_changes;
+use borsh_serde_adapter::schema_registry::{fingerprint, DirectorySchemaRegistry, InMemorySchemaRegistry, SchemaRegistry};
+
+#[allow(dead_code)]
+mod v1 {
+    use super::*;
+
+    #[derive(BorshSchema)]
+    pub struct Person {
+        pub first_name: String,
+        pub last_name: String,
+    }
+
+    #[derive(BorshSchema)]
+    pub enum Status {
+        Active,
+        Inactive(u8),
+    }
+}
+
+#[allow(dead_code)]
+mod v2 {
+    use super::*;
+
+    #[derive(BorshSchema)]
+    pub struct Person {
+        pub first_name: String,
+        pub last_name: String,
+        pub age: u8,
+    }
+
+    #[derive(BorshSchema)]
+    pub enum Status {
+        Active,
+        Inactive(u8),
+        Banned,
+    }
+
+    #[derive(BorshSchema)]
+    pub enum StatusWithoutInactive {
+        Active,
+    }
+}
+
+#[test]
+fn register_and_get_schema() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = DirectorySchemaRegistry::new(dir.path()).expect("Failed to open registry");
+    let container: BorshSchemaContainer = schema_container_of::<v1::Person>();
+
+    let entry = registry.register("person", 1, &container, false).expect("Registration failed");
+    assert_eq!(entry.fingerprint, fingerprint(&container));
+    assert!(dir.path().join("person").join("1_schema.dat").is_file());
+
+    assert_eq!(registry.get("person", 1).expect("Schema not found"), container);
+    assert_eq!(registry.get_by_fingerprint(&entry.fingerprint).expect("Schema not found"), container);
+    assert_eq!(registry.find_by_fingerprint(&entry.fingerprint).expect("Schema not found"), entry);
+    assert_eq!(registry.names().expect("Listing names failed"), vec!["person".to_string()]);
+    assert!(matches!(registry.get("person", 2), Err(RegistryError::NotFound(_, 2))));
+    assert!(matches!(registry.versions("unknown"), Err(RegistryError::NameNotFound(_))));
+    assert!(matches!(registry.register("person", 1, &container, true), Err(RegistryError::AlreadyExists(_, 1))));
+    assert!(matches!(registry.register("../person", 1, &container, false), Err(RegistryError::InvalidName(_))));
+}
+
+#[test]
+fn compatible_versions_are_registered() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = DirectorySchemaRegistry::new(dir.path()).expect("Failed to open registry");
+
+    registry.register("status", 1, &schema_container_of::<v1::Status>(), false).expect("Registration failed");
+    registry.register("status", 2, &schema_container_of::<v2::Status>(), false).expect("Registration failed");
+
+    assert_eq!(registry.versions("status").expect("Listing versions failed"), vec![1, 2]);
+    let (version, latest) = registry.get_latest("status").expect("Schema not found");
+    assert_eq!(version, 2);
+    assert_eq!(latest, schema_container_of::<v2::Status>());
+}
+
+#[test]
+fn breaking_versions_are_refused_unless_forced() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = DirectorySchemaRegistry::new(dir.path()).expect("Failed to open registry");
+
+    registry.register("person", 1, &schema_container_of::<v1::Person>(), false).expect("Registration failed");
+
+    let result = registry.register("person", 2, &schema_container_of::<v2::Person>(), false);
+    match result {
+        Err(RegistryError::BreakingChange { previous, changes, .. }) => {
+            assert_eq!(previous, 1);
+            assert_eq!(changes[0].path, "");
+        }
+        _ => panic!("Expected a breaking change"),
+    }
+    assert_eq!(registry.versions("person").expect("Listing versions failed"), vec![1]);
+
+    registry.register("person", 2, &schema_container_of::<v2::Person>(), true).expect("Forced registration failed");
+    assert_eq!(registry.versions("person").expect("Listing versions failed"), vec![1, 2]);
+}
+
+/// Registers the same version from several threads at once and checks that only one registration succeeds
+fn assert_one_concurrent_registration(registry: &(impl SchemaRegistry + Sync)) {
+    let person = schema_container_of::<v1::Person>();
+    let status = schema_container_of::<v1::Status>();
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let schema = if i % 2 == 0 { &person } else { &status };
+                scope.spawn(move || registry.register("shared", 1, schema, true))
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    let registered: Vec<_> = results.iter().filter_map(|result| result.as_ref().ok()).collect();
+    assert_eq!(registered.len(), 1);
+    assert!(results.iter().filter(|result| result.is_err()).all(|result| matches!(result, Err(RegistryError::AlreadyExists(_, 1)))));
+    assert_eq!(fingerprint(&registry.get("shared", 1).expect("Schema not found")), registered[0].fingerprint);
+}
+
+#[test]
+fn concurrent_registrations_do_not_overwrite() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    assert_one_concurrent_registration(&DirectorySchemaRegistry::new(dir.path()).expect("Failed to open registry"));
+    assert_one_concurrent_registration(&InMemorySchemaRegistry::new());
+}
+
+#[test]
+fn removed_variant_is_breaking() {
+    let changes = breaking_changes(
+        &schema_container_of::<v1::Status>(),
+        &schema_container_of::<v2::StatusWithoutInactive>(),
+    );
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path, "/Inactive");
+
+    assert!(breaking_changes(&schema_container_of::<v1::Person>(), &schema_container_of::<v1::Person>()).is_empty());
+}
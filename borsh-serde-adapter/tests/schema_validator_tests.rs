@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroU8;
+
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+use borsh_serde_adapter::borsh_schema_util::{is_supported_primitive, PrimitiveType};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::errors::SchemaIssue;
+use borsh_serde_adapter::schema_validator::validate_schema;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use serde_json::json;
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub enum Shape {
+    Circle(u32),
+    Rectangle { width: u32, height: u32 },
+}
+
+#[allow(dead_code)]
+#[derive(BorshSchema)]
+pub struct Drawing {
+    name: String,
+    shapes: Vec<Shape>,
+    origin: (i64, i64),
+    color: [u8; 3],
+}
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Counter {
+    count: NonZeroU8,
+}
+
+fn struct_definition(fields: Vec<(&str, &str)>) -> Definition {
+    Definition::Struct {
+        fields: Fields::NamedFields(
+            fields.into_iter().map(|(name, declaration)| (name.to_string(), declaration.to_string())).collect(),
+        ),
+    }
+}
+
+#[test]
+fn derived_schema_is_valid() {
+    let container: BorshSchemaContainer = schema_container_of::<Drawing>();
+    assert_eq!(validate_schema(&container), vec![]);
+}
+
+#[test]
+fn unit_is_a_supported_primitive() {
+    let container: BorshSchemaContainer = schema_container_of::<Option<u8>>();
+    assert_eq!(validate_schema(&container), vec![]);
+    assert!(is_supported_primitive("()"));
+    assert_eq!(PrimitiveType::from_declaration("()"), Some(PrimitiveType::Unit));
+    assert!(PrimitiveType::ALL.iter().all(|primitive| PrimitiveType::from_declaration(primitive.declaration()) == Some(*primitive)));
+}
+
+#[test]
+fn dangling_references_are_reported() {
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Account".to_string(), struct_definition(vec![("owner", "Pubkey"), ("balance", "u64")]));
+    let container = BorshSchemaContainer::new("Account".to_string(), definitions);
+
+    assert_eq!(validate_schema(&container), vec![
+        SchemaIssue::DanglingReference("Pubkey".to_string(), "Account".to_string()),
+    ]);
+
+    let container = BorshSchemaContainer::new("Missing".to_string(), BTreeMap::new());
+    assert_eq!(validate_schema(&container), vec![
+        SchemaIssue::DanglingReference("Missing".to_string(), "container".to_string()),
+    ]);
+}
+
+#[test]
+fn unsupported_primitives_are_reported_and_rejected_by_adapters() {
+    let container: BorshSchemaContainer = schema_container_of::<Counter>();
+    assert_eq!(validate_schema(&container), vec![SchemaIssue::UnsupportedPrimitive("NonZeroU8".to_string())]);
+
+    let counter_ser = to_vec(&Counter { count: NonZeroU8::new(1).unwrap() }).expect("Error trying to serialize Counter");
+    let error = deserialize_from_schema(&mut counter_ser.as_slice(), &container).expect_err("Deserialization should fail");
+    assert_eq!(error.to_string(), "Unsupported primitive type NonZeroU8");
+
+    let mut writer = Vec::new();
+    let error = serialize_serde_json_to_borsh(&mut writer, &json!({"count": 1}), &container).expect_err("Serialization should fail");
+    assert_eq!(error.to_string(), "Unsupported primitive type NonZeroU8");
+}
+
+#[test]
+fn infinite_recursion_is_reported() {
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Node".to_string(), struct_definition(vec![("value", "u8"), ("next", "Link")]));
+    definitions.insert("Link".to_string(), Definition::Tuple { elements: vec!["Node".to_string()] });
+    definitions.insert("Tree".to_string(), struct_definition(vec![("children", "Vec<Tree>")]));
+    definitions.insert("Vec<Tree>".to_string(), Definition::Sequence {
+        length_width: Definition::DEFAULT_LENGTH_WIDTH,
+        length_range: Definition::DEFAULT_LENGTH_RANGE,
+        elements: "Tree".to_string(),
+    });
+    let container = BorshSchemaContainer::new("Node".to_string(), definitions);
+
+    assert_eq!(validate_schema(&container), vec![
+        SchemaIssue::InfiniteRecursion("Link".to_string()),
+        SchemaIssue::InfiniteRecursion("Node".to_string()),
+    ]);
+}
+
+#[test]
+fn sequence_and_enum_issues_are_reported() {
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Packet".to_string(), Definition::Tuple { elements: vec!["Body".to_string(), "Kind".to_string()] });
+    definitions.insert("Body".to_string(), Definition::Sequence {
+        length_width: 0,
+        length_range: 20..=65536,
+        elements: "u8".to_string(),
+    });
+    definitions.insert("Kind".to_string(), Definition::Enum {
+        tag_width: 1,
+        variants: vec![
+            (0, "Ping".to_string(), "()".to_string()),
+            (0, "Pong".to_string(), "()".to_string()),
+            (1, "Ping".to_string(), "()".to_string()),
+        ],
+    });
+    definitions.insert("()".to_string(), Definition::Struct { fields: Fields::Empty });
+    let container = BorshSchemaContainer::new("Packet".to_string(), definitions);
+
+    let issues = validate_schema(&container);
+    assert_eq!(issues, vec![
+        SchemaIssue::InconsistentFixedLength("Body".to_string(), 20..=65536),
+        SchemaIssue::DuplicateDiscriminant("Kind".to_string(), 0),
+        SchemaIssue::DuplicateVariant("Kind".to_string(), "Ping".to_string()),
+    ]);
+    assert_eq!(issues[1].to_string(), "Enum Kind has duplicate discriminant 0");
+}
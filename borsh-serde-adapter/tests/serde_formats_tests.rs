@@ -0,0 +1,132 @@
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::serde_formats::{transcode_from_borsh, transcode_to_borsh};
+
+mod common;
+use common::Status;
+
+/// An account with a u128 and a float, which the formats encode differently from JSON
+#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balance: u64,
+    supply: u128,
+    rate: f32,
+    tags: Vec<String>,
+    status: Status,
+    delegate: Option<String>,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "alice".to_string(),
+        balance: 1_000,
+        supply: u128::MAX,
+        rate: 0.25,
+        tags: vec!["a".to_string(), "b".to_string()],
+        status: Status::Active { since: -7 },
+        delegate: Some("bob".to_string()),
+    }
+}
+
+#[test]
+fn transcode_with_any_serializer_test() {
+    let container = schema_container_of::<Account>();
+    let bytes = to_vec(&account()).unwrap();
+
+    let mut json = Vec::new();
+    transcode_from_borsh(&mut bytes.as_slice(), &container, &mut serde_json::Serializer::pretty(&mut json)).unwrap();
+    let expected = deserialize_from_schema(&mut bytes.as_slice(), &container).unwrap();
+    assert_eq!(json, serde_json::to_vec_pretty(&expected).unwrap());
+
+    let mut borsh = Vec::new();
+    transcode_to_borsh(&mut borsh, &mut serde_json::Deserializer::from_slice(&json), &container).unwrap();
+    assert_eq!(borsh, bytes);
+}
+
+#[test]
+fn transcode_invalid_input_test() {
+    let container = schema_container_of::<Account>();
+    let mut json = Vec::new();
+    assert!(transcode_from_borsh(&mut [1u8, 0].as_slice(), &container, &mut serde_json::Serializer::new(&mut json)).is_err());
+
+    let mut borsh = Vec::new();
+    let error = transcode_to_borsh(&mut borsh, &mut serde_json::Deserializer::from_str("{\"owner\": 1}"), &container).unwrap_err();
+    assert!(error.to_string().contains("balance"), "{error}");
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_round_trip_test() {
+    use borsh_serde_adapter::serde_formats::{deserialize_to_yaml, serialize_yaml_to_borsh};
+
+    let container = schema_container_of::<Account>();
+    let bytes = to_vec(&account()).unwrap();
+    let yaml = deserialize_to_yaml(&mut bytes.as_slice(), &container).unwrap();
+    assert!(yaml.contains("owner: alice\n"), "{yaml}");
+    assert!(yaml.contains("    since: -7\n"), "{yaml}");
+
+    let mut borsh = Vec::new();
+    serialize_yaml_to_borsh(&mut borsh, &yaml, &container).unwrap();
+    assert_eq!(borsh, bytes);
+
+    let frozen = Account { status: Status::Frozen, delegate: None, ..account() };
+    let yaml = deserialize_to_yaml(&mut to_vec(&frozen).unwrap().as_slice(), &container).unwrap();
+    let mut borsh = Vec::new();
+    serialize_yaml_to_borsh(&mut borsh, &yaml, &container).unwrap();
+    assert_eq!(Account::try_from_slice(&borsh).unwrap(), frozen);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_round_trip_test() {
+    use borsh_serde_adapter::serde_formats::{deserialize_to_toml, serialize_toml_to_borsh};
+
+    let container = schema_container_of::<Account>();
+    let bytes = to_vec(&account()).unwrap();
+    let toml = deserialize_to_toml(&mut bytes.as_slice(), &container).unwrap();
+    assert!(toml.contains("owner = \"alice\"\n"), "{toml}");
+
+    let mut borsh = Vec::new();
+    serialize_toml_to_borsh(&mut borsh, &toml, &container).unwrap();
+    assert_eq!(borsh, bytes);
+
+    // TOML has no null, so None can't be written
+    let account = Account { delegate: None, ..account() };
+    assert!(deserialize_to_toml(&mut to_vec(&account).unwrap().as_slice(), &container).is_err());
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_round_trip_test() {
+    use borsh_serde_adapter::serde_formats::{deserialize_to_cbor, serialize_cbor_to_borsh};
+
+    let container = schema_container_of::<Account>();
+    let bytes = to_vec(&account()).unwrap();
+    let cbor = deserialize_to_cbor(&mut bytes.as_slice(), &container).unwrap();
+    let value: serde_json::Value = ciborium::from_reader(cbor.as_slice()).unwrap();
+    assert_eq!(value, deserialize_from_schema(&mut bytes.as_slice(), &container).unwrap());
+
+    let mut borsh = Vec::new();
+    serialize_cbor_to_borsh(&mut borsh, &cbor, &container).unwrap();
+    assert_eq!(borsh, bytes);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack_round_trip_test() {
+    use borsh_serde_adapter::serde_formats::{deserialize_to_msgpack, serialize_msgpack_to_borsh};
+
+    let container = schema_container_of::<Account>();
+    let bytes = to_vec(&account()).unwrap();
+    let msgpack = deserialize_to_msgpack(&mut bytes.as_slice(), &container).unwrap();
+    let value: serde_json::Value = rmp_serde::from_slice(&msgpack).unwrap();
+    assert_eq!(value, deserialize_from_schema(&mut bytes.as_slice(), &container).unwrap());
+
+    let mut borsh = Vec::new();
+    serialize_msgpack_to_borsh(&mut borsh, &msgpack, &container).unwrap();
+    assert_eq!(borsh, bytes);
+
+    let mut borsh = Vec::new();
+    assert!(serialize_msgpack_to_borsh(&mut borsh, &[0xc1], &container).is_err());
+}
@@ -0,0 +1,105 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::flat_export::{FlatOptions, FlatSchema};
+use borsh_serde_adapter::json_validator::validate;
+use borsh_serde_adapter::projection::project_from_schema;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use borsh_serde_adapter::value_diff::{diff_values, ValueChange};
+use serde_json::{json, Value};
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Bytes(Vec<u8>);
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Single([u16; 1]);
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Nested((u32,));
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Pair(u8, String);
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Wrapper(u32);
+
+#[derive(BorshSerialize, BorshSchema)]
+pub struct Wrapped {
+    bytes: Bytes,
+    pairs: Vec<Pair>,
+}
+
+/// Checks that the value is the JSON of the bytes in the adapters, the plan and the validator
+fn assert_json(container: &BorshSchemaContainer, bytes: &[u8], value: Value) {
+    assert_eq!(deserialize_from_schema(&mut &bytes[..], container).expect("Deserialization failed"), value);
+    assert_eq!(SchemaPlan::compile(container).deserialize(&mut &bytes[..]).expect("Plan deserialization failed"), value);
+    assert_serializes(container, bytes, value);
+}
+
+/// Checks that the value serializes to the bytes in the adapter and the plan, and passes the validator
+fn assert_serializes(container: &BorshSchemaContainer, bytes: &[u8], value: Value) {
+    let plan = SchemaPlan::compile(container);
+    assert_eq!(validate(&value, container), []);
+
+    let mut writer = Vec::new();
+    serialize_serde_json_to_borsh(&mut writer, &value, container).expect("Serialization failed");
+    assert_eq!(writer, bytes);
+    let mut writer = Vec::new();
+    plan.serialize(&mut writer, &value).expect("Plan serialization failed");
+    assert_eq!(writer, bytes);
+}
+
+#[test]
+fn single_unnamed_fields_are_one_element_arrays() {
+    assert_json(&schema_container_of::<Bytes>(), &[1, 0, 0, 0, 5], json!([[5]]));
+    assert_json(&schema_container_of::<Single>(), &to_vec(&Single([7])).unwrap(), json!([[7]]));
+    assert_json(&schema_container_of::<Nested>(), &to_vec(&Nested((9,))).unwrap(), json!([[9]]));
+
+    let wrapped = Wrapped {
+        bytes: Bytes(vec![1, 2]),
+        pairs: vec![Pair(3, "c".to_string())],
+    };
+    assert_json(&schema_container_of::<Wrapped>(), &to_vec(&wrapped).unwrap(), json!({"bytes": [[1, 2]], "pairs": [[3, "c"]]}));
+}
+
+#[test]
+fn single_unnamed_fields_may_be_given_bare() {
+    assert_serializes(&schema_container_of::<Bytes>(), &[2, 0, 0, 0, 5, 6], json!([5, 6]));
+    assert_serializes(&schema_container_of::<Wrapper>(), &to_vec(&Wrapper(3)).unwrap(), json!(3));
+
+    // A bare field that is itself an array of one element is taken for the one element array form
+    let container = schema_container_of::<Bytes>();
+    let mut writer = Vec::new();
+    assert!(serialize_serde_json_to_borsh(&mut writer, &json!([5]), &container).is_err());
+    assert!(!validate(&json!([5]), &container).is_empty());
+}
+
+#[test]
+fn doubly_wrapped_single_fields_are_rejected() {
+    let container = schema_container_of::<Bytes>();
+    let mut writer = Vec::new();
+    assert!(serialize_serde_json_to_borsh(&mut writer, &json!([[[5]]]), &container).is_err());
+    assert!(SchemaPlan::compile(&container).serialize(&mut writer, &json!([[[5]]])).is_err());
+    assert!(!validate(&json!([[[5]]]), &container).is_empty());
+}
+
+#[test]
+fn paths_index_the_single_field() {
+    let container = schema_container_of::<Wrapped>();
+    let wrapped = Wrapped {
+        bytes: Bytes(vec![1, 2]),
+        pairs: vec![Pair(3, "c".to_string())],
+    };
+    let bytes = to_vec(&wrapped).unwrap();
+
+    let result = project_from_schema(&mut bytes.as_slice(), &container, &["bytes[0]", "pairs[*][1]"]).expect("Projection failed");
+    assert_eq!(result, json!({"bytes": [[1, 2]], "pairs": [["c"]]}));
+
+    let flat = FlatSchema::compile(&container, &FlatOptions::default());
+    assert_eq!(flat.columns()[0], "bytes[0]");
+    assert_eq!(flat.rows(&json!({"bytes": [[1, 2]], "pairs": []}))[0][0], "0102");
+
+    let changes = diff_values(&json!({"bytes": [[1]], "pairs": []}), &json!({"bytes": [[1, 2]], "pairs": []}), &container);
+    assert_eq!(changes, [ValueChange::Added { path: "/bytes/0/1".to_string(), value: json!(2) }]);
+}
@@ -0,0 +1,158 @@
+use borsh::{BorshSchema, BorshSerialize, schema_container_of, to_vec};
+use borsh::schema::BorshSchemaContainer;
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::value_diff::{diff_from_schema, format_diff, to_json_patch, ValueChange};
+use serde_json::{json, Value};
+
+#[derive(Clone, BorshSerialize, BorshSchema)]
+pub enum AssetKind {
+    Native,
+    Token { decimals: u8 },
+}
+
+#[derive(Clone, BorshSerialize, BorshSchema)]
+pub struct Balance {
+    mint: String,
+    amount: u64,
+    kind: AssetKind,
+}
+
+/// An account with sequences of structs and an enum, so that changes are found at every depth
+#[derive(Clone, BorshSerialize, BorshSchema)]
+pub struct Account {
+    owner: String,
+    balances: Vec<Balance>,
+    history: Vec<u64>,
+    key: [u8; 4],
+    settings: (bool, String),
+}
+
+fn account() -> Account {
+    Account {
+        owner: "alice".to_string(),
+        balances: vec![
+            Balance { mint: "sol".to_string(), amount: 5, kind: AssetKind::Native },
+            Balance { mint: "usdc".to_string(), amount: 100, kind: AssetKind::Token { decimals: 6 } },
+        ],
+        history: vec![10, 20, 30, 40],
+        key: [1, 2, 3, 4],
+        settings: (true, "dark".to_string()),
+    }
+}
+
+/// Applies a JSON Patch with add, remove and replace operations
+fn apply_json_patch(value: &mut Value, patch: &Value) {
+    for operation in patch.as_array().expect("Patch should be an array") {
+        let path = operation["path"].as_str().expect("Operation should have a path");
+        let (parent, last) = path.rsplit_once('/').expect("Path should not be the root");
+        let target = value.pointer_mut(parent).expect("Parent should exist");
+        match (operation["op"].as_str(), target) {
+            (Some("replace"), target) => *target.pointer_mut(&format!("/{last}")).expect("Path should exist") = operation["value"].clone(),
+            (Some("add"), Value::Array(items)) => items.insert(last.parse().unwrap(), operation["value"].clone()),
+            (Some("remove"), Value::Array(items)) => {
+                items.remove(last.parse().unwrap());
+            }
+            (op, _) => panic!("Unexpected operation {op:?}"),
+        }
+    }
+}
+
+fn diff(old: &Account, new: &Account) -> (Vec<ValueChange>, BorshSchemaContainer) {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let old_ser = to_vec(old).expect("Error trying to serialize Account");
+    let new_ser = to_vec(new).expect("Error trying to serialize Account");
+    let changes = diff_from_schema(&old_ser, &new_ser, &container).expect("Diff failed");
+
+    // Applying the patch to the old value must always give the new one
+    let mut patched = deserialize_from_schema(&mut old_ser.as_slice(), &container).expect("Deserialization failed");
+    apply_json_patch(&mut patched, &to_json_patch(&changes));
+    assert_eq!(patched, deserialize_from_schema(&mut new_ser.as_slice(), &container).expect("Deserialization failed"));
+    (changes, container)
+}
+
+#[test]
+fn identical_values_have_no_changes() {
+    let (changes, _) = diff(&account(), &account());
+    assert_eq!(changes, vec![]);
+    assert_eq!(to_json_patch(&changes), json!([]));
+}
+
+#[test]
+fn field_changes_are_reported_by_path() {
+    let mut new = account();
+    new.owner = "bob".to_string();
+    new.balances[1].amount = 250;
+    new.key[2] = 9;
+    new.settings.1 = "light".to_string();
+
+    let (changes, _) = diff(&account(), &new);
+    assert_eq!(format_diff(&changes), concat!(
+        "/owner: \"alice\" -> \"bob\"\n",
+        "/balances/1/amount: 100 -> 250\n",
+        "/key/2: 3 -> 9\n",
+        "/settings/1: \"dark\" -> \"light\"\n",
+    ));
+    assert_eq!(to_json_patch(&changes)[0], json!({"op": "replace", "path": "/owner", "value": "bob"}));
+}
+
+#[test]
+fn variant_switches_are_reported() {
+    let mut new = account();
+    new.balances[0].kind = AssetKind::Token { decimals: 9 };
+    new.balances[1].kind = AssetKind::Token { decimals: 2 };
+
+    let (changes, _) = diff(&account(), &new);
+    assert_eq!(changes[0], ValueChange::VariantChanged {
+        path: "/balances/0/kind".to_string(),
+        old_variant: "Native".to_string(),
+        new_variant: "Token".to_string(),
+        old: json!({"Native": []}),
+        new: json!({"Token": {"decimals": 9}}),
+    });
+    assert_eq!(format_diff(&changes), concat!(
+        "/balances/0/kind: variant Native -> Token {\"decimals\":9}\n",
+        "/balances/1/kind/Token/decimals: 6 -> 2\n",
+    ));
+}
+
+#[test]
+fn sequence_insertions_and_removals_are_aligned() {
+    let mut new = account();
+    new.history = vec![5, 10, 30, 35, 40, 50];
+    new.balances.remove(0);
+
+    let (changes, _) = diff(&account(), &new);
+    assert_eq!(format_diff(&changes), concat!(
+        "/balances/0: removed {\"amount\":5,\"kind\":{\"Native\":[]},\"mint\":\"sol\"}\n",
+        "/history/0: added 5\n",
+        "/history/2: removed 20\n",
+        "/history/3: added 35\n",
+        "/history/5: added 50\n",
+    ));
+    assert_eq!(to_json_patch(&changes)[0], json!({"op": "remove", "path": "/balances/0"}));
+}
+
+#[test]
+fn mixed_changes_apply_as_a_json_patch() {
+    let mut new = account();
+    new.balances.insert(1, Balance { mint: "bonk".to_string(), amount: 1, kind: AssetKind::Native });
+    new.balances[2].kind = AssetKind::Native;
+    new.history = vec![40, 30, 20, 10];
+    new.balances.push(Balance { mint: "ape".to_string(), amount: 3, kind: AssetKind::Token { decimals: 0 } });
+    diff(&account(), &new);
+
+    let mut emptied = account();
+    emptied.balances.clear();
+    emptied.history.clear();
+    let (changes, _) = diff(&account(), &emptied);
+    assert_eq!(changes.len(), 6);
+    diff(&emptied, &account());
+}
+
+#[test]
+fn invalid_input_is_an_error() {
+    let container: BorshSchemaContainer = schema_container_of::<Account>();
+    let old_ser = to_vec(&account()).expect("Error trying to serialize Account");
+    let error = diff_from_schema(&old_ser, &old_ser[..3], &container).expect_err("Diff should fail");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "generator")]
+
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of};
+use borsh::schema::{BorshSchemaContainer, Definition};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::schema_plan::SchemaPlan;
+use borsh_serde_adapter::schema_validator::validate_schema;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use borsh_serde_adapter::value_generator::{generate_from_schema, random_schema, GeneratorLimits, ValueGenerator};
+
+#[derive(Debug, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum Shape {
+    Point,
+    Circle { radius: f32 },
+    Polygon { corners: Vec<(i16, i16)> },
+}
+
+#[derive(Debug, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Drawing {
+    name: String,
+    shapes: Vec<Shape>,
+    layer: Option<u8>,
+    color: [u8; 3],
+    scale: f64,
+    id: u128,
+    children: Vec<Drawing>,
+}
+
+#[test]
+fn generated_values_decode_with_derived_types() {
+    let container: BorshSchemaContainer = schema_container_of::<Drawing>();
+    let mut generator = ValueGenerator::new(&container, 7, GeneratorLimits::default());
+    for _ in 0..200 {
+        let (value, bytes) = generator.generate().expect("Generation failed");
+        Drawing::try_from_slice(&bytes).expect("Generated bytes should decode as a Drawing");
+        let result = deserialize_from_schema(&mut bytes.as_slice(), &container).expect("Deserialization failed");
+        assert_eq!(result, value);
+    }
+}
+
+#[test]
+fn limits_are_respected() {
+    let container: BorshSchemaContainer = schema_container_of::<Drawing>();
+    let limits = GeneratorLimits {
+        max_sequence_length: 2,
+        max_string_length: 3,
+        max_depth: 3,
+    };
+    let mut generator = ValueGenerator::new(&container, 11, limits);
+    for _ in 0..200 {
+        let value = generator.generate_value();
+        assert!(value["shapes"].as_array().unwrap().len() <= 2);
+        assert!(value["name"].as_str().unwrap().chars().count() <= 3);
+        assert_eq!(value["color"].as_array().unwrap().len(), 3);
+        // Drawing is depth 0 and its children depth 1, so grandchildren are at the limit and have no children
+        for child in value["children"].as_array().unwrap() {
+            for grandchild in child["children"].as_array().unwrap() {
+                assert_eq!(grandchild["children"].as_array().unwrap().len(), 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn enums_past_the_depth_limit_are_sized_once() {
+    // Big holds 40 levels of tuples of two of the next level, which would take 2^40 steps to size level by level
+    let mut definitions = BTreeMap::new();
+    definitions.insert("Root".to_string(), Definition::Enum {
+        tag_width: 1,
+        variants: vec![(0, "Big".to_string(), "Level0".to_string()), (1, "Small".to_string(), "u8".to_string())],
+    });
+    for level in 0..40 {
+        let next = format!("Level{}", level + 1);
+        definitions.insert(format!("Level{level}"), Definition::Tuple { elements: vec![next.clone(), next] });
+    }
+    definitions.insert("Level40".to_string(), Definition::Tuple { elements: vec!["u8".to_string()] });
+    let container = BorshSchemaContainer::new("Root".to_string(), definitions);
+
+    let limits = GeneratorLimits { max_depth: 0, ..GeneratorLimits::default() };
+    let mut generator = ValueGenerator::new(&container, 3, limits);
+    for _ in 0..100 {
+        assert!(generator.generate_value()["Small"].is_u64());
+    }
+}
+
+#[test]
+fn the_same_seed_gives_the_same_values() {
+    let container: BorshSchemaContainer = schema_container_of::<Drawing>();
+    let limits = GeneratorLimits::default();
+    assert_eq!(
+        generate_from_schema(&container, 3, &limits).expect("Generation failed"),
+        generate_from_schema(&container, 3, &limits).expect("Generation failed"),
+    );
+    assert_ne!(
+        generate_from_schema(&container, 3, &limits).expect("Generation failed"),
+        generate_from_schema(&container, 4, &limits).expect("Generation failed"),
+    );
+    assert_eq!(random_schema(9, 4), random_schema(9, 4));
+}
+
+#[test]
+fn adapters_round_trip_random_schemas() {
+    let limits = GeneratorLimits { max_depth: 6, ..GeneratorLimits::default() };
+    for seed in 0..300 {
+        let container = random_schema(seed, 4);
+        assert_eq!(validate_schema(&container), vec![], "seed {seed}");
+        let plan = SchemaPlan::compile(&container);
+
+        let mut generator = ValueGenerator::new(&container, seed, limits.clone());
+        for _ in 0..5 {
+            let (value, bytes) = generator.generate().unwrap_or_else(|error| panic!("seed {seed}: {error}"));
+
+            let mut buffer = bytes.as_slice();
+            let result = deserialize_from_schema(&mut buffer, &container).unwrap_or_else(|error| panic!("seed {seed}: {error}"));
+            assert_eq!(result, value, "seed {seed}");
+            assert!(buffer.is_empty(), "seed {seed}");
+            assert_eq!(plan.deserialize(&mut bytes.as_slice()).expect("Plan deserialization failed"), value, "seed {seed}");
+
+            let mut writer = Vec::new();
+            serialize_serde_json_to_borsh(&mut writer, &result, &container).expect("Serialization failed");
+            assert_eq!(writer, bytes, "seed {seed}");
+            let mut writer = Vec::new();
+            plan.serialize(&mut writer, &result).expect("Plan serialization failed");
+            assert_eq!(writer, bytes, "seed {seed}");
+        }
+    }
+}
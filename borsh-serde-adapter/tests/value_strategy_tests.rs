@@ -0,0 +1,35 @@
+#![cfg(feature = "proptest")]
+
+use std::sync::Arc;
+
+use borsh::schema::BorshSchemaContainer;
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize, schema_container_of};
+use borsh_serde_adapter::deserialize_adapter::deserialize_from_schema;
+use borsh_serde_adapter::serialize_adapter::serialize_serde_json_to_borsh;
+use borsh_serde_adapter::value_generator::{value_strategy, GeneratorLimits};
+use proptest::prelude::*;
+
+#[derive(Debug, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct Order {
+    id: u64,
+    customer: Option<String>,
+    lines: Vec<(u32, i64)>,
+    priority: i8,
+}
+
+fn order_schema() -> Arc<BorshSchemaContainer> {
+    Arc::new(schema_container_of::<Order>())
+}
+
+proptest! {
+    #[test]
+    fn generated_orders_round_trip((value, bytes) in value_strategy(order_schema(), GeneratorLimits::default())) {
+        let container = order_schema();
+        prop_assert!(Order::try_from_slice(&bytes).is_ok());
+        prop_assert_eq!(&deserialize_from_schema(&mut bytes.as_slice(), &container).unwrap(), &value);
+
+        let mut writer = Vec::new();
+        serialize_serde_json_to_borsh(&mut writer, &value, &container).unwrap();
+        prop_assert_eq!(writer, bytes);
+    }
+}